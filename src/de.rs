@@ -1,17 +1,22 @@
+use crate::document::Directives;
 use crate::error::{self, Error, ErrorImpl};
 use crate::libyaml::error::Mark;
 use crate::libyaml::parser::{MappingStart, Scalar, ScalarStyle, SequenceStart};
 use crate::libyaml::tag::Tag;
-use crate::loader::{Document, Loader};
+use crate::loader::{Document, Loader, SequenceLoader};
 use crate::path::Path;
+use crate::value::Value;
 use serde::de::value::StrDeserializer;
 use serde::de::{
     self, Deserialize, DeserializeOwned, DeserializeSeed, Expected, IgnoredAny, Unexpected, Visitor,
 };
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fmt;
 use std::io;
 use std::mem;
 use std::num::ParseIntError;
+use std::rc::Rc;
 use std::str;
 use std::sync::Arc;
 
@@ -55,8 +60,26 @@ type Result<T, E = Error> = std::result::Result<T, E>;
 /// ```
 pub struct Deserializer<'de> {
     progress: Progress<'de>,
+    options: Options<'de>,
 }
 
+// Settings that carry over from a multi-document deserializer to each of the
+// documents it produces.
+#[derive(Clone, Default)]
+struct Options<'de> {
+    scalar_transform: Option<Rc<RefCell<ScalarTransform<'de>>>>,
+    coerce_single_element_seq: bool,
+    strict_f32: bool,
+    numeric_bools: bool,
+    strict_scalar_types: bool,
+    max_documents: Option<usize>,
+    predefined_anchors: Option<Rc<HashMap<String, Rc<Value>>>>,
+    tag_shorthands: bool,
+    mark_empty_values: bool,
+}
+
+type ScalarTransform<'de> = dyn FnMut(&str, Option<&str>) -> Option<Value> + 'de;
+
 pub(crate) enum Progress<'de> {
     Str(&'de str),
     Slice(&'de [u8]),
@@ -70,13 +93,19 @@ impl<'de> Deserializer<'de> {
     /// Creates a YAML deserializer from a `&str`.
     pub fn from_str(s: &'de str) -> Self {
         let progress = Progress::Str(s);
-        Deserializer { progress }
+        Deserializer {
+            progress,
+            options: Options::default(),
+        }
     }
 
     /// Creates a YAML deserializer from a `&[u8]`.
     pub fn from_slice(v: &'de [u8]) -> Self {
         let progress = Progress::Slice(v);
-        Deserializer { progress }
+        Deserializer {
+            progress,
+            options: Options::default(),
+        }
     }
 
     /// Creates a YAML deserializer from an `io::Read`.
@@ -84,12 +113,272 @@ impl<'de> Deserializer<'de> {
     /// Reader-based deserializers do not support deserializing borrowed types
     /// like `&str`, since the `std::io::Read` trait has no non-copying methods
     /// -- everything it does involves copying bytes out of the data source.
+    ///
+    /// The whole input is read into memory before any of it is parsed. To
+    /// deserialize a long sequence without holding all of it, use
+    /// [`sequence_stream`].
     pub fn from_reader<R>(rdr: R) -> Self
     where
         R: io::Read + 'de,
     {
         let progress = Progress::Read(Box::new(rdr));
-        Deserializer { progress }
+        Deserializer {
+            progress,
+            options: Options::default(),
+        }
+    }
+
+    /// Installs a hook that sees every plain scalar before it is deserialized.
+    ///
+    /// The hook receives the text of the scalar and its tag, if it has one.
+    /// Returning `Some(value)` deserializes `value` in place of the scalar,
+    /// while returning `None` deserializes the scalar as usual. Quoted and
+    /// block scalars are not passed to the hook.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_yaml_ng::{Deserializer, Value};
+    ///
+    /// let input = "user: admin\npassword: secret:hunter2\n";
+    /// let de = Deserializer::from_str(input).scalar_transform(|scalar, _tag| {
+    ///     match scalar.strip_prefix("secret:") {
+    ///         Some(_) => Some(Value::String("<redacted>".to_owned())),
+    ///         None => None,
+    ///     }
+    /// });
+    ///
+    /// let value = Value::deserialize(de).unwrap();
+    /// assert_eq!(value["password"], "<redacted>");
+    /// ```
+    pub fn scalar_transform<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(&str, Option<&str>) -> Option<Value> + 'de,
+    {
+        self.options.scalar_transform = Some(Rc::new(RefCell::new(f)));
+        self
+    }
+
+    /// Accepts a sequence of exactly one scalar wherever a scalar is expected,
+    /// deserializing the element in place of the sequence.
+    ///
+    /// Sequences of any other length still fail to deserialize into a scalar.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_yaml_ng::Deserializer;
+    ///
+    /// let de = Deserializer::from_str("[x]").coerce_single_element_seq(true);
+    /// assert_eq!(String::deserialize(de).unwrap(), "x");
+    /// ```
+    pub fn coerce_single_element_seq(mut self, enable: bool) -> Self {
+        self.options.coerce_single_element_seq = enable;
+        self
+    }
+
+    /// Rejects numbers deserialized into `f32` that would lose precision.
+    ///
+    /// Floats are always parsed into `f32` directly, so that `0.1` becomes the
+    /// `f32` closest to 0.1, just as `"0.1".parse::<f32>()` would give. By
+    /// default a number with more significant digits than `f32` can hold, like
+    /// `0.123456789`, is rounded to the nearest `f32`. In strict mode it is an
+    /// error instead. Numbers too large for `f32` are also an error in strict
+    /// mode, instead of becoming infinity.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_yaml_ng::Deserializer;
+    ///
+    /// let de = Deserializer::from_str("0.1").strict_f32(true);
+    /// assert_eq!(f32::deserialize(de).unwrap(), 0.1);
+    ///
+    /// let de = Deserializer::from_str("0.123456789").strict_f32(true);
+    /// assert!(f32::deserialize(de).is_err());
+    /// ```
+    pub fn strict_f32(mut self, enable: bool) -> Self {
+        self.options.strict_f32 = enable;
+        self
+    }
+
+    /// Accepts the numbers `1` and `0` wherever a boolean is expected, as
+    /// `true` and `false`.
+    ///
+    /// Floats of the same value, like `1.0`, are accepted too. Other numbers
+    /// still fail to deserialize into a `bool`, as do all numbers by default.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_yaml_ng::Deserializer;
+    ///
+    /// let de = Deserializer::from_str("1").numeric_bools(true);
+    /// assert_eq!(bool::deserialize(de).unwrap(), true);
+    ///
+    /// let de = Deserializer::from_str("1");
+    /// assert!(bool::deserialize(de).is_err());
+    /// ```
+    pub fn numeric_bools(mut self, enable: bool) -> Self {
+        self.options.numeric_bools = enable;
+        self
+    }
+
+    /// Rejects plain scalars that resolve to something other than a string
+    /// wherever a string is expected.
+    ///
+    /// By default any scalar deserializes into a `String`, so `5`, `true` and
+    /// `~` become `"5"`, `"true"` and `"~"`. In strict mode those are errors,
+    /// and a string has to be quoted, written as a block scalar or tagged
+    /// `!!str` to be accepted. This applies to mapping keys too. Scalars
+    /// never deserialize into numbers or booleans unless they resolve to one,
+    /// whether or not this is enabled.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_yaml_ng::Deserializer;
+    ///
+    /// let de = Deserializer::from_str("'5'").strict_scalar_types(true);
+    /// assert_eq!(String::deserialize(de).unwrap(), "5");
+    ///
+    /// let de = Deserializer::from_str("5").strict_scalar_types(true);
+    /// assert!(String::deserialize(de).is_err());
+    /// ```
+    pub fn strict_scalar_types(mut self, enable: bool) -> Self {
+        self.options.strict_scalar_types = enable;
+        self
+    }
+
+    /// Limits the number of documents that iterating over this deserializer
+    /// will produce.
+    ///
+    /// Once `max` documents have been produced, a further document in the
+    /// input is an error instead, which ends the iteration.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_yaml_ng::Deserializer;
+    ///
+    /// let input = "--- 1\n--- 2\n--- 3\n";
+    /// let mut documents = Deserializer::from_str(input).max_documents(2);
+    ///
+    /// assert_eq!(u32::deserialize(documents.next().unwrap()).unwrap(), 1);
+    /// assert_eq!(u32::deserialize(documents.next().unwrap()).unwrap(), 2);
+    /// assert!(u32::deserialize(documents.next().unwrap()).is_err());
+    /// assert!(documents.next().is_none());
+    /// ```
+    pub fn max_documents(mut self, max: usize) -> Self {
+        self.options.max_documents = Some(max);
+        self
+    }
+
+    /// Supplies values for aliases to anchors that the document itself does
+    /// not define.
+    ///
+    /// An alias like `*common` resolves to the anchor of that name defined
+    /// earlier in the same document if there is one, and otherwise to the
+    /// entry `common` of `anchors`. Only an alias whose anchor is found in
+    /// neither place is an error.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_yaml_ng::{Deserializer, Value};
+    /// use std::collections::HashMap;
+    ///
+    /// let mut anchors = HashMap::new();
+    /// anchors.insert("region".to_owned(), Value::from("eu-west-1"));
+    ///
+    /// let de = Deserializer::from_str("bucket: {region: *region}").predefined_anchors(anchors);
+    /// let value = Value::deserialize(de).unwrap();
+    /// assert_eq!(value["bucket"]["region"], "eu-west-1");
+    /// ```
+    pub fn predefined_anchors(mut self, anchors: HashMap<String, Value>) -> Self {
+        let anchors = anchors
+            .into_iter()
+            .map(|(name, value)| (name, Rc::new(value)))
+            .collect();
+        self.options.predefined_anchors = Some(Rc::new(anchors));
+        self
+    }
+
+    /// Keeps tags written with a handle declared by a `%TAG` directive in that
+    /// shorthand form, like `!e!widget`, instead of resolving them to the full
+    /// tag, like `tag:example.com,2000:widget`.
+    ///
+    /// This is for writing a document back out together with its
+    /// [`directives`][Self::directives] through
+    /// [`Serializer::set_directives`][crate::Serializer::set_directives], which
+    /// expands the shorthand again. [`Document::from_str`][crate::Document::from_str]
+    /// turns it on.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_yaml_ng::{Deserializer, Value};
+    ///
+    /// let input = "%TAG !e! tag:example.com,2000:\n--- !e!widget x\n";
+    /// let document = Deserializer::from_str(input).tag_shorthands(true);
+    ///
+    /// match Value::deserialize(document).unwrap() {
+    ///     Value::Tagged(tagged) => assert_eq!(tagged.tag, "!e!widget"),
+    ///     _ => unreachable!(),
+    /// }
+    /// ```
+    pub fn tag_shorthands(mut self, enable: bool) -> Self {
+        self.options.tag_shorthands = enable;
+        self
+    }
+
+    /// Tells an empty value, like `key:`, apart from an explicit null, like
+    /// `key: null` or `key: ~`, when deserializing into a [`Value`].
+    ///
+    /// Both normally load as `Value::Null`. With this enabled, an empty value
+    /// that has no tag loads as a null tagged `!empty` instead, which
+    /// [`Value::is_empty_value`] recognizes and [`Value::merge`] treats like
+    /// an absent key. Only `Value` and other types that deserialize through
+    /// `deserialize_any` see the tag: an `Option` still deserializes an empty
+    /// value as `None`.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_yaml_ng::{Deserializer, Value};
+    ///
+    /// let de = Deserializer::from_str("a:\nb: null\n").mark_empty_values(true);
+    /// let value = Value::deserialize(de).unwrap();
+    /// assert!(value["a"].is_empty_value());
+    /// assert!(!value["b"].is_empty_value());
+    /// assert!(value["a"].is_null() && value["b"].is_null());
+    /// ```
+    pub fn mark_empty_values(mut self, enable: bool) -> Self {
+        self.options.mark_empty_values = enable;
+        self
+    }
+
+    /// Returns the `%YAML` and `%TAG` directives of this document.
+    ///
+    /// Directives are only known once the input has been parsed, so this is
+    /// available on the per-document deserializers produced by iterating over
+    /// a `Deserializer`, and returns `None` on any other.
+    ///
+    /// ```
+    /// use serde_yaml_ng::Deserializer;
+    ///
+    /// let input = "%TAG !e! tag:example.com,2000:\n--- !e!widget x\n";
+    /// let document = Deserializer::from_str(input).next().unwrap();
+    ///
+    /// let directives = document.directives().unwrap().clone();
+    /// assert_eq!(directives.tags[0].handle, "!e!");
+    /// assert_eq!(directives.tags[0].prefix, "tag:example.com,2000:");
+    /// ```
+    pub fn directives(&self) -> Option<&Directives> {
+        match &self.progress {
+            Progress::Document(document) => Some(&document.directives),
+            _ => None,
+        }
+    }
+
+    // Whether this document was written with an explicit `---` and `...`,
+    // known under the same conditions as the directives.
+    pub(crate) fn explicit_markers(&self) -> Option<(bool, bool)> {
+        match &self.progress {
+            Progress::Document(document) => Some((document.explicit_start, document.explicit_end)),
+            _ => None,
+        }
     }
 
     fn de<T>(
@@ -98,8 +387,9 @@ impl<'de> Deserializer<'de> {
     ) -> Result<T> {
         let mut pos = 0;
         let mut jumpcount = 0;
+        let Deserializer { progress, options } = self;
 
-        match self.progress {
+        match progress {
             Progress::Iterable(_) => return Err(error::new(ErrorImpl::MoreThanOneDocument)),
             Progress::Document(document) => {
                 let t = f(&mut DeserializerFromEvents {
@@ -109,6 +399,8 @@ impl<'de> Deserializer<'de> {
                     path: Path::Root,
                     remaining_depth: 128,
                     current_enum: None,
+                    transform_declined: None,
+                    options: &options,
                 })?;
                 if let Some(parse_error) = document.error {
                     return Err(error::shared(parse_error));
@@ -118,7 +410,11 @@ impl<'de> Deserializer<'de> {
             _ => {}
         }
 
-        let mut loader = Loader::new(self.progress)?;
+        let mut loader = Loader::new(
+            progress,
+            options.predefined_anchors.clone(),
+            options.tag_shorthands,
+        )?;
         let document = match loader.next_document() {
             Some(document) => document,
             None => return Err(error::new(ErrorImpl::EndOfStream)),
@@ -130,6 +426,8 @@ impl<'de> Deserializer<'de> {
             path: Path::Root,
             remaining_depth: 128,
             current_enum: None,
+            transform_declined: None,
+            options: &options,
         })?;
         if let Some(parse_error) = document.error {
             return Err(error::shared(parse_error));
@@ -148,15 +446,32 @@ impl<'de> Iterator for Deserializer<'de> {
     fn next(&mut self) -> Option<Self> {
         match &mut self.progress {
             Progress::Iterable(loader) => {
+                let limit = self
+                    .options
+                    .max_documents
+                    .filter(|max| loader.document_count >= *max);
+                if let Some(max) = limit {
+                    if !loader.has_next_document() {
+                        return None;
+                    }
+                    loader.close();
+                    let err = error::new(ErrorImpl::DocumentLimitExceeded(max)).shared();
+                    return Some(Deserializer {
+                        progress: Progress::Fail(err),
+                        options: self.options.clone(),
+                    });
+                }
                 let document = loader.next_document()?;
                 return Some(Deserializer {
                     progress: Progress::Document(document),
+                    options: self.options.clone(),
                 });
             }
             Progress::Document(_) => return None,
             Progress::Fail(err) => {
                 return Some(Deserializer {
                     progress: Progress::Fail(Arc::clone(err)),
+                    options: self.options.clone(),
                 });
             }
             _ => {}
@@ -164,7 +479,8 @@ impl<'de> Iterator for Deserializer<'de> {
 
         let dummy = Progress::Str("");
         let input = mem::replace(&mut self.progress, dummy);
-        match Loader::new(input) {
+        let predefined_anchors = self.options.predefined_anchors.clone();
+        match Loader::new(input, predefined_anchors, self.options.tag_shorthands) {
             Ok(loader) => {
                 self.progress = Progress::Iterable(loader);
                 self.next()
@@ -174,6 +490,7 @@ impl<'de> Iterator for Deserializer<'de> {
                 self.progress = Progress::Fail(Arc::clone(&fail));
                 Some(Deserializer {
                     progress: Progress::Fail(fail),
+                    options: self.options.clone(),
                 })
             }
         }
@@ -419,6 +736,9 @@ impl<'de> de::Deserializer<'de> for Deserializer<'de> {
 #[derive(Debug)]
 pub(crate) enum Event<'de> {
     Alias(usize),
+    // An alias to a value from outside of the document, such as one of the
+    // predefined anchors.
+    Value(Rc<Value>),
     Scalar(Scalar<'de>),
     SequenceStart(SequenceStart),
     SequenceEnd,
@@ -434,6 +754,11 @@ struct DeserializerFromEvents<'de, 'document> {
     path: Path<'document>,
     remaining_depth: u8,
     current_enum: Option<CurrentEnum<'document>>,
+    // Position of a plain scalar that the scalar_transform hook has already
+    // seen and left alone, so that it is not called a second time when
+    // deserialize_option hands the same scalar on to the inner type.
+    transform_declined: Option<usize>,
+    options: &'document Options<'de>,
 }
 
 #[derive(Copy, Clone)]
@@ -469,6 +794,63 @@ impl<'de, 'document> DeserializerFromEvents<'de, 'document> {
         })
     }
 
+    // A value to deserialize in place of the next event: the value of a
+    // predefined anchor, or the result of the scalar_transform hook. The hook
+    // sees each plain scalar once, and the scalar is consumed only if the
+    // hook replaces it. A tagged scalar is revisited as the content of its
+    // enum variant, but the hook has already seen it by then.
+    fn substitute_value(&mut self) -> Result<Option<(Value, Mark)>> {
+        if let (Event::Value(value), mark) = self.peek_event_mark()? {
+            let value = Value::clone(value);
+            self.next_event()?;
+            return Ok(Some((value, mark)));
+        }
+        let transform = match &self.options.scalar_transform {
+            Some(transform)
+                if self.current_enum.is_none() && self.transform_declined != Some(*self.pos) =>
+            {
+                transform
+            }
+            _ => return Ok(None),
+        };
+        let (event, mark) = self.peek_event_mark()?;
+        let scalar = match event {
+            Event::Scalar(scalar) if scalar.style == ScalarStyle::Plain => scalar,
+            _ => return Ok(None),
+        };
+        let value = match str::from_utf8(&scalar.value) {
+            Ok(value) => value,
+            Err(_) => return Ok(None),
+        };
+        let tag = scalar.tag.as_ref().and_then(|tag| str::from_utf8(tag).ok());
+        let transformed = (transform.borrow_mut())(value, tag);
+        if transformed.is_some() {
+            self.next_event()?;
+        } else {
+            self.transform_declined = Some(*self.pos);
+        }
+        Ok(transformed.map(|value| (value, mark)))
+    }
+
+    // With coerce_single_element_seq, steps into a sequence holding exactly one
+    // scalar so that the scalar is deserialized in place of the sequence. The
+    // caller consumes the SequenceEnd afterward.
+    fn enter_single_element_seq(&mut self) -> bool {
+        if !self.options.coerce_single_element_seq {
+            return false;
+        }
+        match self.document.events.get(*self.pos..) {
+            Some(
+                [(Event::SequenceStart(_), _), (Event::Scalar(_) | Event::Alias(_), _), (Event::SequenceEnd, _), ..],
+            ) => {
+                *self.pos += 1;
+                self.current_enum = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
     fn jump<'anchor>(
         &'anchor mut self,
         pos: &'anchor mut usize,
@@ -487,6 +869,8 @@ impl<'de, 'document> DeserializerFromEvents<'de, 'document> {
                     path: Path::Alias { parent: &self.path },
                     remaining_depth: self.remaining_depth,
                     current_enum: None,
+                    transform_declined: None,
+                    options: self.options,
                 })
             }
             None => panic!("unresolved alias: {}", *pos),
@@ -503,7 +887,7 @@ impl<'de, 'document> DeserializerFromEvents<'de, 'document> {
 
         loop {
             match self.next_event()? {
-                Event::Alias(_) | Event::Scalar(_) | Event::Void => {}
+                Event::Alias(_) | Event::Value(_) | Event::Scalar(_) | Event::Void => {}
                 Event::SequenceStart(_) => {
                     stack.push(Nest::Sequence);
                 }
@@ -672,6 +1056,8 @@ impl<'de, 'document, 'seq> de::SeqAccess<'de> for SeqAccess<'de, 'document, 'seq
                     },
                     remaining_depth: self.de.remaining_depth,
                     current_enum: None,
+                    transform_declined: None,
+                    options: self.de.options,
                 };
                 self.len += 1;
                 seed.deserialize(&mut element_de).map(Some)
@@ -732,6 +1118,8 @@ impl<'de, 'document, 'map> de::MapAccess<'de> for MapAccess<'de, 'document, 'map
             },
             remaining_depth: self.de.remaining_depth,
             current_enum: None,
+            transform_declined: None,
+            options: self.de.options,
         };
         seed.deserialize(&mut value_de)
     }
@@ -763,6 +1151,8 @@ impl<'de, 'document, 'variant> de::EnumAccess<'de> for EnumAccess<'de, 'document
                 name: self.name,
                 tag: self.tag,
             }),
+            transform_declined: None,
+            options: self.de.options,
         };
         Ok((variant, visitor))
     }
@@ -920,6 +1310,12 @@ fn parse_borrowed_str<'de>(
     None
 }
 
+// Whether the scalar is an empty value, like `key:`, as opposed to an
+// explicit null or an empty string.
+fn is_empty_value(scalar: &Scalar) -> bool {
+    scalar.tag.is_none() && scalar.style == ScalarStyle::Plain && scalar.value.is_empty()
+}
+
 fn parse_null(scalar: &[u8]) -> Option<()> {
     match scalar {
         b"null" | b"Null" | b"NULL" | b"~" => Some(()),
@@ -935,10 +1331,35 @@ fn parse_bool(scalar: &str) -> Option<bool> {
     }
 }
 
+// YAML 1.1 allows underscores between the digits of an integer, as in
+// `1_000` or `0xFF_FF`. Returns the integer without them, or None if it has
+// none, one of them is not between digits, or the rest are not all digits,
+// which is checked first so that other scalars are not copied.
+fn strip_digit_separators(scalar: &str) -> Option<String> {
+    if !scalar.contains('_') {
+        return None;
+    }
+    let unsigned = scalar.strip_prefix(['+', '-']).unwrap_or(scalar);
+    let (digits, radix) = [("0x", 16), ("0o", 8), ("0b", 2)]
+        .iter()
+        .find_map(|&(prefix, radix)| Some((unsigned.strip_prefix(prefix)?, radix)))
+        .unwrap_or((unsigned, 10));
+    if digits.starts_with('_') || digits.ends_with('_') {
+        return None;
+    }
+    if !digits.chars().all(|ch| ch == '_' || ch.is_digit(radix)) {
+        return None;
+    }
+    Some(scalar.replace('_', ""))
+}
+
 fn parse_unsigned_int<T>(
     scalar: &str,
     from_str_radix: fn(&str, radix: u32) -> Result<T, ParseIntError>,
 ) -> Option<T> {
+    if let Some(scalar) = strip_digit_separators(scalar) {
+        return parse_unsigned_int(&scalar, from_str_radix);
+    }
     let unpositive = scalar.strip_prefix('+').unwrap_or(scalar);
     if let Some(rest) = unpositive.strip_prefix("0x") {
         if rest.starts_with(['+', '-']) {
@@ -977,6 +1398,9 @@ fn parse_signed_int<T>(
     scalar: &str,
     from_str_radix: fn(&str, radix: u32) -> Result<T, ParseIntError>,
 ) -> Option<T> {
+    if let Some(scalar) = strip_digit_separators(scalar) {
+        return parse_signed_int(&scalar, from_str_radix);
+    }
     let unpositive = if let Some(unpositive) = scalar.strip_prefix('+') {
         if unpositive.starts_with(['+', '-']) {
             return None;
@@ -1037,6 +1461,9 @@ fn parse_negative_int<T>(
     scalar: &str,
     from_str_radix: fn(&str, radix: u32) -> Result<T, ParseIntError>,
 ) -> Option<T> {
+    if let Some(scalar) = strip_digit_separators(scalar) {
+        return parse_negative_int(&scalar, from_str_radix);
+    }
     if let Some(rest) = scalar.strip_prefix("-0x") {
         let negative = format!("-{}", rest);
         if let Ok(int) = from_str_radix(&negative, 16) {
@@ -1087,6 +1514,23 @@ pub(crate) fn parse_f64(scalar: &str) -> Option<f64> {
     None
 }
 
+// Parses straight to f32 rather than rounding twice by way of f64. Also
+// returns whether the f32 is as precise as an f64 would have been, meaning its
+// shortest decimal representation reads back as the same f64.
+fn parse_f32(scalar: &str) -> Option<(f32, bool)> {
+    let double = parse_f64(scalar)?;
+    if double.is_nan() {
+        return Some((f32::NAN.copysign(1.0), true));
+    }
+    if double.is_infinite() {
+        return Some((double as f32, true));
+    }
+    let unpositive = scalar.strip_prefix('+').unwrap_or(scalar);
+    let float = unpositive.parse::<f32>().unwrap_or(double as f32);
+    let exact = float.to_string().parse::<f64>() == Ok(double);
+    Some((float, exact))
+}
+
 pub(crate) fn digits_but_not_number(scalar: &str) -> bool {
     // Leading zero(s) followed by numeric characters is a string according to
     // the YAML 1.2 spec. https://yaml.org/spec/1.2/spec.html#id2761292
@@ -1094,6 +1538,13 @@ pub(crate) fn digits_but_not_number(scalar: &str) -> bool {
     scalar.len() > 1 && scalar.starts_with('0') && scalar[1..].bytes().all(|b| b.is_ascii_digit())
 }
 
+fn decimal_int_overflow(scalar: &str) -> bool {
+    // Only reached after visit_int has failed, so any decimal integer here is
+    // out of range of both u128 and i128.
+    let digits = scalar.strip_prefix(['-', '+']).unwrap_or(scalar);
+    !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
 pub(crate) fn visit_int<'de, V>(visitor: V, v: &str) -> Result<Result<V::Value>, V>
 where
     V: Visitor<'de>,
@@ -1132,7 +1583,9 @@ where
         Ok(result) => return result,
         Err(visitor) => visitor,
     };
-    if !digits_but_not_number(v) {
+    // Integers too big even for 128 bits stay strings rather than silently
+    // losing precision as a float.
+    if !digits_but_not_number(v) && !decimal_int_overflow(v) {
         if let Some(float) = parse_f64(v) {
             return visitor.visit_f64(float);
         }
@@ -1144,6 +1597,53 @@ where
     }
 }
 
+// Whether the scalar is a string rather than a null, boolean or number.
+fn resolves_to_str(scalar: &Scalar, tagged_already: bool) -> bool {
+    struct IsStr;
+
+    impl<'de> Visitor<'de> for IsStr {
+        type Value = bool;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("any scalar")
+        }
+
+        fn visit_bool<E>(self, _v: bool) -> Result<bool, E> {
+            Ok(false)
+        }
+
+        fn visit_i64<E>(self, _v: i64) -> Result<bool, E> {
+            Ok(false)
+        }
+
+        fn visit_i128<E>(self, _v: i128) -> Result<bool, E> {
+            Ok(false)
+        }
+
+        fn visit_u64<E>(self, _v: u64) -> Result<bool, E> {
+            Ok(false)
+        }
+
+        fn visit_u128<E>(self, _v: u128) -> Result<bool, E> {
+            Ok(false)
+        }
+
+        fn visit_f64<E>(self, _v: f64) -> Result<bool, E> {
+            Ok(false)
+        }
+
+        fn visit_str<E>(self, _v: &str) -> Result<bool, E> {
+            Ok(true)
+        }
+
+        fn visit_unit<E>(self) -> Result<bool, E> {
+            Ok(false)
+        }
+    }
+
+    visit_scalar(IsStr, scalar, tagged_already).unwrap_or(false)
+}
+
 fn is_plain_or_tagged_literal_scalar(
     expected: &str,
     scalar: &Scalar,
@@ -1172,7 +1672,7 @@ fn invalid_type(event: &Event, exp: &dyn Expected) -> Error {
     }
 
     match event {
-        Event::Alias(_) => unreachable!(),
+        Event::Alias(_) | Event::Value(_) => unreachable!(),
         Event::Scalar(scalar) => {
             let get_type = InvalidType { exp };
             // TODO: I'm annotating this because github keeps emailing me every 8 hours that the
@@ -1214,6 +1714,11 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
     where
         V: Visitor<'de>,
     {
+        if let Some((value, mark)) = self.substitute_value()? {
+            return value
+                .deserialize_any(visitor)
+                .map_err(|err| error::fix_mark(err, mark, self.path));
+        }
         let tagged_already = self.current_enum.is_some();
         let (next, mark) = self.next_event_mark()?;
         fn enum_tag(tag: &Option<Tag>, tagged_already: bool) -> Option<&str> {
@@ -1225,7 +1730,11 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
         loop {
             match next {
                 Event::Alias(mut pos) => break self.jump(&mut pos)?.deserialize_any(visitor),
+                Event::Value(_) => unreachable!(),
                 Event::Scalar(scalar) => {
+                    if self.options.mark_empty_values && is_empty_value(scalar) {
+                        break Value::empty_value().deserialize_any(visitor);
+                    }
                     if let Some(tag) = enum_tag(&scalar.tag, tagged_already) {
                         *self.pos -= 1;
                         break visitor.visit_enum(EnumAccess {
@@ -1272,6 +1781,16 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
     where
         V: Visitor<'de>,
     {
+        if let Some((value, mark)) = self.substitute_value()? {
+            return value
+                .deserialize_bool(visitor)
+                .map_err(|err| error::fix_mark(err, mark, self.path));
+        }
+        if self.enter_single_element_seq() {
+            let value = (&mut *self).deserialize_bool(visitor)?;
+            self.next_event()?;
+            return Ok(value);
+        }
         let tagged_already = self.current_enum.is_some();
         let (next, mark) = self.next_event_mark()?;
         loop {
@@ -1284,6 +1803,12 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
                         if let Some(boolean) = parse_bool(value) {
                             break visitor.visit_bool(boolean);
                         }
+                        if self.options.numeric_bools {
+                            let number = parse_f64(value);
+                            if number == Some(1.0) || number == Some(0.0) {
+                                break visitor.visit_bool(number == Some(1.0));
+                            }
+                        }
                     }
                 }
                 _ => {}
@@ -1318,6 +1843,16 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
     where
         V: Visitor<'de>,
     {
+        if let Some((value, mark)) = self.substitute_value()? {
+            return value
+                .deserialize_i64(visitor)
+                .map_err(|err| error::fix_mark(err, mark, self.path));
+        }
+        if self.enter_single_element_seq() {
+            let value = (&mut *self).deserialize_i64(visitor)?;
+            self.next_event()?;
+            return Ok(value);
+        }
         let tagged_already = self.current_enum.is_some();
         let (next, mark) = self.next_event_mark()?;
         loop {
@@ -1343,6 +1878,16 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
     where
         V: Visitor<'de>,
     {
+        if let Some((value, mark)) = self.substitute_value()? {
+            return value
+                .deserialize_i128(visitor)
+                .map_err(|err| error::fix_mark(err, mark, self.path));
+        }
+        if self.enter_single_element_seq() {
+            let value = (&mut *self).deserialize_i128(visitor)?;
+            self.next_event()?;
+            return Ok(value);
+        }
         let tagged_already = self.current_enum.is_some();
         let (next, mark) = self.next_event_mark()?;
         loop {
@@ -1389,6 +1934,16 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
     where
         V: Visitor<'de>,
     {
+        if let Some((value, mark)) = self.substitute_value()? {
+            return value
+                .deserialize_u64(visitor)
+                .map_err(|err| error::fix_mark(err, mark, self.path));
+        }
+        if self.enter_single_element_seq() {
+            let value = (&mut *self).deserialize_u64(visitor)?;
+            self.next_event()?;
+            return Ok(value);
+        }
         let tagged_already = self.current_enum.is_some();
         let (next, mark) = self.next_event_mark()?;
         loop {
@@ -1414,6 +1969,16 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
     where
         V: Visitor<'de>,
     {
+        if let Some((value, mark)) = self.substitute_value()? {
+            return value
+                .deserialize_u128(visitor)
+                .map_err(|err| error::fix_mark(err, mark, self.path));
+        }
+        if self.enter_single_element_seq() {
+            let value = (&mut *self).deserialize_u128(visitor)?;
+            self.next_event()?;
+            return Ok(value);
+        }
         let tagged_already = self.current_enum.is_some();
         let (next, mark) = self.next_event_mark()?;
         loop {
@@ -1439,13 +2004,57 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
     where
         V: Visitor<'de>,
     {
-        self.deserialize_f64(visitor)
+        if let Some((value, mark)) = self.substitute_value()? {
+            return value
+                .deserialize_f32(visitor)
+                .map_err(|err| error::fix_mark(err, mark, self.path));
+        }
+        if self.enter_single_element_seq() {
+            let value = (&mut *self).deserialize_f32(visitor)?;
+            self.next_event()?;
+            return Ok(value);
+        }
+        let tagged_already = self.current_enum.is_some();
+        let (next, mark) = self.next_event_mark()?;
+        loop {
+            match next {
+                Event::Alias(mut pos) => break self.jump(&mut pos)?.deserialize_f32(visitor),
+                Event::Scalar(scalar)
+                    if is_plain_or_tagged_literal_scalar(Tag::FLOAT, scalar, tagged_already) =>
+                {
+                    if let Ok(value) = str::from_utf8(&scalar.value) {
+                        if let Some((float, exact)) = parse_f32(value) {
+                            if self.options.strict_f32 && !exact {
+                                break Err(de::Error::invalid_value(
+                                    Unexpected::Str(value),
+                                    &"a float that f32 can hold without losing precision",
+                                ));
+                            }
+                            break visitor.visit_f32(float);
+                        }
+                    }
+                }
+                _ => {}
+            }
+            break Err(invalid_type(next, &visitor));
+        }
+        .map_err(|err| error::fix_mark(err, mark, self.path))
     }
 
     fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
+        if let Some((value, mark)) = self.substitute_value()? {
+            return value
+                .deserialize_f64(visitor)
+                .map_err(|err| error::fix_mark(err, mark, self.path));
+        }
+        if self.enter_single_element_seq() {
+            let value = (&mut *self).deserialize_f64(visitor)?;
+            self.next_event()?;
+            return Ok(value);
+        }
         let tagged_already = self.current_enum.is_some();
         let (next, mark) = self.next_event_mark()?;
         loop {
@@ -1478,8 +2087,24 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
     where
         V: Visitor<'de>,
     {
+        if let Some((value, mark)) = self.substitute_value()? {
+            return value
+                .deserialize_str(visitor)
+                .map_err(|err| error::fix_mark(err, mark, self.path));
+        }
+        if self.enter_single_element_seq() {
+            let value = (&mut *self).deserialize_str(visitor)?;
+            self.next_event()?;
+            return Ok(value);
+        }
+        let tagged_already = self.current_enum.is_some();
         let (next, mark) = self.next_event_mark()?;
         match next {
+            Event::Scalar(scalar)
+                if self.options.strict_scalar_types && !resolves_to_str(scalar, tagged_already) =>
+            {
+                Err(invalid_type(next, &visitor))
+            }
             Event::Scalar(scalar) => {
                 if let Ok(v) = str::from_utf8(&scalar.value) {
                     if let Some(borrowed) = parse_borrowed_str(v, scalar.repr, scalar.style) {
@@ -1523,11 +2148,17 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
     where
         V: Visitor<'de>,
     {
+        if let Some((value, mark)) = self.substitute_value()? {
+            return value
+                .deserialize_option(visitor)
+                .map_err(|err| error::fix_mark(err, mark, self.path));
+        }
         let is_some = match self.peek_event()? {
             Event::Alias(mut pos) => {
                 *self.pos += 1;
                 return self.jump(&mut pos)?.deserialize_option(visitor);
             }
+            Event::Value(_) => unreachable!(),
             Event::Scalar(scalar) => {
                 let tagged_already = self.current_enum.is_some();
                 if scalar.style != ScalarStyle::Plain {
@@ -1569,6 +2200,11 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
     where
         V: Visitor<'de>,
     {
+        if let Some((value, mark)) = self.substitute_value()? {
+            return value
+                .deserialize_unit(visitor)
+                .map_err(|err| error::fix_mark(err, mark, self.path));
+        }
         let tagged_already = self.current_enum.is_some();
         let (next, mark) = self.next_event_mark()?;
         match next {
@@ -1618,6 +2254,11 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
     where
         V: Visitor<'de>,
     {
+        if let Some((value, mark)) = self.substitute_value()? {
+            return value
+                .deserialize_seq(visitor)
+                .map_err(|err| error::fix_mark(err, mark, self.path));
+        }
         let (next, mark) = self.next_event_mark()?;
         match next {
             Event::Alias(mut pos) => self.jump(&mut pos)?.deserialize_seq(visitor),
@@ -1666,6 +2307,11 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
     where
         V: Visitor<'de>,
     {
+        if let Some((value, mark)) = self.substitute_value()? {
+            return value
+                .deserialize_map(visitor)
+                .map_err(|err| error::fix_mark(err, mark, self.path));
+        }
         let (next, mark) = self.next_event_mark()?;
         match next {
             Event::Alias(mut pos) => self.jump(&mut pos)?.deserialize_map(visitor),
@@ -1716,6 +2362,11 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
     where
         V: Visitor<'de>,
     {
+        if let Some((value, mark)) = self.substitute_value()? {
+            return value
+                .deserialize_enum(name, variants, visitor)
+                .map_err(|err| error::fix_mark(err, mark, self.path));
+        }
         let (next, mark) = self.peek_event_mark()?;
         loop {
             if let Some(current_enum) = self.current_enum {
@@ -1743,6 +2394,7 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
                     self.jump(&mut pos)?
                         .deserialize_enum(name, variants, visitor)
                 }
+                Event::Value(_) => unreachable!(),
                 Event::Scalar(scalar) => {
                     if let Some(tag) = parse_tag(&scalar.tag) {
                         return visitor.visit_enum(EnumAccess {
@@ -1817,6 +2469,86 @@ where
     T::deserialize(Deserializer::from_str(s))
 }
 
+/// Deserialize each document of a string of YAML text, which may contain
+/// several separated by `---`, as an instance of type `T`.
+///
+/// Every document yields one item. An error in one document, including a
+/// syntax error, is returned as that document's item, and the documents after
+/// it are still read: after a syntax error, reading resumes at the next line
+/// that starts with `---`.
+///
+/// ```
+/// use serde_yaml_ng::Value;
+///
+/// let yaml = "name: a\n---\n[unclosed\n---\nname: c\n";
+/// let documents: Vec<_> = serde_yaml_ng::from_str_multi::<Value>(yaml).collect();
+/// assert_eq!(documents.len(), 3);
+/// assert_eq!(documents[0].as_ref().unwrap()["name"], "a");
+/// assert!(documents[1].is_err());
+/// assert_eq!(documents[2].as_ref().unwrap()["name"], "c");
+/// ```
+pub fn from_str_multi<'de, T>(s: &'de str) -> impl Iterator<Item = Result<T>> + 'de
+where
+    T: Deserialize<'de> + 'de,
+{
+    Deserializer::from_str(s).map(T::deserialize)
+}
+
+/// Parses as much of a string of YAML as possible, collecting every syntax
+/// error rather than stopping at the first.
+///
+/// This is meant for tools like editors and linters, which want to report all
+/// the problems in a file at once. An error only stops the parsing of the
+/// document that contains it: parsing resumes at the next line that starts
+/// with `---`, just as when iterating over a [`Deserializer`]. The locations
+/// of the errors are relative to the whole input.
+///
+/// The returned value is `None` if no document could be parsed, the document
+/// itself if exactly one could, and otherwise a sequence of all the documents
+/// that were parsed, in order. A single document that is a sequence therefore
+/// looks the same as several documents. Callers that need to tell them apart
+/// can iterate over a [`Deserializer`] instead, which yields the same
+/// documents and errors one at a time.
+///
+/// ```
+/// use serde_yaml_ng::Value;
+///
+/// let input = "a: [1, 2\n---\nb: 2\n---\nc: d: e\n";
+/// let (value, errors) = serde_yaml_ng::parse_lenient(input);
+///
+/// assert_eq!(value, Some(serde_yaml_ng::from_str::<Value>("b: 2").unwrap()));
+/// assert_eq!(errors.len(), 2);
+/// assert_eq!(errors[0].location().unwrap().line(), 2);
+/// assert_eq!(errors[1].location().unwrap().line(), 5);
+/// ```
+pub fn parse_lenient(s: &str) -> (Option<Value>, Vec<Error>) {
+    let mut values = Vec::new();
+    let mut errors = Vec::new();
+
+    // Loading from a string cannot fail up front.
+    let mut loader = Loader::new(Progress::Str(s), None, false).unwrap();
+    while let Some(document) = loader.next_document() {
+        if let Some(parse_error) = document.error {
+            errors.push(error::shared(parse_error));
+            continue;
+        }
+        if let [(Event::Void, _)] = document.events.as_slice() {
+            continue;
+        }
+        match deserialize_at(&document, 0) {
+            Ok(value) => values.push(value),
+            Err(err) => errors.push(err),
+        }
+    }
+
+    let value = match values.len() {
+        0 => None,
+        1 => values.pop(),
+        _ => Some(Value::Sequence(values)),
+    };
+    (value, errors)
+}
+
 /// Deserialize an instance of type `T` from an IO stream of YAML.
 ///
 /// This conversion can fail if the structure of the Value does not match the
@@ -1826,6 +2558,8 @@ where
 /// is wrong with the data, for example required struct fields are missing from
 /// the YAML map or some number is too big to fit in the expected primitive
 /// type.
+///
+/// The whole stream is read into memory before any of it is parsed.
 pub fn from_reader<R, T>(rdr: R) -> Result<T>
 where
     R: io::Read,
@@ -1834,6 +2568,116 @@ where
     T::deserialize(Deserializer::from_reader(rdr))
 }
 
+/// Deserialize an instance of type `T` from an IO stream of gzip-compressed
+/// YAML.
+///
+/// The stream is decompressed as it is read, so there is no need to wrap it
+/// in a decoder first. This function is available with the `gzip` feature.
+///
+/// This conversion can fail if the stream is not valid gzip, or for any of the
+/// reasons that [`from_reader`] can fail.
+#[cfg(feature = "gzip")]
+pub fn from_gz_reader<R, T>(rdr: R) -> Result<T>
+where
+    R: io::Read,
+    T: DeserializeOwned,
+{
+    from_reader(flate2::read::MultiGzDecoder::new(rdr))
+}
+
+/// Deserialize the elements of a YAML sequence from an IO stream one at a
+/// time, as instances of type `T`.
+///
+/// The stream must hold a single document whose top level is a sequence,
+/// written in either block or flow style. Each element is parsed and
+/// deserialized only when the iterator reaches it, and is not kept once it
+/// has been yielded, so the whole sequence never has to fit in memory. The
+/// stream is read from as parsing goes along.
+///
+/// An element that fails to deserialize into `T` yields an error, and the
+/// elements after it are still read. A syntax error or an error from the
+/// stream itself ends the iteration. If the document is not a sequence, it
+/// is deserialized as a `Vec<T>` all at once, just as [`from_reader`] would.
+///
+/// ```
+/// let yaml = "- 1\n- 2\n- three\n";
+/// let mut elements = serde_yaml_ng::sequence_stream::<_, u32>(yaml.as_bytes());
+/// assert_eq!(elements.next().unwrap().unwrap(), 1);
+/// assert_eq!(elements.next().unwrap().unwrap(), 2);
+/// assert!(elements.next().unwrap().is_err());
+/// assert!(elements.next().is_none());
+/// ```
+pub fn sequence_stream<'de, R, T>(rdr: R) -> impl Iterator<Item = Result<T>> + 'de
+where
+    R: io::Read + 'de,
+    T: DeserializeOwned + 'de,
+{
+    SequenceStream {
+        loader: SequenceLoader::new(Box::new(rdr)),
+        rest: Vec::new().into_iter(),
+    }
+}
+
+struct SequenceStream<'de, T> {
+    loader: SequenceLoader<'de>,
+    // The elements of a document that was not a sequence, which have to be
+    // read all at once.
+    rest: std::vec::IntoIter<T>,
+}
+
+impl<'de, T> Iterator for SequenceStream<'de, T>
+where
+    T: DeserializeOwned,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        if let Some(element) = self.rest.next() {
+            return Some(Ok(element));
+        }
+        let element = match self.loader.next_element()? {
+            Ok(element) => element,
+            Err(err) => return Some(Err(err)),
+        };
+        if element.whole_document {
+            return match deserialize_at::<Vec<T>>(&element.document, 0) {
+                Ok(elements) => {
+                    self.rest = elements.into_iter();
+                    self.next()
+                }
+                Err(err) => Some(Err(err)),
+            };
+        }
+        let result = deserialize_at(&element.document, 0);
+        // Later elements may refer to the anchors of this one, which is about
+        // to be dropped.
+        for (name, index) in element.anchors {
+            if let Ok(value) = deserialize_at(&element.document, index) {
+                self.loader.anchors.insert(name, Rc::new(value));
+            }
+        }
+        Some(result)
+    }
+}
+
+// Deserializes the node that starts at event `pos` of the document.
+fn deserialize_at<'de, T>(document: &Document<'de>, mut pos: usize) -> Result<T>
+where
+    T: Deserialize<'de>,
+{
+    let mut jumpcount = 0;
+    T::deserialize(&mut DeserializerFromEvents {
+        document,
+        pos: &mut pos,
+        jumpcount: &mut jumpcount,
+        path: Path::Root,
+        remaining_depth: 128,
+        current_enum: None,
+        transform_declined: None,
+        options: &Options::default(),
+    })
+}
+
 /// Deserialize an instance of type `T` from bytes of YAML text.
 ///
 /// This conversion can fail if the structure of the Value does not match the
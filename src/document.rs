@@ -0,0 +1,154 @@
+//! Document-level metadata, such as the `%YAML` and `%TAG` directives.
+
+use crate::de::Deserializer;
+use crate::error::{self, ErrorImpl, Result};
+use crate::ser::Serializer;
+use crate::value::Value;
+use serde::{Deserialize, Serialize};
+
+/// A single YAML document together with the way it was written: its
+/// directives and whether it has explicit `---` and `...` markers.
+///
+/// [`Document::from_str`] and [`Document::to_string`] preserve all of these.
+/// Serializing or deserializing a `Document` through any other serde
+/// serializer or deserializer only handles the [`value`][Document::value],
+/// and the metadata is left at its default.
+///
+/// ```
+/// use serde_yaml_ng::Document;
+///
+/// let yaml = "%YAML 1.2\n---\nname: example\n...\n";
+/// let document = Document::from_str(yaml).unwrap();
+///
+/// assert_eq!(document.value["name"], "example");
+/// assert_eq!(document.directives.version.unwrap().minor, 2);
+/// assert!(document.explicit_end);
+/// assert_eq!(document.to_string().unwrap(), yaml);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Document {
+    /// The root of the document.
+    pub value: Value,
+    /// The `%YAML` and `%TAG` directives before the document.
+    pub directives: Directives,
+    /// Whether the document starts with `---`. This is always written when
+    /// there are directives, regardless of this setting.
+    pub explicit_start: bool,
+    /// Whether the document ends with `...`.
+    pub explicit_end: bool,
+}
+
+/// The directives written at the start of a YAML document, before `---`.
+///
+/// Directives captured while deserializing are available from
+/// [`Deserializer::directives`][crate::Deserializer::directives], and can be
+/// written back out with
+/// [`Serializer::set_directives`][crate::Serializer::set_directives].
+///
+/// ```yaml
+/// %YAML 1.2
+/// %TAG !e! tag:example.com,2000:
+/// ---
+/// !e!widget x
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Directives {
+    /// The version from a `%YAML` directive, if there is one.
+    pub version: Option<VersionDirective>,
+    /// The handles declared by `%TAG` directives, in order.
+    pub tags: Vec<TagDirective>,
+}
+
+/// A `%YAML` directive, like `%YAML 1.2`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct VersionDirective {
+    /// The major version, which is always 1.
+    pub major: u32,
+    /// The minor version, either 1 or 2.
+    pub minor: u32,
+}
+
+/// A `%TAG` directive, like `%TAG !e! tag:example.com,2000:`.
+///
+/// With [`Deserializer::tag_shorthands`][crate::Deserializer::tag_shorthands],
+/// as in [`Document::from_str`], tags written with a declared handle, such as
+/// `!e!widget`, keep that shorthand form in [`Tag`][crate::value::Tag] rather
+/// than being resolved to the full `tag:example.com,2000:widget`. They are
+/// expanded again by a serializer that has the same directive set.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TagDirective {
+    /// The handle, like `!e!`.
+    pub handle: String,
+    /// The prefix that the handle stands for, like `tag:example.com,2000:`.
+    pub prefix: String,
+}
+
+impl Directives {
+    /// Returns true if there are no directives.
+    pub fn is_empty(&self) -> bool {
+        self.version.is_none() && self.tags.is_empty()
+    }
+}
+
+impl Document {
+    /// Creates a document holding `value`, without directives or explicit
+    /// markers.
+    pub fn new(value: Value) -> Self {
+        Document {
+            value,
+            ..Document::default()
+        }
+    }
+
+    /// Deserializes a document from a string of YAML, which must contain
+    /// exactly one document.
+    pub fn from_str(s: &str) -> Result<Self> {
+        let mut documents = Deserializer::from_str(s).tag_shorthands(true);
+        let document = match documents.next() {
+            Some(document) => document,
+            None => return Err(error::new(ErrorImpl::EndOfStream)),
+        };
+        let directives = document.directives().cloned().unwrap_or_default();
+        let (explicit_start, explicit_end) = document.explicit_markers().unwrap_or_default();
+        let value = Value::deserialize(document)?;
+        if documents.next().is_some() {
+            return Err(error::new(ErrorImpl::MoreThanOneDocument));
+        }
+        Ok(Document {
+            value,
+            directives,
+            explicit_start,
+            explicit_end,
+        })
+    }
+
+    /// Serializes this document as a string of YAML, with its directives and
+    /// markers.
+    pub fn to_string(&self) -> Result<String> {
+        let mut vec = Vec::with_capacity(128);
+        let mut serializer = Serializer::new(&mut vec);
+        serializer.set_directives(self.directives.clone());
+        serializer.set_explicit_markers(self.explicit_start, self.explicit_end);
+        self.value.serialize(&mut serializer)?;
+        serializer.into_inner()?;
+        String::from_utf8(vec).map_err(|error| error::new(ErrorImpl::FromUtf8(error)))
+    }
+}
+
+impl Serialize for Document {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.value.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Document {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Value::deserialize(deserializer).map(Document::new)
+    }
+}
@@ -20,21 +20,30 @@ pub(crate) enum ErrorImpl {
 
     Libyaml(libyaml::Error),
     Io(io::Error),
+    Read(io::Error),
     FromUtf8(string::FromUtf8Error),
 
     EndOfStream,
     MoreThanOneDocument,
     RecursionLimitExceeded(libyaml::Mark),
     RepetitionLimitExceeded,
+    DocumentLimitExceeded(usize),
     BytesUnsupported,
     UnknownAnchor(libyaml::Mark),
     SerializeNestedEnum,
+    DepthLimitExceeded(usize),
     ScalarInMerge,
     TaggedInMerge,
     ScalarInMergeElement,
     SequenceInMergeElement,
     EmptyTag,
     FailedToParseNumber,
+    UnsetVariable(String, String),
+    UnclosedVariable,
+    #[cfg(feature = "json")]
+    DuplicateJsonKey(String),
+    #[cfg(feature = "json")]
+    JsonNumberOutOfRange(String),
 
     Shared(Arc<ErrorImpl>),
 }
@@ -54,6 +63,16 @@ pub struct Location {
 }
 
 impl Location {
+    /// Creates a location from a byte index and a 1-based line and column, as
+    /// reported by [`Location::line`] and [`Location::column`].
+    pub fn new(index: usize, line: usize, column: usize) -> Self {
+        Location {
+            index,
+            line,
+            column,
+        }
+    }
+
     /// The byte index of the error
     pub fn index(&self) -> usize {
         self.index
@@ -79,6 +98,14 @@ impl Location {
             column: mark.column() as usize + 1,
         }
     }
+
+    fn to_mark(&self) -> libyaml::Mark {
+        libyaml::Mark::new(
+            self.index as u64,
+            self.line.saturating_sub(1) as u64,
+            self.column.saturating_sub(1) as u64,
+        )
+    }
 }
 
 impl Error {
@@ -102,6 +129,59 @@ impl Error {
     pub fn location(&self) -> Option<Location> {
         self.0.location()
     }
+
+    /// Returns the underlying I/O error if reading the input or writing the
+    /// output failed, as opposed to the YAML being invalid or not matching
+    /// the expected type.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// use std::io::{self, Read};
+    ///
+    /// struct Unplugged;
+    ///
+    /// impl Read for Unplugged {
+    ///     fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+    ///         Err(io::Error::new(io::ErrorKind::BrokenPipe, "unplugged"))
+    ///     }
+    /// }
+    ///
+    /// let error = serde_yaml_ng::from_reader::<_, Value>(Unplugged).unwrap_err();
+    /// assert_eq!(error.io_error().unwrap().kind(), io::ErrorKind::BrokenPipe);
+    ///
+    /// let error = serde_yaml_ng::from_str::<Value>("[").unwrap_err();
+    /// assert!(error.io_error().is_none());
+    /// ```
+    pub fn io_error(&self) -> Option<&io::Error> {
+        self.0.io_error()
+    }
+
+    /// Creates an error with a custom message attached to the given input
+    /// location.
+    ///
+    /// This is the counterpart of `serde::de::Error::custom` for errors found
+    /// outside of deserialization, for example by validating a document after
+    /// it has been loaded. The location is included in the formatted message.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use serde_yaml_ng::{Error, Location};
+    /// #
+    /// let error = Error::custom_at("port out of range", Location::new(20, 3, 9));
+    ///
+    /// assert_eq!(error.to_string(), "port out of range at line 3 column 9");
+    /// assert_eq!(error.location().unwrap().line(), 3);
+    /// ```
+    pub fn custom_at<T: Display>(msg: T, location: Location) -> Self {
+        new(ErrorImpl::Message(
+            msg.to_string(),
+            Some(Pos {
+                mark: location.to_mark(),
+                path: ".".to_owned(),
+            }),
+        ))
+    }
 }
 
 pub(crate) fn new(inner: ErrorImpl) -> Error {
@@ -184,9 +264,17 @@ impl ErrorImpl {
         self.mark().map(Location::from_mark)
     }
 
+    fn io_error(&self) -> Option<&io::Error> {
+        match self {
+            ErrorImpl::Io(err) | ErrorImpl::Read(err) => Some(err),
+            ErrorImpl::Shared(err) => err.io_error(),
+            _ => None,
+        }
+    }
+
     fn source(&self) -> Option<&(dyn StdError + 'static)> {
         match self {
-            ErrorImpl::Io(err) => err.source(),
+            ErrorImpl::Io(err) | ErrorImpl::Read(err) => err.source(),
             ErrorImpl::FromUtf8(err) => err.source(),
             ErrorImpl::Shared(err) => err.source(),
             _ => None,
@@ -215,6 +303,7 @@ impl ErrorImpl {
             }
             ErrorImpl::Libyaml(_) => unreachable!(),
             ErrorImpl::Io(err) => Display::fmt(err, f),
+            ErrorImpl::Read(err) => write!(f, "error reading YAML input: {}", err),
             ErrorImpl::FromUtf8(err) => Display::fmt(err, f),
             ErrorImpl::EndOfStream => f.write_str("EOF while parsing a value"),
             ErrorImpl::MoreThanOneDocument => f.write_str(
@@ -222,6 +311,9 @@ impl ErrorImpl {
             ),
             ErrorImpl::RecursionLimitExceeded(_mark) => f.write_str("recursion limit exceeded"),
             ErrorImpl::RepetitionLimitExceeded => f.write_str("repetition limit exceeded"),
+            ErrorImpl::DocumentLimitExceeded(max) => {
+                write!(f, "document limit of {} exceeded", max)
+            }
             ErrorImpl::BytesUnsupported => {
                 f.write_str("serialization and deserialization of bytes in YAML is not implemented")
             }
@@ -229,6 +321,9 @@ impl ErrorImpl {
             ErrorImpl::SerializeNestedEnum => {
                 f.write_str("serializing nested enums in YAML is not supported yet")
             }
+            ErrorImpl::DepthLimitExceeded(max) => {
+                write!(f, "nesting depth limit of {} exceeded", max)
+            }
             ErrorImpl::ScalarInMerge => {
                 f.write_str("expected a mapping or list of mappings for merging, but found scalar")
             }
@@ -241,6 +336,30 @@ impl ErrorImpl {
             }
             ErrorImpl::EmptyTag => f.write_str("empty YAML tag is not allowed"),
             ErrorImpl::FailedToParseNumber => f.write_str("failed to parse YAML number"),
+            ErrorImpl::UnsetVariable(name, message) => {
+                if message.is_empty() {
+                    write!(f, "{}: parameter null or not set", name)
+                } else {
+                    write!(f, "{}: {}", name, message)
+                }
+            }
+            ErrorImpl::UnclosedVariable => f.write_str("unclosed `${` in variable reference"),
+            #[cfg(feature = "json")]
+            ErrorImpl::DuplicateJsonKey(key) => {
+                write!(
+                    f,
+                    "duplicate key {:?} after converting mapping keys to JSON strings",
+                    key
+                )
+            }
+            #[cfg(feature = "json")]
+            ErrorImpl::JsonNumberOutOfRange(number) => {
+                write!(
+                    f,
+                    "integer {} is out of range for serde_json::Value",
+                    number
+                )
+            }
             ErrorImpl::Shared(_) => unreachable!(),
         }
     }
@@ -162,16 +162,27 @@
     clippy::must_use_candidate,
 )]
 
-pub use crate::de::{from_reader, from_slice, from_str, Deserializer};
+#[cfg(feature = "gzip")]
+pub use crate::de::from_gz_reader;
+pub use crate::de::{
+    from_reader, from_slice, from_str, from_str_multi, parse_lenient, sequence_stream, Deserializer,
+};
+pub use crate::document::{Directives, Document, TagDirective, VersionDirective};
 pub use crate::error::{Error, Location, Result};
-pub use crate::ser::{to_string, to_writer, Serializer};
+pub use crate::ser::{
+    needs_quoting_as_key, to_string, to_string_sorted, to_string_with_comments, to_writer,
+    NullStyle, QuoteStyle, SequenceIndent, SerializeOptions, Serializer, Style,
+};
+#[cfg(feature = "units")]
+pub use crate::units::{ByteSize, HumanDuration};
 #[doc(inline)]
-pub use crate::value::{from_value, to_value, Index, Number, Sequence, Value};
+pub use crate::value::{from_value, merge_all, to_value, Index, Number, Sequence, Value};
 
 #[doc(inline)]
 pub use crate::mapping::Mapping;
 
 mod de;
+mod document;
 mod error;
 mod libyaml;
 mod loader;
@@ -179,6 +190,8 @@ pub mod mapping;
 mod number;
 mod path;
 mod ser;
+#[cfg(feature = "units")]
+mod units;
 pub mod value;
 pub mod with;
 
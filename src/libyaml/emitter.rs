@@ -27,8 +27,8 @@ struct EmitterPinned<'a> {
 pub(crate) enum Event<'a> {
     StreamStart,
     StreamEnd,
-    DocumentStart,
-    DocumentEnd,
+    DocumentStart(DocumentStart<'a>),
+    DocumentEnd(DocumentEnd),
     Scalar(Scalar<'a>),
     SequenceStart(Sequence),
     SequenceEnd,
@@ -36,6 +36,18 @@ pub(crate) enum Event<'a> {
     MappingEnd,
 }
 
+#[derive(Debug)]
+pub(crate) struct DocumentStart<'a> {
+    pub version: Option<(i32, i32)>,
+    pub tags: Vec<(&'a str, &'a str)>,
+    pub implicit: bool,
+}
+
+#[derive(Debug)]
+pub(crate) struct DocumentEnd {
+    pub implicit: bool,
+}
+
 #[derive(Debug)]
 pub(crate) struct Scalar<'a> {
     pub tag: Option<String>,
@@ -48,17 +60,27 @@ pub(crate) enum ScalarStyle {
     Any,
     Plain,
     SingleQuoted,
+    DoubleQuoted,
     Literal,
 }
 
 #[derive(Debug)]
 pub(crate) struct Sequence {
     pub tag: Option<String>,
+    pub style: CollectionStyle,
 }
 
 #[derive(Debug)]
 pub(crate) struct Mapping {
     pub tag: Option<String>,
+    pub style: CollectionStyle,
+}
+
+#[derive(Debug)]
+pub(crate) enum CollectionStyle {
+    Any,
+    Block,
+    Flow,
 }
 
 impl<'a> Emitter<'a> {
@@ -79,6 +101,25 @@ impl<'a> Emitter<'a> {
         Emitter { pin }
     }
 
+    pub fn set_indent(&mut self, indent: usize) {
+        // libyaml only validates the indent when the stream starts, which has
+        // already happened by now, so apply the same fallback here.
+        let indent = if (2..=9).contains(&indent) { indent } else { 2 };
+        unsafe {
+            let emitter = addr_of_mut!((*self.pin.ptr).sys);
+            sys::yaml_emitter_set_indent(emitter, indent as i32);
+        }
+    }
+
+    pub fn set_width(&mut self, width: i32) {
+        // As with the indent, libyaml would only normalize a negative width
+        // to "unlimited" when the stream starts, so take that as given here.
+        unsafe {
+            let emitter = addr_of_mut!((*self.pin.ptr).sys);
+            sys::yaml_emitter_set_width(emitter, width);
+        }
+    }
+
     pub fn emit(&mut self, event: Event) -> Result<(), Error> {
         let mut sys_event = MaybeUninit::<sys::yaml_event_t>::uninit();
         let sys_event = sys_event.as_mut_ptr();
@@ -89,11 +130,36 @@ impl<'a> Emitter<'a> {
                     sys::yaml_stream_start_event_initialize(sys_event, sys::YAML_UTF8_ENCODING)
                 }
                 Event::StreamEnd => sys::yaml_stream_end_event_initialize(sys_event),
-                Event::DocumentStart => {
-                    let version_directive = ptr::null_mut();
-                    let tag_directives_start = ptr::null_mut();
-                    let tag_directives_end = ptr::null_mut();
-                    let implicit = true;
+                Event::DocumentStart(document_start) => {
+                    let mut version = document_start.version.map(|(major, minor)| {
+                        let mut version =
+                            MaybeUninit::<sys::yaml_version_directive_t>::zeroed().assume_init();
+                        version.major = major;
+                        version.minor = minor;
+                        version
+                    });
+                    let version_directive = version
+                        .as_mut()
+                        .map_or_else(ptr::null_mut, |version| version as *mut _);
+                    let mut strings = Vec::with_capacity(document_start.tags.len());
+                    for (handle, prefix) in &document_start.tags {
+                        let mut handle = handle.as_bytes().to_vec();
+                        handle.push(b'\0');
+                        let mut prefix = prefix.as_bytes().to_vec();
+                        prefix.push(b'\0');
+                        strings.push((handle, prefix));
+                    }
+                    let mut tags = Vec::with_capacity(strings.len());
+                    for (handle, prefix) in &mut strings {
+                        let mut tag =
+                            MaybeUninit::<sys::yaml_tag_directive_t>::zeroed().assume_init();
+                        tag.handle = handle.as_mut_ptr();
+                        tag.prefix = prefix.as_mut_ptr();
+                        tags.push(tag);
+                    }
+                    let tag_directives_start = tags.as_mut_ptr();
+                    let tag_directives_end = tag_directives_start.add(tags.len());
+                    let implicit = document_start.implicit && version.is_none() && tags.is_empty();
                     sys::yaml_document_start_event_initialize(
                         sys_event,
                         version_directive,
@@ -102,9 +168,8 @@ impl<'a> Emitter<'a> {
                         implicit,
                     )
                 }
-                Event::DocumentEnd => {
-                    let implicit = true;
-                    sys::yaml_document_end_event_initialize(sys_event, implicit)
+                Event::DocumentEnd(document_end) => {
+                    sys::yaml_document_end_event_initialize(sys_event, document_end.implicit)
                 }
                 Event::Scalar(mut scalar) => {
                     let anchor = ptr::null();
@@ -120,6 +185,7 @@ impl<'a> Emitter<'a> {
                         ScalarStyle::Any => sys::YAML_ANY_SCALAR_STYLE,
                         ScalarStyle::Plain => sys::YAML_PLAIN_SCALAR_STYLE,
                         ScalarStyle::SingleQuoted => sys::YAML_SINGLE_QUOTED_SCALAR_STYLE,
+                        ScalarStyle::DoubleQuoted => sys::YAML_DOUBLE_QUOTED_SCALAR_STYLE,
                         ScalarStyle::Literal => sys::YAML_LITERAL_SCALAR_STYLE,
                     };
                     sys::yaml_scalar_event_initialize(
@@ -140,7 +206,11 @@ impl<'a> Emitter<'a> {
                         tag.as_ptr()
                     });
                     let implicit = tag.is_null();
-                    let style = sys::YAML_ANY_SEQUENCE_STYLE;
+                    let style = match sequence.style {
+                        CollectionStyle::Any => sys::YAML_ANY_SEQUENCE_STYLE,
+                        CollectionStyle::Block => sys::YAML_BLOCK_SEQUENCE_STYLE,
+                        CollectionStyle::Flow => sys::YAML_FLOW_SEQUENCE_STYLE,
+                    };
                     sys::yaml_sequence_start_event_initialize(
                         sys_event, anchor, tag, implicit, style,
                     )
@@ -153,7 +223,11 @@ impl<'a> Emitter<'a> {
                         tag.as_ptr()
                     });
                     let implicit = tag.is_null();
-                    let style = sys::YAML_ANY_MAPPING_STYLE;
+                    let style = match mapping.style {
+                        CollectionStyle::Any => sys::YAML_ANY_MAPPING_STYLE,
+                        CollectionStyle::Block => sys::YAML_BLOCK_MAPPING_STYLE,
+                        CollectionStyle::Flow => sys::YAML_FLOW_MAPPING_STYLE,
+                    };
                     sys::yaml_mapping_start_event_initialize(
                         sys_event, anchor, tag, implicit, style,
                     )
@@ -176,6 +250,10 @@ impl<'a> Emitter<'a> {
             if sys::yaml_emitter_flush(emitter).fail {
                 return Err(self.error());
             }
+            let write = &mut (*self.pin.ptr).write;
+            if let Err(err) = write.flush() {
+                return Err(Error::Io(err));
+            }
         }
         Ok(())
     }
@@ -60,6 +60,16 @@ impl Error {
     pub fn mark(&self) -> Mark {
         self.problem_mark
     }
+
+    // For errors from a parser that was started partway into the input.
+    pub fn offset(mut self, index: u64, line: u64) -> Self {
+        self.problem_offset += index;
+        self.problem_mark = self.problem_mark.offset(index, line);
+        if self.context.is_some() {
+            self.context_mark = self.context_mark.offset(index, line);
+        }
+        self
+    }
 }
 
 impl Display for Error {
@@ -120,6 +130,15 @@ pub(crate) struct Mark {
 }
 
 impl Mark {
+    pub fn new(index: u64, line: u64, column: u64) -> Self {
+        // yaml_mark_t is non_exhaustive, but it is plain integers all the way.
+        let mut sys = unsafe { MaybeUninit::<sys::yaml_mark_t>::zeroed().assume_init() };
+        sys.index = index;
+        sys.line = line;
+        sys.column = column;
+        Mark { sys }
+    }
+
     pub fn index(&self) -> u64 {
         self.sys.index
     }
@@ -131,6 +150,14 @@ impl Mark {
     pub fn column(&self) -> u64 {
         self.sys.column
     }
+
+    // Moves the mark forward by `index` bytes and `line` lines, for a parser
+    // that was started at the beginning of a line partway into the input.
+    pub fn offset(mut self, index: u64, line: u64) -> Self {
+        self.sys.index += index;
+        self.sys.line += line;
+        self
+    }
 }
 
 impl Display for Mark {
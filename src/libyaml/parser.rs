@@ -3,7 +3,9 @@ use crate::libyaml::error::{Error, Mark, Result};
 use crate::libyaml::tag::Tag;
 use crate::libyaml::util::Owned;
 use std::borrow::Cow;
+use std::ffi::c_void;
 use std::fmt::{self, Debug};
+use std::io;
 use std::mem::MaybeUninit;
 use std::ptr::{addr_of_mut, NonNull};
 use std::slice;
@@ -16,14 +18,16 @@ pub(crate) struct Parser<'input> {
 struct ParserPinned<'input> {
     sys: sys::yaml_parser_t,
     input: Cow<'input, [u8]>,
+    read: Option<Box<dyn io::Read + 'input>>,
+    read_error: Option<io::Error>,
 }
 
 #[derive(Debug)]
 pub(crate) enum Event<'input> {
     StreamStart,
     StreamEnd,
-    DocumentStart,
-    DocumentEnd,
+    DocumentStart(DocumentStart),
+    DocumentEnd(DocumentEnd),
     Alias(Anchor),
     Scalar(Scalar<'input>),
     SequenceStart(SequenceStart),
@@ -32,6 +36,24 @@ pub(crate) enum Event<'input> {
     MappingEnd,
 }
 
+#[derive(Debug)]
+pub(crate) struct DocumentStart {
+    pub version: Option<(i32, i32)>,
+    pub tags: Vec<TagDirective>,
+    pub implicit: bool,
+}
+
+#[derive(Debug)]
+pub(crate) struct DocumentEnd {
+    pub implicit: bool,
+}
+
+#[derive(Debug)]
+pub(crate) struct TagDirective {
+    pub handle: Box<[u8]>,
+    pub prefix: Box<[u8]>,
+}
+
 pub(crate) struct Scalar<'input> {
     pub anchor: Option<Anchor>,
     pub tag: Option<Tag>,
@@ -75,11 +97,51 @@ impl<'input> Parser<'input> {
             sys::yaml_parser_set_encoding(parser, sys::YAML_UTF8_ENCODING);
             sys::yaml_parser_set_input_string(parser, input.as_ptr(), input.len() as u64);
             addr_of_mut!((*owned.ptr).input).write(input);
+            addr_of_mut!((*owned.ptr).read).write(None);
+            addr_of_mut!((*owned.ptr).read_error).write(None);
+            Owned::assume_init(owned)
+        };
+        Parser { pin }
+    }
+
+    // A parser that pulls its input from `read` as it goes, rather than
+    // reading all of it up front. Scalars from such a parser have no `repr`,
+    // and `input` is empty.
+    pub fn from_reader(read: Box<dyn io::Read + 'input>) -> Parser<'input> {
+        let owned = Owned::<ParserPinned>::new_uninit();
+        let pin = unsafe {
+            let parser = addr_of_mut!((*owned.ptr).sys);
+            if sys::yaml_parser_initialize(parser).fail {
+                panic!("malloc error: {}", Error::parse_error(parser));
+            }
+            sys::yaml_parser_set_encoding(parser, sys::YAML_UTF8_ENCODING);
+            addr_of_mut!((*owned.ptr).input).write(Cow::Owned(Vec::new()));
+            addr_of_mut!((*owned.ptr).read).write(Some(read));
+            addr_of_mut!((*owned.ptr).read_error).write(None);
+            sys::yaml_parser_set_input(parser, read_handler, owned.ptr.cast());
             Owned::assume_init(owned)
         };
         Parser { pin }
     }
 
+    // The error from the reader that made the last call to `next` fail, if
+    // that is why it failed.
+    pub fn take_read_error(&mut self) -> Option<io::Error> {
+        unsafe { (*self.pin.ptr).read_error.take() }
+    }
+
+    pub fn input(&self) -> &[u8] {
+        unsafe { &(*self.pin.ptr).input }
+    }
+
+    // The input from byte `index` onward, without copying borrowed input.
+    pub fn remainder(&self, index: usize) -> Cow<'input, [u8]> {
+        match unsafe { &(*self.pin.ptr).input } {
+            Cow::Borrowed(input) => Cow::Borrowed(&input[index..]),
+            Cow::Owned(input) => Cow::Owned(input[index..].to_vec()),
+        }
+    }
+
     pub fn next(&mut self) -> Result<(Event<'input>, Mark)> {
         let mut event = MaybeUninit::<sys::yaml_event_t>::uninit();
         unsafe {
@@ -108,8 +170,20 @@ unsafe fn convert_event<'input>(
     match sys.type_ {
         sys::YAML_STREAM_START_EVENT => Event::StreamStart,
         sys::YAML_STREAM_END_EVENT => Event::StreamEnd,
-        sys::YAML_DOCUMENT_START_EVENT => Event::DocumentStart,
-        sys::YAML_DOCUMENT_END_EVENT => Event::DocumentEnd,
+        sys::YAML_DOCUMENT_START_EVENT => Event::DocumentStart(DocumentStart {
+            version: unsafe { sys.data.document_start.version_directive.as_ref() }
+                .map(|version| (version.major, version.minor)),
+            tags: unsafe {
+                tag_directives(
+                    sys.data.document_start.tag_directives.start,
+                    sys.data.document_start.tag_directives.end,
+                )
+            },
+            implicit: unsafe { sys.data.document_start.implicit },
+        }),
+        sys::YAML_DOCUMENT_END_EVENT => Event::DocumentEnd(DocumentEnd {
+            implicit: unsafe { sys.data.document_end.implicit },
+        }),
         sys::YAML_ALIAS_EVENT => {
             Event::Alias(unsafe { optional_anchor(sys.data.alias.anchor) }.unwrap())
         }
@@ -148,6 +222,46 @@ unsafe fn convert_event<'input>(
     }
 }
 
+unsafe fn read_handler(data: *mut c_void, buffer: *mut u8, size: u64, size_read: *mut u64) -> i32 {
+    let data = data.cast::<ParserPinned>();
+    let read = match unsafe { &mut (*data).read } {
+        Some(read) => read,
+        None => return 0,
+    };
+    let buffer = unsafe { slice::from_raw_parts_mut(buffer, size as usize) };
+    loop {
+        match read.read(buffer) {
+            Ok(n) => {
+                unsafe { *size_read = n as u64 };
+                return 1;
+            }
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => {
+                unsafe { (*data).read_error = Some(err) };
+                return 0;
+            }
+        }
+    }
+}
+
+unsafe fn tag_directives(
+    mut start: *const sys::yaml_tag_directive_t,
+    end: *const sys::yaml_tag_directive_t,
+) -> Vec<TagDirective> {
+    let mut tags = Vec::new();
+    while start < end {
+        let directive = unsafe { &*start };
+        let handle = unsafe { CStr::from_ptr(NonNull::new_unchecked(directive.handle.cast())) };
+        let prefix = unsafe { CStr::from_ptr(NonNull::new_unchecked(directive.prefix.cast())) };
+        tags.push(TagDirective {
+            handle: Box::from(handle.to_bytes()),
+            prefix: Box::from(prefix.to_bytes()),
+        });
+        start = unsafe { start.add(1) };
+    }
+    tags
+}
+
 unsafe fn optional_anchor(anchor: *const u8) -> Option<Anchor> {
     let ptr = NonNull::new(anchor as *mut i8)?;
     let cstr = unsafe { CStr::from_ptr(ptr) };
@@ -188,6 +302,12 @@ impl<'input> Debug for Scalar<'input> {
     }
 }
 
+impl Anchor {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
 impl Debug for Anchor {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         cstr::debug_lossy(&self.0, formatter)
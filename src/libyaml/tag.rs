@@ -16,6 +16,18 @@ impl Tag {
     pub fn starts_with(&self, prefix: &str) -> bool {
         self.0.starts_with(prefix.as_bytes())
     }
+
+    // Undo the resolution of a tag like `!e!widget` through a `%TAG` directive
+    // into `tag:example.com,2000:widget`.
+    pub fn shorten(&mut self, handle: &[u8], prefix: &[u8]) -> bool {
+        match self.0.strip_prefix(prefix) {
+            Some(suffix) => {
+                self.0 = [handle, suffix].concat().into_boxed_slice();
+                true
+            }
+            None => false,
+        }
+    }
 }
 
 impl PartialEq<str> for Tag {
@@ -1,14 +1,36 @@
 use crate::de::{Event, Progress};
+use crate::document::{Directives, TagDirective, VersionDirective};
 use crate::error::{self, Error, ErrorImpl, Result};
+use crate::libyaml;
 use crate::libyaml::error::Mark;
-use crate::libyaml::parser::{Event as YamlEvent, Parser};
+use crate::libyaml::parser::{
+    Anchor, DocumentStart, Event as YamlEvent, Parser, TagDirective as YamlTag,
+};
+use crate::libyaml::tag::Tag;
+use crate::value::Value;
 use std::borrow::Cow;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::rc::Rc;
+use std::str;
 use std::sync::Arc;
 
 pub(crate) struct Loader<'input> {
     parser: Option<Parser<'input>>,
-    document_count: usize,
+    // Byte index and line within the whole input at which the parser's input
+    // starts. Nonzero once the parser has been restarted after an error.
+    offset: (u64, u64),
+    // Byte index within the parser's input of the last event.
+    position: u64,
+    predefined_anchors: Option<Rc<HashMap<String, Rc<Value>>>>,
+    // The number of nodes in the predefined anchors, which count as read for
+    // the limit on copying them.
+    predefined_nodes: usize,
+    // Whether tags written with a `%TAG` handle are kept in that form.
+    tag_shorthands: bool,
+    pub document_count: usize,
+    // An event read ahead by has_next_document.
+    peeked: Option<libyaml::error::Result<(YamlEvent<'input>, Mark)>>,
 }
 
 pub(crate) struct Document<'input> {
@@ -16,17 +38,24 @@ pub(crate) struct Document<'input> {
     pub error: Option<Arc<ErrorImpl>>,
     /// Map from alias id to index in events.
     pub aliases: BTreeMap<usize, usize>,
+    pub directives: Directives,
+    pub explicit_start: bool,
+    pub explicit_end: bool,
 }
 
 impl<'input> Loader<'input> {
-    pub fn new(progress: Progress<'input>) -> Result<Self> {
+    pub fn new(
+        progress: Progress<'input>,
+        predefined_anchors: Option<Rc<HashMap<String, Rc<Value>>>>,
+        tag_shorthands: bool,
+    ) -> Result<Self> {
         let input = match progress {
             Progress::Str(s) => Cow::Borrowed(s.as_bytes()),
             Progress::Slice(bytes) => Cow::Borrowed(bytes),
             Progress::Read(mut rdr) => {
                 let mut buffer = Vec::new();
                 if let Err(io_error) = rdr.read_to_end(&mut buffer) {
-                    return Err(error::new(ErrorImpl::Io(io_error)));
+                    return Err(error::new(ErrorImpl::Read(io_error)));
                 }
                 Cow::Owned(buffer)
             }
@@ -34,12 +63,44 @@ impl<'input> Loader<'input> {
             Progress::Fail(err) => return Err(error::shared(err)),
         };
 
+        let predefined_nodes = match &predefined_anchors {
+            Some(anchors) => anchors.values().map(|value| node_count(value)).sum(),
+            None => 0,
+        };
         Ok(Loader {
             parser: Some(Parser::new(input)),
+            offset: (0, 0),
+            position: 0,
+            predefined_anchors,
+            predefined_nodes,
+            tag_shorthands,
             document_count: 0,
+            peeked: None,
         })
     }
 
+    pub fn close(&mut self) {
+        self.parser = None;
+    }
+
+    // Whether there is another document, found without loading any of it.
+    pub fn has_next_document(&mut self) -> bool {
+        let parser = match &mut self.parser {
+            Some(parser) => parser,
+            None => return false,
+        };
+        loop {
+            let next = self.peeked.take().unwrap_or_else(|| parser.next());
+            let more = match &next {
+                Ok((YamlEvent::StreamStart, _mark)) => continue,
+                Ok((YamlEvent::StreamEnd, _mark)) => self.document_count == 0,
+                _ => true,
+            };
+            self.peeked = Some(next);
+            return more;
+        }
+    }
+
     pub fn next_document(&mut self) -> Option<Document<'input>> {
         let parser = match &mut self.parser {
             Some(parser) => parser,
@@ -48,19 +109,34 @@ impl<'input> Loader<'input> {
 
         let first = self.document_count == 0;
         self.document_count += 1;
+        let start = self.position;
 
         let mut anchors = BTreeMap::new();
         let mut document = Document {
             events: Vec::new(),
             error: None,
             aliases: BTreeMap::new(),
+            directives: Directives::default(),
+            explicit_start: false,
+            explicit_end: false,
         };
+        let mut tag_directives = Vec::new();
+        // Nodes copied out of the predefined anchors for aliases, held to the
+        // same limit as the copies of earlier anchors in a SequenceLoader.
+        let mut copied = 0;
 
         loop {
-            let (event, mark) = match parser.next() {
-                Ok((event, mark)) => (event, mark),
+            let next = self.peeked.take().unwrap_or_else(|| parser.next());
+            let (event, mark) = match next {
+                Ok((event, mark)) => {
+                    self.position = mark.index();
+                    (event, mark.offset(self.offset.0, self.offset.1))
+                }
                 Err(err) => {
+                    let index = err.mark().index();
+                    let err = err.offset(self.offset.0, self.offset.1);
                     document.error = Some(Error::from(err).shared());
+                    self.recover(index, start);
                     return Some(document);
                 }
             };
@@ -77,38 +153,57 @@ impl<'input> Loader<'input> {
                         None
                     };
                 }
-                YamlEvent::DocumentStart => continue,
-                YamlEvent::DocumentEnd => return Some(document),
-                YamlEvent::Alias(alias) => match anchors.get(&alias) {
-                    Some(id) => Event::Alias(*id),
-                    None => {
-                        document.error = Some(error::new(ErrorImpl::UnknownAnchor(mark)).shared());
-                        return Some(document);
+                YamlEvent::DocumentStart(document_start) => {
+                    document.directives = directives(&document_start);
+                    document.explicit_start = !document_start.implicit;
+                    if self.tag_shorthands {
+                        tag_directives = document_start.tags;
                     }
-                },
-                YamlEvent::Scalar(mut scalar) => {
-                    if let Some(anchor) = scalar.anchor.take() {
-                        let id = anchors.len();
-                        anchors.insert(anchor, id);
-                        document.aliases.insert(id, document.events.len());
+                    continue;
+                }
+                YamlEvent::DocumentEnd(document_end) => {
+                    document.explicit_end = !document_end.implicit;
+                    return Some(document);
+                }
+                YamlEvent::Alias(alias) => {
+                    let event = match anchors.get(&alias) {
+                        Some(id) => Ok(Event::Alias(*id)),
+                        None => match predefined(self.predefined_anchors.as_deref(), &alias) {
+                            Some(value) => {
+                                copied += node_count(value);
+                                let read = document.events.len() + 1 + self.predefined_nodes;
+                                if copied > read * 100 {
+                                    Err(ErrorImpl::RepetitionLimitExceeded)
+                                } else {
+                                    Ok(Event::Value(Rc::clone(value)))
+                                }
+                            }
+                            None => Err(ErrorImpl::UnknownAnchor(mark)),
+                        },
+                    };
+                    match event {
+                        Ok(event) => event,
+                        Err(err) => {
+                            document.error = Some(error::new(err).shared());
+                            self.skip_document(start);
+                            return Some(document);
+                        }
                     }
+                }
+                YamlEvent::Scalar(mut scalar) => {
+                    shorten_tag(&mut scalar.tag, &tag_directives);
+                    record_anchor(scalar.anchor.take(), &mut anchors, &mut document);
                     Event::Scalar(scalar)
                 }
                 YamlEvent::SequenceStart(mut sequence_start) => {
-                    if let Some(anchor) = sequence_start.anchor.take() {
-                        let id = anchors.len();
-                        anchors.insert(anchor, id);
-                        document.aliases.insert(id, document.events.len());
-                    }
+                    shorten_tag(&mut sequence_start.tag, &tag_directives);
+                    record_anchor(sequence_start.anchor.take(), &mut anchors, &mut document);
                     Event::SequenceStart(sequence_start)
                 }
                 YamlEvent::SequenceEnd => Event::SequenceEnd,
                 YamlEvent::MappingStart(mut mapping_start) => {
-                    if let Some(anchor) = mapping_start.anchor.take() {
-                        let id = anchors.len();
-                        anchors.insert(anchor, id);
-                        document.aliases.insert(id, document.events.len());
-                    }
+                    shorten_tag(&mut mapping_start.tag, &tag_directives);
+                    record_anchor(mapping_start.anchor.take(), &mut anchors, &mut document);
                     Event::MappingStart(mapping_start)
                 }
                 YamlEvent::MappingEnd => Event::MappingEnd,
@@ -116,4 +211,297 @@ impl<'input> Loader<'input> {
             document.events.push((event, mark));
         }
     }
+
+    // Reads past the rest of a document that is already known to be
+    // invalid, so that the next one starts in the right place.
+    fn skip_document(&mut self, start: u64) {
+        while let Some(parser) = &mut self.parser {
+            match parser.next() {
+                Ok((YamlEvent::DocumentEnd(_), _mark)) => return,
+                Ok((YamlEvent::StreamEnd, _mark)) => self.parser = None,
+                Ok((_event, mark)) => self.position = mark.index(),
+                Err(err) => return self.recover(err.mark().index(), start),
+            }
+        }
+    }
+
+    // libyaml cannot continue after a syntax error, so parsing starts over at
+    // the next `---` line after the error, where the following document
+    // begins. A document that began at `start` is never parsed again.
+    fn recover(&mut self, error: u64, start: u64) {
+        let parser = match self.parser.take() {
+            Some(parser) => parser,
+            None => return,
+        };
+        let input = parser.input();
+        let from = line_start(input, error as usize).max(start as usize + 1);
+        if let Some(restart) = next_document_start(input, from) {
+            let lines = count_lines(&input[..restart]);
+            self.offset = (self.offset.0 + restart as u64, self.offset.1 + lines);
+            self.position = 0;
+            self.parser = Some(Parser::new(parser.remainder(restart)));
+        }
+    }
+}
+
+// Reads the elements of the top-level sequence of a single document one at a
+// time, without holding on to the ones already read.
+pub(crate) struct SequenceLoader<'input> {
+    parser: Option<Parser<'input>>,
+    // Whether the start of the document, and of the top-level sequence in
+    // it, have been read.
+    in_document: bool,
+    in_sequence: bool,
+    // The anchors defined in elements already read, for aliases in later
+    // ones.
+    pub anchors: HashMap<String, Rc<Value>>,
+    // Events read so far, and nodes copied out of `anchors` for aliases. The
+    // copies are held to the same limit as the jumps of aliases within a
+    // document, so that nesting them cannot blow up.
+    events: usize,
+    copied: usize,
+}
+
+pub(crate) struct Element<'input> {
+    pub document: Document<'input>,
+    // Name and index in the events of each anchor defined in the element.
+    pub anchors: Vec<(String, usize)>,
+    // Set if the document is not a sequence, in which case the whole
+    // document is the element.
+    pub whole_document: bool,
+}
+
+impl<'input> SequenceLoader<'input> {
+    pub fn new(read: Box<dyn io::Read + 'input>) -> Self {
+        SequenceLoader {
+            parser: Some(Parser::from_reader(read)),
+            in_document: false,
+            in_sequence: false,
+            anchors: HashMap::new(),
+            events: 0,
+            copied: 0,
+        }
+    }
+
+    pub fn next_element(&mut self) -> Option<Result<Element<'input>>> {
+        let result = self.read_element();
+        if let Err(_) | Ok(None) = result {
+            self.parser = None;
+        }
+        result.transpose()
+    }
+
+    fn read_element(&mut self) -> Result<Option<Element<'input>>> {
+        let mut anchors = BTreeMap::new();
+        let mut document = Document {
+            events: Vec::new(),
+            error: None,
+            aliases: BTreeMap::new(),
+            directives: Directives::default(),
+            explicit_start: false,
+            explicit_end: false,
+        };
+        let mut whole_document = false;
+        let mut depth = 0;
+
+        loop {
+            let (event, mark) = match self.next_event()? {
+                Some(next) => next,
+                None => return Ok(None),
+            };
+            let event = match event {
+                YamlEvent::StreamStart => continue,
+                YamlEvent::StreamEnd => return Ok(None),
+                YamlEvent::DocumentStart(_) if self.in_document => {
+                    return Err(error::new(ErrorImpl::MoreThanOneDocument));
+                }
+                YamlEvent::DocumentStart(_) => {
+                    self.in_document = true;
+                    continue;
+                }
+                YamlEvent::DocumentEnd(_) => continue,
+                YamlEvent::SequenceStart(_) if depth == 0 && !self.in_sequence => {
+                    self.in_sequence = true;
+                    continue;
+                }
+                YamlEvent::SequenceEnd if depth == 0 && self.in_sequence => continue,
+                YamlEvent::Alias(alias) => match anchors.get(&alias) {
+                    Some(id) => Event::Alias(*id),
+                    None => match str::from_utf8(alias.as_bytes())
+                        .ok()
+                        .and_then(|name| self.anchors.get(name))
+                    {
+                        Some(value) => {
+                            self.copied += node_count(value);
+                            if self.copied > self.events * 100 {
+                                return Err(error::new(ErrorImpl::RepetitionLimitExceeded));
+                            }
+                            Event::Value(Rc::clone(value))
+                        }
+                        None => return Err(error::new(ErrorImpl::UnknownAnchor(mark))),
+                    },
+                },
+                YamlEvent::Scalar(mut scalar) => {
+                    record_anchor(scalar.anchor.take(), &mut anchors, &mut document);
+                    Event::Scalar(scalar)
+                }
+                YamlEvent::SequenceStart(mut sequence_start) => {
+                    record_anchor(sequence_start.anchor.take(), &mut anchors, &mut document);
+                    depth += 1;
+                    Event::SequenceStart(sequence_start)
+                }
+                YamlEvent::SequenceEnd => {
+                    depth -= 1;
+                    Event::SequenceEnd
+                }
+                YamlEvent::MappingStart(mut mapping_start) => {
+                    record_anchor(mapping_start.anchor.take(), &mut anchors, &mut document);
+                    depth += 1;
+                    Event::MappingStart(mapping_start)
+                }
+                YamlEvent::MappingEnd => {
+                    depth -= 1;
+                    Event::MappingEnd
+                }
+            };
+            if !self.in_sequence {
+                // The document is not a sequence. Read it whole, as the
+                // sequence would have been if it were one.
+                whole_document = true;
+                self.in_sequence = true;
+            }
+            document.events.push((event, mark));
+            if depth == 0 {
+                break;
+            }
+        }
+
+        let anchors = anchors
+            .into_iter()
+            .map(|(anchor, id)| {
+                let name = String::from_utf8_lossy(anchor.as_bytes()).into_owned();
+                (name, document.aliases[&id])
+            })
+            .collect();
+        Ok(Some(Element {
+            document,
+            anchors,
+            whole_document,
+        }))
+    }
+
+    fn next_event(&mut self) -> Result<Option<(YamlEvent<'input>, Mark)>> {
+        let parser = match &mut self.parser {
+            Some(parser) => parser,
+            None => return Ok(None),
+        };
+        match parser.next() {
+            Ok(next) => {
+                self.events += 1;
+                Ok(Some(next))
+            }
+            Err(err) => match parser.take_read_error() {
+                Some(io_error) => Err(error::new(ErrorImpl::Read(io_error))),
+                None => Err(Error::from(err)),
+            },
+        }
+    }
+}
+
+// The number of nodes in `value`, the way the events of a document count them.
+fn node_count(value: &Value) -> usize {
+    match value {
+        Value::Sequence(sequence) => 1 + sequence.iter().map(node_count).sum::<usize>(),
+        Value::Mapping(mapping) => {
+            1 + mapping
+                .iter()
+                .map(|(key, value)| node_count(key) + node_count(value))
+                .sum::<usize>()
+        }
+        Value::Tagged(tagged) => node_count(&tagged.value),
+        Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => 1,
+    }
+}
+
+// Gives the anchor of the node about to be pushed to `document` the next id.
+fn record_anchor(
+    anchor: Option<Anchor>,
+    anchors: &mut BTreeMap<Anchor, usize>,
+    document: &mut Document,
+) {
+    if let Some(anchor) = anchor {
+        let id = anchors.len();
+        anchors.insert(anchor, id);
+        document.aliases.insert(id, document.events.len());
+    }
+}
+
+fn is_line_start(input: &[u8], index: usize) -> bool {
+    index == 0 || matches!(input[index - 1], b'\n' | b'\r')
+}
+
+fn line_start(input: &[u8], index: usize) -> usize {
+    let mut index = index.min(input.len());
+    while !is_line_start(input, index) {
+        index -= 1;
+    }
+    index
+}
+
+fn next_document_start(input: &[u8], from: usize) -> Option<usize> {
+    (from..input.len()).find(|&index| {
+        is_line_start(input, index)
+            && input[index..].starts_with(b"---")
+            && matches!(
+                input.get(index + 3),
+                None | Some(b' ' | b'\t' | b'\r' | b'\n')
+            )
+    })
+}
+
+fn count_lines(input: &[u8]) -> u64 {
+    let mut lines = 0;
+    for (index, byte) in input.iter().enumerate() {
+        if *byte == b'\n' || (*byte == b'\r' && input.get(index + 1) != Some(&b'\n')) {
+            lines += 1;
+        }
+    }
+    lines
+}
+
+fn predefined<'a>(
+    anchors: Option<&'a HashMap<String, Rc<Value>>>,
+    alias: &Anchor,
+) -> Option<&'a Rc<Value>> {
+    let name = str::from_utf8(alias.as_bytes()).ok()?;
+    anchors?.get(name)
+}
+
+fn directives(document_start: &DocumentStart) -> Directives {
+    Directives {
+        version: document_start
+            .version
+            .map(|(major, minor)| VersionDirective {
+                major: major as u32,
+                minor: minor as u32,
+            }),
+        tags: document_start
+            .tags
+            .iter()
+            .map(|tag| TagDirective {
+                handle: String::from_utf8_lossy(&tag.handle).into_owned(),
+                prefix: String::from_utf8_lossy(&tag.prefix).into_owned(),
+            })
+            .collect(),
+    }
+}
+
+fn shorten_tag(tag: &mut Option<Tag>, tag_directives: &[YamlTag]) {
+    if let Some(tag) = tag {
+        for directive in tag_directives {
+            if tag.shorten(&directive.handle, &directive.prefix) {
+                return;
+            }
+        }
+    }
 }
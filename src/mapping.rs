@@ -57,6 +57,30 @@ impl Mapping {
         self.map.insert(k, v)
     }
 
+    /// Inserts a key-value pair into the map if the key is not already
+    /// present, returning a mutable reference to the inserted value.
+    ///
+    /// If the key already exists, the map is left unchanged and the key and
+    /// value that were passed in are given back as the error.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::{Mapping, Value};
+    /// let mut m = Mapping::new();
+    /// assert_eq!(m.try_insert("a".into(), 1.into()).unwrap(), 1);
+    /// assert_eq!(m.try_insert("a".into(), 2.into()).unwrap_err().1, 2);
+    /// assert_eq!(m["a"], 1);
+    /// ```
+    #[allow(clippy::result_large_err)]
+    pub fn try_insert(&mut self, k: Value, v: Value) -> Result<&mut Value, (Value, Value)> {
+        if self.map.contains_key(&k) {
+            return Err((k, v));
+        }
+        match self.map.entry(k) {
+            indexmap::map::Entry::Vacant(vacant) => Ok(vacant.insert(v)),
+            indexmap::map::Entry::Occupied(_) => unreachable!(),
+        }
+    }
+
     /// Checks if the map contains the given key.
     #[inline]
     pub fn contains_key<I: Index>(&self, index: I) -> bool {
@@ -69,6 +93,15 @@ impl Mapping {
         index.index_into(self)
     }
 
+    /// Returns the key-value pair corresponding to the key in the map.
+    ///
+    /// The returned key is the one stored in the map, which may be a distinct
+    /// object from the key used for the lookup.
+    #[inline]
+    pub fn get_key_value<I: Index>(&self, index: I) -> Option<(&Value, &Value)> {
+        index.key_value_into(self)
+    }
+
     /// Returns the mutable reference corresponding to the key in the map.
     #[inline]
     pub fn get_mut<I: Index>(&mut self, index: I) -> Option<&mut Value> {
@@ -99,9 +132,9 @@ impl Mapping {
     /// Remove and return the key-value pair.
     ///
     /// This is equivalent to [`.swap_remove_entry(index)`][Self::swap_remove_entry],
-    /// replacing this entry's position with the last element. If you need to
-    /// preserve the relative order of the keys in the map, use
-    /// [`.shift_remove_entry(key)`][Self::shift_remove_entry] instead.
+    /// replacing this entry's position with the last element, so the relative
+    /// order of the keys in the map is not preserved. The order-preserving
+    /// variant is [`.shift_remove_entry(key)`][Self::shift_remove_entry].
     #[inline]
     pub fn remove_entry<I: Index>(&mut self, index: I) -> Option<(Value, Value)> {
         self.swap_remove_entry(index)
@@ -157,6 +190,37 @@ impl Mapping {
         self.map.retain(keep);
     }
 
+    /// Split the map into the entries for which the closure `f` returns true
+    /// and those for which it returns false, preserving the order of entries
+    /// within each half.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::{Mapping, Value};
+    /// let mapping: Mapping = serde_yaml_ng::from_str("_a: 1\nb: 2\n_c: 3\n").unwrap();
+    /// let (private, public) = mapping.partition(|k, _v| k.as_str().unwrap().starts_with('_'));
+    /// assert_eq!(private.len(), 2);
+    /// assert_eq!(public["b"], 2);
+    /// ```
+    pub fn partition<F>(self, mut f: F) -> (Mapping, Mapping)
+    where
+        F: FnMut(&Value, &Value) -> bool,
+    {
+        let mut left = Mapping::new();
+        let mut right = Mapping::new();
+        for (k, v) in self {
+            if f(&k, &v) {
+                left.insert(k, v);
+            } else {
+                right.insert(k, v);
+            }
+        }
+        (left, right)
+    }
+
+    pub(crate) fn sort_keys(&mut self) {
+        self.map.sort_by(|a, _, b, _| total_cmp(a, b));
+    }
+
     /// Returns the maximum number of key-value pairs the map can hold without
     /// reallocating.
     #[inline]
@@ -248,6 +312,9 @@ pub trait Index: private::Sealed {
     #[doc(hidden)]
     fn index_into<'a>(&self, v: &'a Mapping) -> Option<&'a Value>;
 
+    #[doc(hidden)]
+    fn key_value_into<'a>(&self, v: &'a Mapping) -> Option<(&'a Value, &'a Value)>;
+
     #[doc(hidden)]
     fn index_into_mut<'a>(&self, v: &'a mut Mapping) -> Option<&'a mut Value>;
 
@@ -291,6 +358,9 @@ impl Index for Value {
     fn index_into<'a>(&self, v: &'a Mapping) -> Option<&'a Value> {
         v.map.get(self)
     }
+    fn key_value_into<'a>(&self, v: &'a Mapping) -> Option<(&'a Value, &'a Value)> {
+        v.map.get_key_value(self)
+    }
     fn index_into_mut<'a>(&self, v: &'a mut Mapping) -> Option<&'a mut Value> {
         v.map.get_mut(self)
     }
@@ -315,6 +385,9 @@ impl Index for str {
     fn index_into<'a>(&self, v: &'a Mapping) -> Option<&'a Value> {
         v.map.get(&HashLikeValue(self))
     }
+    fn key_value_into<'a>(&self, v: &'a Mapping) -> Option<(&'a Value, &'a Value)> {
+        v.map.get_key_value(&HashLikeValue(self))
+    }
     fn index_into_mut<'a>(&self, v: &'a mut Mapping) -> Option<&'a mut Value> {
         v.map.get_mut(&HashLikeValue(self))
     }
@@ -339,6 +412,9 @@ impl Index for String {
     fn index_into<'a>(&self, v: &'a Mapping) -> Option<&'a Value> {
         self.as_str().index_into(v)
     }
+    fn key_value_into<'a>(&self, v: &'a Mapping) -> Option<(&'a Value, &'a Value)> {
+        self.as_str().key_value_into(v)
+    }
     fn index_into_mut<'a>(&self, v: &'a mut Mapping) -> Option<&'a mut Value> {
         self.as_str().index_into_mut(v)
     }
@@ -366,6 +442,9 @@ where
     fn index_into<'a>(&self, v: &'a Mapping) -> Option<&'a Value> {
         (**self).index_into(v)
     }
+    fn key_value_into<'a>(&self, v: &'a Mapping) -> Option<(&'a Value, &'a Value)> {
+        (**self).key_value_into(v)
+    }
     fn index_into_mut<'a>(&self, v: &'a mut Mapping) -> Option<&'a mut Value> {
         (**self).index_into_mut(v)
     }
@@ -403,77 +482,6 @@ impl PartialOrd for Mapping {
         let mut self_entries = Vec::from_iter(self);
         let mut other_entries = Vec::from_iter(other);
 
-        // Sort in an arbitrary order that is consistent with Value's PartialOrd
-        // impl.
-        fn total_cmp(a: &Value, b: &Value) -> Ordering {
-            match (a, b) {
-                (Value::Null, Value::Null) => Ordering::Equal,
-                (Value::Null, _) => Ordering::Less,
-                (_, Value::Null) => Ordering::Greater,
-
-                (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
-                (Value::Bool(_), _) => Ordering::Less,
-                (_, Value::Bool(_)) => Ordering::Greater,
-
-                (Value::Number(a), Value::Number(b)) => a.total_cmp(b),
-                (Value::Number(_), _) => Ordering::Less,
-                (_, Value::Number(_)) => Ordering::Greater,
-
-                (Value::String(a), Value::String(b)) => a.cmp(b),
-                (Value::String(_), _) => Ordering::Less,
-                (_, Value::String(_)) => Ordering::Greater,
-
-                (Value::Sequence(a), Value::Sequence(b)) => iter_cmp_by(a, b, total_cmp),
-                (Value::Sequence(_), _) => Ordering::Less,
-                (_, Value::Sequence(_)) => Ordering::Greater,
-
-                (Value::Mapping(a), Value::Mapping(b)) => {
-                    iter_cmp_by(a, b, |(ak, av), (bk, bv)| {
-                        total_cmp(ak, bk).then_with(|| total_cmp(av, bv))
-                    })
-                }
-                (Value::Mapping(_), _) => Ordering::Less,
-                (_, Value::Mapping(_)) => Ordering::Greater,
-
-                (Value::Tagged(a), Value::Tagged(b)) => a
-                    .tag
-                    .cmp(&b.tag)
-                    .then_with(|| total_cmp(&a.value, &b.value)),
-            }
-        }
-
-        fn iter_cmp_by<I, F>(this: I, other: I, mut cmp: F) -> Ordering
-        where
-            I: IntoIterator,
-            F: FnMut(I::Item, I::Item) -> Ordering,
-        {
-            let mut this = this.into_iter();
-            let mut other = other.into_iter();
-
-            loop {
-                let x = match this.next() {
-                    None => {
-                        if other.next().is_none() {
-                            return Ordering::Equal;
-                        } else {
-                            return Ordering::Less;
-                        }
-                    }
-                    Some(val) => val,
-                };
-
-                let y = match other.next() {
-                    None => return Ordering::Greater,
-                    Some(val) => val,
-                };
-
-                match cmp(x, y) {
-                    Ordering::Equal => {}
-                    non_eq => return non_eq,
-                }
-            }
-        }
-
         // While sorting by map key, we get to assume that no two keys are
         // equal, otherwise they wouldn't both be in the map. This is not a safe
         // assumption outside of this situation.
@@ -484,6 +492,82 @@ impl PartialOrd for Mapping {
     }
 }
 
+// The order of Value's Ord impl.
+pub(crate) fn total_cmp(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Null, _) => Ordering::Less,
+        (_, Value::Null) => Ordering::Greater,
+
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        (Value::Bool(_), _) => Ordering::Less,
+        (_, Value::Bool(_)) => Ordering::Greater,
+
+        (Value::Number(a), Value::Number(b)) => a.total_cmp(b),
+        (Value::Number(_), _) => Ordering::Less,
+        (_, Value::Number(_)) => Ordering::Greater,
+
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        (Value::String(_), _) => Ordering::Less,
+        (_, Value::String(_)) => Ordering::Greater,
+
+        (Value::Sequence(a), Value::Sequence(b)) => iter_cmp_by(a, b, total_cmp),
+        (Value::Sequence(_), _) => Ordering::Less,
+        (_, Value::Sequence(_)) => Ordering::Greater,
+
+        (Value::Mapping(a), Value::Mapping(b)) => {
+            // Mappings are equal regardless of the order of their entries,
+            // so they are compared in order of key.
+            let mut a = Vec::from_iter(a);
+            let mut b = Vec::from_iter(b);
+            a.sort_by(|(a, _), (b, _)| total_cmp(a, b));
+            b.sort_by(|(a, _), (b, _)| total_cmp(a, b));
+            iter_cmp_by(a, b, |(ak, av), (bk, bv)| {
+                total_cmp(ak, bk).then_with(|| total_cmp(av, bv))
+            })
+        }
+        (Value::Mapping(_), _) => Ordering::Less,
+        (_, Value::Mapping(_)) => Ordering::Greater,
+
+        (Value::Tagged(a), Value::Tagged(b)) => a
+            .tag
+            .cmp(&b.tag)
+            .then_with(|| total_cmp(&a.value, &b.value)),
+    }
+}
+
+fn iter_cmp_by<I, F>(this: I, other: I, mut cmp: F) -> Ordering
+where
+    I: IntoIterator,
+    F: FnMut(I::Item, I::Item) -> Ordering,
+{
+    let mut this = this.into_iter();
+    let mut other = other.into_iter();
+
+    loop {
+        let x = match this.next() {
+            None => {
+                if other.next().is_none() {
+                    return Ordering::Equal;
+                } else {
+                    return Ordering::Less;
+                }
+            }
+            Some(val) => val,
+        };
+
+        let y = match other.next() {
+            None => return Ordering::Greater,
+            Some(val) => val,
+        };
+
+        match cmp(x, y) {
+            Ordering::Equal => {}
+            non_eq => return non_eq,
+        }
+    }
+}
+
 impl<I> std::ops::Index<I> for Mapping
 where
     I: Index,
@@ -8,6 +8,13 @@ use std::hash::{Hash, Hasher};
 use std::str::FromStr;
 
 /// Represents a YAML number, whether integer or floating point.
+///
+/// Numbers implement `Hash` consistently with `PartialEq`, so that a [`Value`]
+/// containing them can be a `HashMap` key. An integer is never equal to a
+/// float, even `1` to `1.0`. All NaNs are equal to each other, and `0.0` is
+/// equal to `-0.0`.
+///
+/// [`Value`]: crate::Value
 #[derive(Clone, PartialEq, PartialOrd)]
 pub struct Number {
     n: N,
@@ -23,6 +30,10 @@ enum N {
     NegInt(i64),
     /// May be infinite or NaN.
     Float(f64),
+    /// Always greater than `u64::MAX`.
+    PosBigInt(u128),
+    /// Always less than `i64::MIN`.
+    NegBigInt(i128),
 }
 
 impl Number {
@@ -57,7 +68,7 @@ impl Number {
         match self.n {
             N::PosInt(v) => v <= i64::MAX as u64,
             N::NegInt(_) => true,
-            N::Float(_) => false,
+            N::Float(_) | N::PosBigInt(_) | N::NegBigInt(_) => false,
         }
     }
 
@@ -88,7 +99,7 @@ impl Number {
     pub fn is_u64(&self) -> bool {
         match self.n {
             N::PosInt(_) => true,
-            N::NegInt(_) | N::Float(_) => false,
+            N::NegInt(_) | N::Float(_) | N::PosBigInt(_) | N::NegBigInt(_) => false,
         }
     }
 
@@ -120,7 +131,7 @@ impl Number {
     pub fn is_f64(&self) -> bool {
         match self.n {
             N::Float(_) => true,
-            N::PosInt(_) | N::NegInt(_) => false,
+            N::PosInt(_) | N::NegInt(_) | N::PosBigInt(_) | N::NegBigInt(_) => false,
         }
     }
 
@@ -153,7 +164,7 @@ impl Number {
                 }
             }
             N::NegInt(n) => Some(n),
-            N::Float(_) => None,
+            N::Float(_) | N::PosBigInt(_) | N::NegBigInt(_) => None,
         }
     }
 
@@ -178,7 +189,61 @@ impl Number {
     pub fn as_u64(&self) -> Option<u64> {
         match self.n {
             N::PosInt(n) => Some(n),
-            N::NegInt(_) | N::Float(_) => None,
+            N::NegInt(_) | N::Float(_) | N::PosBigInt(_) | N::NegBigInt(_) => None,
+        }
+    }
+
+    /// If the `Number` is an integer, represent it as i128 if possible.
+    /// Returns None otherwise.
+    ///
+    /// ```
+    /// # fn main() -> serde_yaml_ng::Result<()> {
+    /// let v: serde_yaml_ng::Value = serde_yaml_ng::from_str(r#"
+    /// a: -170141183460469231731687303715884105728
+    /// b: 340282366920938463463374607431768211455
+    /// c: 256.0
+    /// "#)?;
+    ///
+    /// assert_eq!(v["a"].as_i128(), Some(i128::MIN));
+    /// assert_eq!(v["b"].as_i128(), None);
+    /// assert_eq!(v["c"].as_i128(), None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn as_i128(&self) -> Option<i128> {
+        match self.n {
+            N::PosInt(n) => Some(n as i128),
+            N::NegInt(n) => Some(n as i128),
+            N::PosBigInt(n) => i128::try_from(n).ok(),
+            N::NegBigInt(n) => Some(n),
+            N::Float(_) => None,
+        }
+    }
+
+    /// If the `Number` is an integer, represent it as u128 if possible.
+    /// Returns None otherwise.
+    ///
+    /// ```
+    /// # fn main() -> serde_yaml_ng::Result<()> {
+    /// let v: serde_yaml_ng::Value = serde_yaml_ng::from_str(r#"
+    /// a: 340282366920938463463374607431768211455
+    /// b: -64
+    /// c: 256.0
+    /// "#)?;
+    ///
+    /// assert_eq!(v["a"].as_u128(), Some(u128::MAX));
+    /// assert_eq!(v["b"].as_u128(), None);
+    /// assert_eq!(v["c"].as_u128(), None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn as_u128(&self) -> Option<u128> {
+        match self.n {
+            N::PosInt(n) => Some(n as u128),
+            N::PosBigInt(n) => Some(n),
+            N::NegInt(_) | N::NegBigInt(_) | N::Float(_) => None,
         }
     }
 
@@ -218,6 +283,8 @@ impl Number {
             N::PosInt(n) => Some(n as f64),
             N::NegInt(n) => Some(n as f64),
             N::Float(n) => Some(n),
+            N::PosBigInt(n) => Some(n as f64),
+            N::NegBigInt(n) => Some(n as f64),
         }
     }
 
@@ -239,7 +306,7 @@ impl Number {
     #[inline]
     pub fn is_nan(&self) -> bool {
         match self.n {
-            N::PosInt(_) | N::NegInt(_) => false,
+            N::PosInt(_) | N::NegInt(_) | N::PosBigInt(_) | N::NegBigInt(_) => false,
             N::Float(f) => f.is_nan(),
         }
     }
@@ -263,7 +330,7 @@ impl Number {
     #[inline]
     pub fn is_infinite(&self) -> bool {
         match self.n {
-            N::PosInt(_) | N::NegInt(_) => false,
+            N::PosInt(_) | N::NegInt(_) | N::PosBigInt(_) | N::NegBigInt(_) => false,
             N::Float(f) => f.is_infinite(),
         }
     }
@@ -286,7 +353,7 @@ impl Number {
     #[inline]
     pub fn is_finite(&self) -> bool {
         match self.n {
-            N::PosInt(_) | N::NegInt(_) => true,
+            N::PosInt(_) | N::NegInt(_) | N::PosBigInt(_) | N::NegBigInt(_) => true,
             N::Float(f) => f.is_finite(),
         }
     }
@@ -297,6 +364,8 @@ impl Display for Number {
         match self.n {
             N::PosInt(i) => formatter.write_str(itoa::Buffer::new().format(i)),
             N::NegInt(i) => formatter.write_str(itoa::Buffer::new().format(i)),
+            N::PosBigInt(i) => formatter.write_str(itoa::Buffer::new().format(i)),
+            N::NegBigInt(i) => formatter.write_str(itoa::Buffer::new().format(i)),
             N::Float(f) if f.is_nan() => formatter.write_str(".nan"),
             N::Float(f) if f.is_infinite() => {
                 if f.is_sign_negative() {
@@ -331,6 +400,8 @@ impl PartialEq for N {
         match (*self, *other) {
             (N::PosInt(a), N::PosInt(b)) => a == b,
             (N::NegInt(a), N::NegInt(b)) => a == b,
+            (N::PosBigInt(a), N::PosBigInt(b)) => a == b,
+            (N::NegBigInt(a), N::NegBigInt(b)) => a == b,
             (N::Float(a), N::Float(b)) => {
                 if a.is_nan() && b.is_nan() {
                     // YAML only has one NaN;
@@ -366,9 +437,16 @@ impl N {
         match (*self, *other) {
             (N::PosInt(a), N::PosInt(b)) => a.cmp(&b),
             (N::NegInt(a), N::NegInt(b)) => a.cmp(&b),
+            (N::PosBigInt(a), N::PosBigInt(b)) => a.cmp(&b),
+            (N::NegBigInt(a), N::NegBigInt(b)) => a.cmp(&b),
             // negint is always less than zero
             (N::NegInt(_), N::PosInt(_)) => Ordering::Less,
             (N::PosInt(_), N::NegInt(_)) => Ordering::Greater,
+            // big ints are always out of range of the 64-bit variants
+            (N::NegBigInt(_), N::NegInt(_) | N::PosInt(_) | N::PosBigInt(_)) => Ordering::Less,
+            (N::NegInt(_) | N::PosInt(_) | N::PosBigInt(_), N::NegBigInt(_)) => Ordering::Greater,
+            (N::PosBigInt(_), N::NegInt(_) | N::PosInt(_)) => Ordering::Greater,
+            (N::NegInt(_) | N::PosInt(_), N::PosBigInt(_)) => Ordering::Less,
             (N::Float(a), N::Float(b)) => a.partial_cmp(&b).unwrap_or_else(|| {
                 // arbitrarily sort the NaN last
                 if !a.is_nan() {
@@ -379,14 +457,63 @@ impl N {
                     Ordering::Equal
                 }
             }),
-            // arbitrarily sort integers below floats
-            // FIXME: maybe something more sensible?
-            (_, N::Float(_)) => Ordering::Less,
-            (N::Float(_), _) => Ordering::Greater,
+            (N::Float(b), a) => cmp_int_float(a, b).reverse(),
+            (a, N::Float(b)) => cmp_int_float(a, b),
         }
     }
 }
 
+// Compares an integer with a float by their numeric values. NaN is greater
+// than every integer, and an integer is less than a float of the same value so
+// that `1` and `1.0`, which are not equal, still have an order.
+fn cmp_int_float(int: N, float: f64) -> Ordering {
+    if float.is_nan() {
+        return Ordering::Less;
+    }
+    let ordering = match int {
+        N::PosInt(u) => cmp_nonnegative_float(u as u128, float),
+        N::PosBigInt(u) => cmp_nonnegative_float(u, float),
+        N::NegInt(i) => cmp_negative_float(i as i128, float),
+        N::NegBigInt(i) => cmp_negative_float(i, float),
+        N::Float(_) => unreachable!(),
+    };
+    ordering.then(Ordering::Less)
+}
+
+fn cmp_nonnegative_float(int: u128, float: f64) -> Ordering {
+    // 2^128, which is exactly representable as f64.
+    const U128_END: f64 = 340_282_366_920_938_463_463_374_607_431_768_211_456.0;
+    if float < 0.0 {
+        return Ordering::Greater;
+    }
+    if float >= U128_END {
+        return Ordering::Less;
+    }
+    let trunc = float.trunc();
+    int.cmp(&(trunc as u128)).then(if float > trunc {
+        Ordering::Less
+    } else {
+        Ordering::Equal
+    })
+}
+
+fn cmp_negative_float(int: i128, float: f64) -> Ordering {
+    // -2^127, which is exactly representable as f64.
+    const I128_MIN: f64 = -170_141_183_460_469_231_731_687_303_715_884_105_728.0;
+    if float >= 0.0 {
+        return Ordering::Less;
+    }
+    if float < I128_MIN {
+        return Ordering::Greater;
+    }
+    let trunc = float.trunc();
+    int.cmp(&(trunc as i128)).then(if float < trunc {
+        Ordering::Greater
+    } else {
+        Ordering::Equal
+    })
+}
+
 impl Number {
     pub(crate) fn total_cmp(&self, other: &Self) -> Ordering {
         self.n.total_cmp(&other.n)
@@ -403,6 +530,8 @@ impl Serialize for Number {
             N::PosInt(i) => serializer.serialize_u64(i),
             N::NegInt(i) => serializer.serialize_i64(i),
             N::Float(f) => serializer.serialize_f64(f),
+            N::PosBigInt(i) => serializer.serialize_u128(i),
+            N::NegBigInt(i) => serializer.serialize_i128(i),
         }
     }
 }
@@ -426,6 +555,16 @@ impl<'de> Visitor<'de> for NumberVisitor {
         Ok(value.into())
     }
 
+    #[inline]
+    fn visit_i128<E>(self, value: i128) -> Result<Number, E> {
+        Ok(value.into())
+    }
+
+    #[inline]
+    fn visit_u128<E>(self, value: u128) -> Result<Number, E> {
+        Ok(value.into())
+    }
+
     #[inline]
     fn visit_f64<E>(self, value: f64) -> Result<Number, E> {
         Ok(value.into())
@@ -454,6 +593,8 @@ impl<'de> Deserializer<'de> for Number {
             N::PosInt(i) => visitor.visit_u64(i),
             N::NegInt(i) => visitor.visit_i64(i),
             N::Float(f) => visitor.visit_f64(f),
+            N::PosBigInt(i) => visitor.visit_u128(i),
+            N::NegBigInt(i) => visitor.visit_i128(i),
         }
     }
 
@@ -476,6 +617,8 @@ impl<'de, 'a> Deserializer<'de> for &'a Number {
             N::PosInt(i) => visitor.visit_u64(i),
             N::NegInt(i) => visitor.visit_i64(i),
             N::Float(f) => visitor.visit_f64(f),
+            N::PosBigInt(i) => visitor.visit_u128(i),
+            N::NegBigInt(i) => visitor.visit_i128(i),
         }
     }
 
@@ -520,6 +663,34 @@ macro_rules! from_unsigned {
 from_signed!(i8 i16 i32 i64 isize);
 from_unsigned!(u8 u16 u32 u64 usize);
 
+impl From<i128> for Number {
+    #[inline]
+    fn from(i: i128) -> Self {
+        if let Ok(u) = u64::try_from(i) {
+            Number { n: N::PosInt(u) }
+        } else if let Ok(i) = i64::try_from(i) {
+            Number { n: N::NegInt(i) }
+        } else if i < 0 {
+            Number { n: N::NegBigInt(i) }
+        } else {
+            Number {
+                n: N::PosBigInt(i as u128),
+            }
+        }
+    }
+}
+
+impl From<u128> for Number {
+    #[inline]
+    fn from(u: u128) -> Self {
+        if let Ok(u) = u64::try_from(u) {
+            Number { n: N::PosInt(u) }
+        } else {
+            Number { n: N::PosBigInt(u) }
+        }
+    }
+}
+
 impl From<f32> for Number {
     fn from(f: f32) -> Self {
         Number::from(f as f64)
@@ -542,12 +713,22 @@ impl From<f64> for Number {
 impl Hash for Number {
     fn hash<H: Hasher>(&self, state: &mut H) {
         match self.n {
-            N::Float(_) => {
-                // you should feel bad for using f64 as a map key
-                3.hash(state);
+            N::Float(f) => {
+                // Consistent with PartialEq, which has a single NaN and treats
+                // 0.0 and -0.0 as equal.
+                let bits = if f.is_nan() {
+                    f64::NAN.to_bits()
+                } else if f == 0.0 {
+                    0
+                } else {
+                    f.to_bits()
+                };
+                bits.hash(state);
             }
             N::PosInt(u) => u.hash(state),
             N::NegInt(i) => i.hash(state),
+            N::PosBigInt(u) => u.hash(state),
+            N::NegBigInt(i) => i.hash(state),
         }
     }
 }
@@ -557,5 +738,6 @@ pub(crate) fn unexpected(number: &Number) -> Unexpected {
         N::PosInt(u) => Unexpected::Unsigned(u),
         N::NegInt(i) => Unexpected::Signed(i),
         N::Float(f) => Unexpected::Float(f),
+        N::PosBigInt(_) | N::NegBigInt(_) => Unexpected::Other("128-bit integer"),
     }
 }
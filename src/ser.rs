@@ -2,17 +2,29 @@
 //!
 //! This module provides YAML serialization with the type `Serializer`.
 
+use crate::document::Directives;
 use crate::error::{self, Error, ErrorImpl};
 use crate::libyaml;
-use crate::libyaml::emitter::{Emitter, Event, Mapping, Scalar, ScalarStyle, Sequence};
+use crate::libyaml::emitter::{
+    CollectionStyle, DocumentEnd, DocumentStart, Emitter, Event, Mapping, Scalar, ScalarStyle,
+    Sequence,
+};
+use crate::libyaml::parser::{Event as YamlEvent, Parser};
 use crate::value::tagged::{self, MaybeTag};
+use crate::value::{self, PathSegment, Value};
 use serde::de::Visitor;
-use serde::ser::{self, Serializer as _};
+use serde::ser;
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::HashMap;
 use std::fmt::{self, Display};
+use std::hash::BuildHasher;
 use std::io;
+use std::iter;
 use std::marker::PhantomData;
 use std::mem;
 use std::num;
+use std::rc::Rc;
 use std::str;
 
 type Result<T, E = Error> = std::result::Result<T, E>;
@@ -43,11 +55,314 @@ type Result<T, E = Error> = std::result::Result<T, E>;
 /// ```
 pub struct Serializer<W> {
     depth: usize,
+    max_depth: usize,
     state: State,
+    mapping_style: Style,
+    sequence_style: Style,
+    null_style: NullStyle,
+    quote_style: QuoteStyle,
+    multiline_strings: bool,
+    sequence_indent: SequenceIndent,
+    integral_floats_as_int: bool,
+    indent: usize,
+    // Shared with the Output at the bottom of the emitter.
+    sequence_shift: Rc<Cell<usize>>,
+    align_colons: Rc<Cell<bool>>,
+    directives: Directives,
+    explicit_start: bool,
+    explicit_end: bool,
+    // Number of enclosing flow collections.
+    flow_depth: usize,
+    flow_threshold: Option<usize>,
+    pending: Option<PendingCollection>,
+    in_key: bool,
+    scalar_formatter: Option<Box<ScalarFormatter>>,
+    // Where the next scalar goes; only kept up to date for scalar_formatter.
+    path: Vec<PathSegment>,
+    // Index of the next element of each enclosing sequence.
+    indices: Vec<usize>,
+    // The key whose value is serialized next.
+    pending_key: Option<Value>,
     emitter: Emitter<'static>,
     writer: PhantomData<W>,
 }
 
+type ScalarFormatter = dyn FnMut(&Value, &[PathSegment]) -> Option<String>;
+
+// A collection held back by flow_threshold until it is known whether it is
+// small enough for flow style.
+struct PendingCollection {
+    mapping: bool,
+    tag: Option<String>,
+    // Number of scalars that still fit.
+    limit: usize,
+    scalars: Vec<PendingScalar>,
+}
+
+struct PendingScalar {
+    tag: Option<String>,
+    value: String,
+    style: ScalarStyle,
+    in_key: bool,
+}
+
+/// How the [`Serializer`] lays out mappings or sequences.
+///
+/// YAML does not allow block collections inside of flow collections, so
+/// anything nested inside a flow mapping or flow sequence is emitted in flow
+/// style regardless of its own setting. Empty collections are always written
+/// as `{}` or `[]`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Style {
+    /// Let the emitter decide. This is block style, as in `Style::Block`.
+    #[default]
+    Any,
+    /// One entry per line, structure given by indentation.
+    ///
+    /// ```yaml
+    /// k: v
+    /// seq:
+    /// - a
+    /// - b
+    /// ```
+    Block,
+    /// Entries delimited by braces or brackets.
+    ///
+    /// ```yaml
+    /// {k: v, seq: [a, b]}
+    /// ```
+    Flow,
+}
+
+impl Style {
+    fn to_emitter(self) -> CollectionStyle {
+        match self {
+            Style::Any => CollectionStyle::Any,
+            Style::Block => CollectionStyle::Block,
+            Style::Flow => CollectionStyle::Flow,
+        }
+    }
+}
+
+/// How the [`Serializer`] writes null values, such as `()` and `None`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum NullStyle {
+    /// `key: null`
+    #[default]
+    Keyword,
+    /// `key: ~`
+    Tilde,
+    /// `key:`
+    ///
+    /// An empty scalar is not possible everywhere: inside of flow collections
+    /// it would be read back as a string, and as a mapping key it would need
+    /// quotes, so `null` is written in those positions instead.
+    Empty,
+}
+
+/// Which strings the [`Serializer`] quotes, and with which quotes.
+///
+/// Under [`Single`][QuoteStyle::Single] and [`Double`][QuoteStyle::Double],
+/// a string is quoted only when it would otherwise read back as something
+/// else, like `true` or `1.0`, or when it contains indicators like `: ` or a
+/// leading `- `. The `Always` styles quote every string, including mapping
+/// keys, but leave strings with a newline to
+/// [`multiline_strings`][Serializer::multiline_strings]. Numbers, booleans and
+/// null are never quoted. A string with control characters can only be
+/// written with double quotes, so that is what it gets under any style.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum QuoteStyle {
+    /// `key: 'it''s: true'`
+    ///
+    /// Single quotes inside the string are doubled.
+    #[default]
+    Single,
+    /// `key: "it's: true"`
+    ///
+    /// Double quotes, backslashes and control characters inside the string
+    /// are escaped with a backslash.
+    Double,
+    /// `key: 'plain'`
+    ///
+    /// Like [`Single`][QuoteStyle::Single], but for every string.
+    AlwaysSingle,
+    /// `key: "plain"`
+    ///
+    /// Like [`Double`][QuoteStyle::Double], but for every string.
+    AlwaysDouble,
+}
+
+/// Where the [`Serializer`] puts the dashes of a block sequence that is the
+/// value of a mapping entry.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum SequenceIndent {
+    /// Dashes at the same indentation as the key.
+    ///
+    /// ```yaml
+    /// key:
+    /// - item
+    /// ```
+    #[default]
+    Same,
+    /// Dashes indented one level further than the key.
+    ///
+    /// ```yaml
+    /// key:
+    ///   - item
+    /// ```
+    Indented,
+}
+
+/// A bundle of formatting settings, for use with
+/// [`Value::pretty_print_to`][crate::Value::pretty_print_to] and
+/// [`Value::to_string_with`][crate::Value::to_string_with].
+///
+/// ```
+/// use serde_yaml_ng::{SerializeOptions, Style, Value};
+///
+/// let value: Value = serde_yaml_ng::from_str("{b: [1, 2], a: {c: 3}}").unwrap();
+/// let options = SerializeOptions::new()
+///     .indent(4)
+///     .sort_keys(true)
+///     .sequence_style(Style::Flow);
+///
+/// assert_eq!(
+///     value.to_string_with(&options).unwrap(),
+///     "a:\n    c: 3\nb: [1, 2]\n",
+/// );
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SerializeOptions {
+    indent: Option<usize>,
+    sort_keys: bool,
+    sort_all_sequences: bool,
+    mapping_style: Style,
+    sequence_style: Style,
+    null_style: NullStyle,
+    quote_style: QuoteStyle,
+    multiline_strings: Option<bool>,
+    flow_threshold: Option<usize>,
+    sequence_indent: SequenceIndent,
+    integral_floats_as_int: bool,
+    line_width: Option<usize>,
+    align_colons: bool,
+}
+
+impl SerializeOptions {
+    /// The default settings, which produce the same output as
+    /// [`to_string`].
+    pub fn new() -> Self {
+        SerializeOptions::default()
+    }
+
+    /// See [`Serializer::indent`].
+    pub fn indent(mut self, indent: usize) -> Self {
+        self.indent = Some(indent);
+        self
+    }
+
+    /// Whether to write the entries of every mapping sorted by key rather
+    /// than in insertion order.
+    pub fn sort_keys(mut self, sort_keys: bool) -> Self {
+        self.sort_keys = sort_keys;
+        self
+    }
+
+    /// Whether to write the elements of every sequence in sorted order,
+    /// whatever the sequence holds.
+    ///
+    /// Serde serializes a `HashSet` as a plain sequence, in an order that
+    /// changes from run to run, and nothing in the output tells it apart from
+    /// a list whose order matters. This option therefore sorts every
+    /// sequence, including lists of steps, priorities and the like, whose
+    /// meaning it changes. Only turn it on for output in which the order of
+    /// no sequence matters, like snapshots of data built from sets. Values
+    /// are ordered null, booleans, numbers, strings, sequences, mappings,
+    /// then tagged values.
+    ///
+    /// ```
+    /// use serde_yaml_ng::{SerializeOptions, Value};
+    /// use std::collections::HashSet;
+    ///
+    /// let set: HashSet<i64> = [3, 1, 2].into_iter().collect();
+    /// let value = serde_yaml_ng::to_value(&set).unwrap();
+    /// let options = SerializeOptions::new().sort_all_sequences(true);
+    /// assert_eq!(value.to_string_with(&options).unwrap(), "- 1\n- 2\n- 3\n");
+    /// ```
+    pub fn sort_all_sequences(mut self, sort_all_sequences: bool) -> Self {
+        self.sort_all_sequences = sort_all_sequences;
+        self
+    }
+
+    /// See [`Serializer::mapping_style`].
+    pub fn mapping_style(mut self, style: Style) -> Self {
+        self.mapping_style = style;
+        self
+    }
+
+    /// See [`Serializer::sequence_style`].
+    pub fn sequence_style(mut self, style: Style) -> Self {
+        self.sequence_style = style;
+        self
+    }
+
+    /// See [`Serializer::flow_threshold`].
+    pub fn flow_threshold(mut self, max: usize) -> Self {
+        self.flow_threshold = Some(max);
+        self
+    }
+
+    /// See [`Serializer::null_style`].
+    pub fn null_style(mut self, style: NullStyle) -> Self {
+        self.null_style = style;
+        self
+    }
+
+    /// See [`Serializer::quote_style`].
+    pub fn quote_style(mut self, style: QuoteStyle) -> Self {
+        self.quote_style = style;
+        self
+    }
+
+    /// See [`Serializer::multiline_strings`].
+    pub fn multiline_strings(mut self, multiline_strings: bool) -> Self {
+        self.multiline_strings = Some(multiline_strings);
+        self
+    }
+
+    /// See [`Serializer::sequence_indent`].
+    pub fn sequence_indent(mut self, sequence_indent: SequenceIndent) -> Self {
+        self.sequence_indent = sequence_indent;
+        self
+    }
+
+    /// See [`Serializer::integral_floats_as_int`].
+    pub fn integral_floats_as_int(mut self, integral_floats_as_int: bool) -> Self {
+        self.integral_floats_as_int = integral_floats_as_int;
+        self
+    }
+
+    /// See [`Serializer::line_width`].
+    pub fn line_width(mut self, width: usize) -> Self {
+        self.line_width = Some(width);
+        self
+    }
+
+    /// See [`Serializer::align_colons`].
+    pub fn align_colons(mut self, align_colons: bool) -> Self {
+        self.align_colons = align_colons;
+        self
+    }
+
+    pub(crate) fn sorts_keys(&self) -> bool {
+        self.sort_keys
+    }
+
+    pub(crate) fn sorts_all_sequences(&self) -> bool {
+        self.sort_all_sequences
+    }
+}
+
 enum State {
     NothingInParticular,
     CheckForTag,
@@ -62,19 +377,422 @@ where
 {
     /// Creates a new YAML serializer.
     pub fn new(writer: W) -> Self {
+        let sequence_shift = Rc::new(Cell::new(0));
+        let align_colons = Rc::new(Cell::new(false));
         let mut emitter = Emitter::new({
-            let writer = Box::new(writer);
+            let writer = Box::new(Output {
+                writer,
+                shift: Rc::clone(&sequence_shift),
+                align: Rc::clone(&align_colons),
+                pending: Vec::new(),
+                line: Vec::new(),
+                sequences: Vec::new(),
+                previous_key: None,
+                continuation: Continuation::None,
+            });
             unsafe { mem::transmute::<Box<dyn io::Write>, Box<dyn io::Write>>(writer) }
         });
         emitter.emit(Event::StreamStart).unwrap();
         Serializer {
             depth: 0,
+            max_depth: usize::MAX,
             state: State::NothingInParticular,
+            mapping_style: Style::Any,
+            sequence_style: Style::Any,
+            null_style: NullStyle::Keyword,
+            quote_style: QuoteStyle::Single,
+            multiline_strings: true,
+            sequence_indent: SequenceIndent::Same,
+            integral_floats_as_int: false,
+            indent: 2,
+            sequence_shift,
+            align_colons,
+            directives: Directives::default(),
+            explicit_start: false,
+            explicit_end: false,
+            flow_depth: 0,
+            flow_threshold: None,
+            pending: None,
+            in_key: false,
+            scalar_formatter: None,
+            path: Vec::new(),
+            indices: Vec::new(),
+            pending_key: None,
             emitter,
             writer: PhantomData,
         }
     }
 
+    /// Sets the style used for every mapping (maps, structs, and the
+    /// single-entry mappings of externally tagged enums).
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_yaml_ng::{Serializer, Style};
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut buffer = Vec::new();
+    /// let mut ser = Serializer::new(&mut buffer).mapping_style(Style::Flow);
+    /// BTreeMap::from([("a", 1), ("b", 2)]).serialize(&mut ser).unwrap();
+    /// assert_eq!(buffer, b"{a: 1, b: 2}\n");
+    /// ```
+    pub fn mapping_style(mut self, style: Style) -> Self {
+        self.mapping_style = style;
+        self
+    }
+
+    /// Sets the style used for every sequence (sequences, tuples, and tuple
+    /// structs).
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_yaml_ng::{Serializer, Style};
+    ///
+    /// let mut buffer = Vec::new();
+    /// let mut ser = Serializer::new(&mut buffer).sequence_style(Style::Flow);
+    /// vec![1, 2, 3].serialize(&mut ser).unwrap();
+    /// assert_eq!(buffer, b"[1, 2, 3]\n");
+    /// ```
+    pub fn sequence_style(mut self, style: Style) -> Self {
+        self.sequence_style = style;
+        self
+    }
+
+    /// Writes a mapping or sequence in flow style, like `[1, 2, 3]` or
+    /// `{a: 1}`, when it holds at most `max` entries and all of them are
+    /// scalars.
+    ///
+    /// A collection with a nested mapping or sequence, or with more than
+    /// `max` entries, stays in block style, while the small collections
+    /// inside of it can still be flow. Entries of a mapping count once per
+    /// key-value pair. Strings with a newline keep a collection in block
+    /// style, as do empty nulls from [`NullStyle::Empty`]. This only affects
+    /// mappings and sequences whose [`mapping_style`][Self::mapping_style] or
+    /// [`sequence_style`][Self::sequence_style] is left at [`Style::Any`].
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_yaml_ng::Serializer;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut buffer = Vec::new();
+    /// let mut ser = Serializer::new(&mut buffer).flow_threshold(3);
+    /// BTreeMap::from([("small", vec![1, 2, 3]), ("large", vec![1, 2, 3, 4])])
+    ///     .serialize(&mut ser)
+    ///     .unwrap();
+    /// assert_eq!(buffer, b"large:\n- 1\n- 2\n- 3\n- 4\nsmall: [1, 2, 3]\n");
+    /// ```
+    pub fn flow_threshold(mut self, max: usize) -> Self {
+        self.flow_threshold = Some(max);
+        self
+    }
+
+    /// Sets how null values are written.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_yaml_ng::{NullStyle, Serializer};
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut buffer = Vec::new();
+    /// let mut ser = Serializer::new(&mut buffer).null_style(NullStyle::Tilde);
+    /// BTreeMap::from([("k", None::<u8>)]).serialize(&mut ser).unwrap();
+    /// assert_eq!(buffer, b"k: ~\n");
+    /// ```
+    pub fn null_style(mut self, style: NullStyle) -> Self {
+        self.null_style = style;
+        self
+    }
+
+    /// Sets which strings are quoted, and with which quotes. See
+    /// [`QuoteStyle`].
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_yaml_ng::{QuoteStyle, Serializer};
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut buffer = Vec::new();
+    /// let mut ser = Serializer::new(&mut buffer).quote_style(QuoteStyle::Double);
+    /// BTreeMap::from([("a", "true"), ("b", "x: y"), ("c", "z")])
+    ///     .serialize(&mut ser)
+    ///     .unwrap();
+    /// assert_eq!(buffer, b"a: \"true\"\nb: \"x: y\"\nc: z\n");
+    /// ```
+    pub fn quote_style(mut self, style: QuoteStyle) -> Self {
+        self.quote_style = style;
+        self
+    }
+
+    /// Whether to write strings that contain a newline as literal block
+    /// scalars (`|`). On by default.
+    ///
+    /// The block header carries whatever the string needs to read back
+    /// unchanged: `|` keeps a single trailing newline, `|-` strips it when
+    /// there is none, `|+` keeps several, and an indentation indicator like
+    /// `|2` is added when the first line starts with a space. Strings with
+    /// spaces at the end of a line cannot be written as block scalars and are
+    /// double quoted instead. When turned off, every string with a newline is
+    /// written double quoted on one line, with the newlines escaped.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_yaml_ng::Serializer;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut buffer = Vec::new();
+    /// let mut ser = Serializer::new(&mut buffer);
+    /// BTreeMap::from([("k", "a\nb")]).serialize(&mut ser).unwrap();
+    /// assert_eq!(buffer, b"k: |-\n  a\n  b\n");
+    ///
+    /// let mut buffer = Vec::new();
+    /// let mut ser = Serializer::new(&mut buffer).multiline_strings(false);
+    /// BTreeMap::from([("k", "a\nb")]).serialize(&mut ser).unwrap();
+    /// assert_eq!(buffer, b"k: \"a\\nb\"\n");
+    /// ```
+    pub fn multiline_strings(mut self, multiline_strings: bool) -> Self {
+        self.multiline_strings = multiline_strings;
+        self
+    }
+
+    /// Sets the number of spaces per level of block indentation, between 2
+    /// and 9. Other values fall back to the default of 2.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_yaml_ng::Serializer;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut buffer = Vec::new();
+    /// let mut ser = Serializer::new(&mut buffer).indent(4);
+    /// BTreeMap::from([("k", BTreeMap::from([("x", 1)]))]).serialize(&mut ser).unwrap();
+    /// assert_eq!(buffer, b"k:\n    x: 1\n");
+    /// ```
+    pub fn indent(mut self, indent: usize) -> Self {
+        self.emitter.set_indent(indent);
+        // Same fallback as the emitter's.
+        self.indent = if (2..=9).contains(&indent) { indent } else { 2 };
+        self.update_sequence_shift();
+        self
+    }
+
+    /// Sets where the dashes of a block sequence go when the sequence is the
+    /// value of a mapping entry.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_yaml_ng::{SequenceIndent, Serializer};
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut buffer = Vec::new();
+    /// let mut ser = Serializer::new(&mut buffer).sequence_indent(SequenceIndent::Indented);
+    /// BTreeMap::from([("k", ["a", "b"])]).serialize(&mut ser).unwrap();
+    /// assert_eq!(buffer, b"k:\n  - a\n  - b\n");
+    /// ```
+    pub fn sequence_indent(mut self, sequence_indent: SequenceIndent) -> Self {
+        self.sequence_indent = sequence_indent;
+        self.update_sequence_shift();
+        self
+    }
+
+    /// Whether to write floats that have no fractional part, like `3.0`, as
+    /// integers, like `3`. Off by default.
+    ///
+    /// Only floats in the range of `i64` are affected. The output reads back
+    /// as an integer rather than a float, so this loses the distinction
+    /// between the two for consumers of `Value`, though deserializing into a
+    /// float type still works.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_yaml_ng::Serializer;
+    ///
+    /// let mut buffer = Vec::new();
+    /// let mut ser = Serializer::new(&mut buffer).integral_floats_as_int(true);
+    /// [3.0, 2.5].serialize(&mut ser).unwrap();
+    /// assert_eq!(buffer, b"- 3\n- 2.5\n");
+    /// ```
+    pub fn integral_floats_as_int(mut self, integral_floats_as_int: bool) -> Self {
+        self.integral_floats_as_int = integral_floats_as_int;
+        self
+    }
+
+    /// Sets the preferred width of a line, past which the emitter breaks the
+    /// line where it can. By default lines are never broken.
+    ///
+    /// Flow sequences and flow mappings continue on the next line once an
+    /// entry ends beyond this width, so a long list of short scalars is laid
+    /// out as rows instead of one entry per line. Strings that contain spaces
+    /// are folded at the same width. A line can still run past the width,
+    /// for example if a single entry or word is longer than it.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_yaml_ng::{Serializer, Style};
+    ///
+    /// let mut buffer = Vec::new();
+    /// let mut ser = Serializer::new(&mut buffer)
+    ///     .sequence_style(Style::Flow)
+    ///     .line_width(10);
+    /// (1..=9).collect::<Vec<_>>().serialize(&mut ser).unwrap();
+    /// assert_eq!(buffer, b"[1, 2, 3, 4,\n  5, 6, 7, 8,\n  9]\n");
+    /// ```
+    pub fn line_width(mut self, width: usize) -> Self {
+        self.emitter
+            .set_width(i32::try_from(width).unwrap_or(i32::MAX));
+        self
+    }
+
+    /// Whether to pad the keys of each block mapping with spaces so that
+    /// their colons, and the values after them, line up. Off by default.
+    ///
+    /// Only entries whose value starts on the same line as the key take part.
+    /// The padding goes between the key and its colon, where YAML ignores it,
+    /// so the output reads back the same.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_yaml_ng::Serializer;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut buffer = Vec::new();
+    /// let mut ser = Serializer::new(&mut buffer).align_colons(true);
+    /// BTreeMap::from([("host", "localhost"), ("port", "80"), ("timeout", "30s")])
+    ///     .serialize(&mut ser)
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(buffer).unwrap(),
+    ///     "host   : localhost\nport   : '80'\ntimeout: 30s\n",
+    /// );
+    /// ```
+    pub fn align_colons(self, align_colons: bool) -> Self {
+        self.align_colons.set(align_colons);
+        self
+    }
+
+    /// Sets how many sequences and mappings may be nested inside one another.
+    /// Unlimited by default.
+    ///
+    /// Serializing a value that nests deeper returns an error rather than
+    /// risking a stack overflow. The deserializer stops at a depth of 128, so
+    /// that is the deepest output that can be read back.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_yaml_ng::Serializer;
+    ///
+    /// let mut buffer = Vec::new();
+    /// let mut ser = Serializer::new(&mut buffer).max_depth(2);
+    /// let err = vec![vec![vec![1]]].serialize(&mut ser).unwrap_err();
+    /// assert_eq!(err.to_string(), "nesting depth limit of 2 exceeded");
+    /// ```
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Sets a function that may take over writing scalars.
+    ///
+    /// The function is called for every scalar value, other than mapping
+    /// keys, with the scalar as a `Value` and the path to it from the root of
+    /// the document. If it returns `Some`, that text is written in place of
+    /// the scalar, as a plain scalar where YAML allows one and quoted
+    /// otherwise. If it returns `None`, the scalar is written as usual.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_yaml_ng::value::PathSegment;
+    /// use serde_yaml_ng::{Serializer, Value};
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut buffer = Vec::new();
+    /// let mut ser = Serializer::new(&mut buffer).scalar_formatter(|value, path| {
+    ///     match (value, path.last()) {
+    ///         (Value::Bool(b), Some(PathSegment::Key(key))) if key == "enabled" => {
+    ///             Some(if *b { "yes" } else { "no" }.to_owned())
+    ///         }
+    ///         _ => None,
+    ///     }
+    /// });
+    /// BTreeMap::from([("enabled", true)]).serialize(&mut ser).unwrap();
+    /// assert_eq!(buffer, b"enabled: yes\n");
+    /// ```
+    pub fn scalar_formatter<F>(mut self, formatter: F) -> Self
+    where
+        F: FnMut(&Value, &[PathSegment]) -> Option<String> + 'static,
+    {
+        self.scalar_formatter = Some(Box::new(formatter));
+        self
+    }
+
+    pub(crate) fn apply_options(mut self, options: &SerializeOptions) -> Self {
+        if let Some(indent) = options.indent {
+            self = self.indent(indent);
+        }
+        self.mapping_style = options.mapping_style;
+        self.sequence_style = options.sequence_style;
+        self.null_style = options.null_style;
+        self.quote_style = options.quote_style;
+        if let Some(multiline_strings) = options.multiline_strings {
+            self.multiline_strings = multiline_strings;
+        }
+        self.flow_threshold = options.flow_threshold;
+        self.sequence_indent = options.sequence_indent;
+        self.update_sequence_shift();
+        self.integral_floats_as_int = options.integral_floats_as_int;
+        if let Some(width) = options.line_width {
+            self = self.line_width(width);
+        }
+        self.align_colons.set(options.align_colons);
+        self
+    }
+
+    fn update_sequence_shift(&self) {
+        self.sequence_shift.set(match self.sequence_indent {
+            SequenceIndent::Same => 0,
+            SequenceIndent::Indented => self.indent,
+        });
+    }
+
+    /// Sets the `%YAML` and `%TAG` directives to write at the start of each
+    /// document serialized from now on.
+    ///
+    /// Tags that begin with one of the declared handles, like `!e!widget`
+    /// for the handle `!e!`, are written in that shorthand form.
+    ///
+    /// ```
+    /// use serde::Serialize;
+    /// use serde_yaml_ng::value::{Tag, TaggedValue};
+    /// use serde_yaml_ng::{Directives, Serializer, TagDirective, Value};
+    ///
+    /// let mut buffer = Vec::new();
+    /// let mut ser = Serializer::new(&mut buffer);
+    /// ser.set_directives(Directives {
+    ///     version: None,
+    ///     tags: vec![TagDirective {
+    ///         handle: "!e!".to_owned(),
+    ///         prefix: "tag:example.com,2000:".to_owned(),
+    ///     }],
+    /// });
+    ///
+    /// let value = Value::Tagged(Box::new(TaggedValue {
+    ///     tag: Tag::new("!e!widget"),
+    ///     value: Value::from("x"),
+    /// }));
+    /// value.serialize(&mut ser).unwrap();
+    /// assert_eq!(buffer, b"%TAG !e! tag:example.com,2000:\n--- !e!widget x\n");
+    /// ```
+    pub fn set_directives(&mut self, directives: Directives) {
+        self.directives = directives;
+    }
+
+    // Writes `---` and `...` around each document serialized from now on, even
+    // where they could be left out.
+    pub(crate) fn set_explicit_markers(&mut self, explicit_start: bool, explicit_end: bool) {
+        self.explicit_start = explicit_start;
+        self.explicit_end = explicit_end;
+    }
+
     /// Calls [`.flush()`](io::Write::flush) on the underlying `io::Write`
     /// object.
     pub fn flush(&mut self) -> Result<()> {
@@ -87,48 +805,280 @@ where
         self.emitter.emit(Event::StreamEnd)?;
         self.emitter.flush()?;
         let writer = self.emitter.into_inner();
-        Ok(*unsafe { Box::from_raw(Box::into_raw(writer).cast::<W>()) })
+        let output = *unsafe { Box::from_raw(Box::into_raw(writer).cast::<Output<W>>()) };
+        Ok(output.writer)
     }
 
-    fn emit_scalar(&mut self, mut scalar: Scalar) -> Result<()> {
+    fn emit_scalar<F>(&mut self, value: F, scalar: Scalar) -> Result<()>
+    where
+        F: FnOnce() -> Value,
+    {
+        let formatted = match &mut self.scalar_formatter {
+            Some(formatter) if !self.in_key => formatter(&value(), &self.path),
+            _ => None,
+        };
+        let mut scalar = match &formatted {
+            Some(formatted) => Scalar {
+                tag: None,
+                value: formatted,
+                style: ScalarStyle::Plain,
+            },
+            None => scalar,
+        };
+        if formatted.is_none()
+            && matches!(scalar.style, ScalarStyle::Any)
+            && matches!(
+                self.quote_style,
+                QuoteStyle::AlwaysSingle | QuoteStyle::AlwaysDouble
+            )
+        {
+            scalar.style = ScalarStyle::SingleQuoted;
+        }
+        if let QuoteStyle::Double | QuoteStyle::AlwaysDouble = self.quote_style {
+            scalar.style = match scalar.style {
+                ScalarStyle::SingleQuoted => ScalarStyle::DoubleQuoted,
+                ScalarStyle::Any | ScalarStyle::Plain
+                    if single_quoted_by_emitter(scalar.value, self.flow_depth > 0, self.in_key) =>
+                {
+                    ScalarStyle::DoubleQuoted
+                }
+                style => style,
+            };
+        }
         self.flush_mapping_start()?;
         if let Some(tag) = self.take_tag() {
             scalar.tag = Some(tag);
         }
         self.value_start()?;
-        self.emitter.emit(Event::Scalar(scalar))?;
+        self.emit_scalar_event(scalar)?;
         self.value_end()
     }
 
     fn emit_sequence_start(&mut self) -> Result<()> {
         self.flush_mapping_start()?;
+        self.check_depth()?;
         self.value_start()?;
         let tag = self.take_tag();
-        self.emitter.emit(Event::SequenceStart(Sequence { tag }))?;
-        Ok(())
+        self.enter_collection(self.sequence_style);
+        self.indices.push(0);
+        self.emit_collection_start(false, tag, self.sequence_style)
     }
 
     fn emit_sequence_end(&mut self) -> Result<()> {
+        self.leave_collection();
+        self.indices.pop();
+        self.flush_pending(true)?;
         self.emitter.emit(Event::SequenceEnd)?;
         self.value_end()
     }
 
     fn emit_mapping_start(&mut self) -> Result<()> {
         self.flush_mapping_start()?;
+        self.check_depth()?;
         self.value_start()?;
         let tag = self.take_tag();
-        self.emitter.emit(Event::MappingStart(Mapping { tag }))?;
-        Ok(())
+        self.enter_collection(self.mapping_style);
+        self.emit_collection_start(true, tag, self.mapping_style)
     }
 
     fn emit_mapping_end(&mut self) -> Result<()> {
+        self.leave_collection();
+        self.flush_pending(true)?;
         self.emitter.emit(Event::MappingEnd)?;
         self.value_end()
     }
 
+    fn emit_collection_start(
+        &mut self,
+        mapping: bool,
+        tag: Option<String>,
+        style: Style,
+    ) -> Result<()> {
+        // A collection that contains another one is not flow.
+        self.flush_pending(false)?;
+        if let (Some(max), Style::Any, 0) = (self.flow_threshold, style, self.flow_depth) {
+            self.pending = Some(PendingCollection {
+                mapping,
+                tag,
+                limit: if mapping { max.saturating_mul(2) } else { max },
+                scalars: Vec::new(),
+            });
+            return Ok(());
+        }
+        let style = style.to_emitter();
+        self.emitter.emit(if mapping {
+            Event::MappingStart(Mapping { tag, style })
+        } else {
+            Event::SequenceStart(Sequence { tag, style })
+        })?;
+        Ok(())
+    }
+
+    fn emit_scalar_event(&mut self, scalar: Scalar) -> Result<()> {
+        if let Some(pending) = &mut self.pending {
+            let fits_flow = match scalar.style {
+                ScalarStyle::Literal => false,
+                ScalarStyle::Plain => !scalar.value.is_empty(),
+                _ => true,
+            };
+            if fits_flow && pending.limit > 0 {
+                pending.limit -= 1;
+                pending.scalars.push(PendingScalar {
+                    tag: scalar.tag,
+                    value: scalar.value.to_owned(),
+                    style: scalar.style,
+                    in_key: self.in_key,
+                });
+                return Ok(());
+            }
+            self.flush_pending(false)?;
+        }
+        self.emitter.emit(Event::Scalar(scalar))?;
+        Ok(())
+    }
+
+    // Writes out the collection held back by flow_threshold, if any, along
+    // with the scalars it has so far.
+    fn flush_pending(&mut self, flow: bool) -> Result<()> {
+        let pending = match self.pending.take() {
+            Some(pending) => pending,
+            None => return Ok(()),
+        };
+        let style = if flow {
+            CollectionStyle::Flow
+        } else {
+            CollectionStyle::Block
+        };
+        let tag = pending.tag;
+        self.emitter.emit(if pending.mapping {
+            Event::MappingStart(Mapping { tag, style })
+        } else {
+            Event::SequenceStart(Sequence { tag, style })
+        })?;
+        let double = matches!(
+            self.quote_style,
+            QuoteStyle::Double | QuoteStyle::AlwaysDouble
+        );
+        for scalar in pending.scalars {
+            // Flow style needs quotes in more places, which emit_scalar did
+            // not know about.
+            let style = match scalar.style {
+                ScalarStyle::Any | ScalarStyle::Plain
+                    if flow
+                        && double
+                        && single_quoted_by_emitter(&scalar.value, true, scalar.in_key) =>
+                {
+                    ScalarStyle::DoubleQuoted
+                }
+                style => style,
+            };
+            self.emitter.emit(Event::Scalar(Scalar {
+                tag: scalar.tag,
+                value: &scalar.value,
+                style,
+            }))?;
+        }
+        Ok(())
+    }
+
+    // Every enclosing value is a collection, so the depth before a collection
+    // starts is the number of collections around it.
+    fn check_depth(&self) -> Result<()> {
+        if self.depth >= self.max_depth {
+            return Err(error::new(ErrorImpl::DepthLimitExceeded(self.max_depth)));
+        }
+        Ok(())
+    }
+
+    fn enter_collection(&mut self, style: Style) {
+        if self.flow_depth > 0 || style == Style::Flow {
+            self.flow_depth += 1;
+        }
+    }
+
+    fn leave_collection(&mut self) {
+        // The styles do not change during serialization, so the collection
+        // being left was counted exactly if anything is counted.
+        if self.flow_depth > 0 {
+            self.flow_depth -= 1;
+        }
+    }
+
+    fn integral_float(&self, v: f64) -> Option<i64> {
+        // 2^63 is exact as an f64, unlike i64::MAX.
+        let in_range = (-9_223_372_036_854_775_808.0..9_223_372_036_854_775_808.0).contains(&v);
+        if self.integral_floats_as_int && in_range && v.fract() == 0.0 {
+            Some(v as i64)
+        } else {
+            None
+        }
+    }
+
+    fn serialize_mapping_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: ?Sized + ser::Serialize,
+    {
+        let in_key = mem::replace(&mut self.in_key, true);
+        let result = key.serialize(&mut *self);
+        self.in_key = in_key;
+        result?;
+        // A key that turned out to be a tag is not part of the path.
+        if self.scalar_formatter.is_some() && !matches!(self.state, State::FoundTag(_)) {
+            self.pending_key = Some(value::to_value(key)?);
+        }
+        Ok(())
+    }
+
+    // Serializes the value of the mapping entry whose key was serialized last.
+    fn serialize_mapping_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + ser::Serialize,
+    {
+        match self.pending_key.take() {
+            Some(key) => self.serialize_at(PathSegment::Key(key), value),
+            None => value.serialize(&mut *self),
+        }
+    }
+
+    fn serialize_sequence_element<T>(&mut self, elem: &T) -> Result<()>
+    where
+        T: ?Sized + ser::Serialize,
+    {
+        let index = match self.indices.last_mut() {
+            Some(index) => mem::replace(index, *index + 1),
+            None => 0,
+        };
+        if self.scalar_formatter.is_some() {
+            self.serialize_at(PathSegment::Index(index), elem)
+        } else {
+            elem.serialize(&mut *self)
+        }
+    }
+
+    fn serialize_at<T>(&mut self, segment: PathSegment, value: &T) -> Result<()>
+    where
+        T: ?Sized + ser::Serialize,
+    {
+        self.path.push(segment);
+        let result = value.serialize(&mut *self);
+        self.path.pop();
+        result
+    }
+
     fn value_start(&mut self) -> Result<()> {
         if self.depth == 0 {
-            self.emitter.emit(Event::DocumentStart)?;
+            let directives = &self.directives;
+            self.emitter.emit(Event::DocumentStart(DocumentStart {
+                version: directives
+                    .version
+                    .map(|version| (version.major as i32, version.minor as i32)),
+                tags: directives
+                    .tags
+                    .iter()
+                    .map(|tag| (tag.handle.as_str(), tag.prefix.as_str()))
+                    .collect(),
+                implicit: !self.explicit_start,
+            }))?;
         }
         self.depth += 1;
         Ok(())
@@ -137,7 +1087,13 @@ where
     fn value_end(&mut self) -> Result<()> {
         self.depth -= 1;
         if self.depth == 0 {
-            self.emitter.emit(Event::DocumentEnd)?;
+            self.emitter.emit(Event::DocumentEnd(DocumentEnd {
+                implicit: !self.explicit_end,
+            }))?;
+            if self.align_colons.get() {
+                // Aligning holds back the document until it is complete.
+                self.emitter.flush()?;
+            }
         }
         Ok(())
     }
@@ -148,6 +1104,13 @@ where
             if !tag.starts_with('!') {
                 tag.insert(0, '!');
             }
+            for directive in &self.directives.tags {
+                if let Some(suffix) = tag.strip_prefix(directive.handle.as_str()) {
+                    // Expanded so that the emitter can abbreviate it again.
+                    tag = format!("{}{}", directive.prefix, suffix);
+                    break;
+                }
+            }
             Some(tag)
         } else {
             self.state = state;
@@ -182,127 +1145,175 @@ where
     type SerializeStructVariant = Self;
 
     fn serialize_bool(self, v: bool) -> Result<()> {
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: if v { "true" } else { "false" },
-            style: ScalarStyle::Plain,
-        })
+        self.emit_scalar(
+            || Value::Bool(v),
+            Scalar {
+                tag: None,
+                value: if v { "true" } else { "false" },
+                style: ScalarStyle::Plain,
+            },
+        )
     }
 
     fn serialize_i8(self, v: i8) -> Result<()> {
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: itoa::Buffer::new().format(v),
-            style: ScalarStyle::Plain,
-        })
+        self.emit_scalar(
+            || Value::from(v),
+            Scalar {
+                tag: None,
+                value: itoa::Buffer::new().format(v),
+                style: ScalarStyle::Plain,
+            },
+        )
     }
 
     fn serialize_i16(self, v: i16) -> Result<()> {
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: itoa::Buffer::new().format(v),
-            style: ScalarStyle::Plain,
-        })
+        self.emit_scalar(
+            || Value::from(v),
+            Scalar {
+                tag: None,
+                value: itoa::Buffer::new().format(v),
+                style: ScalarStyle::Plain,
+            },
+        )
     }
 
     fn serialize_i32(self, v: i32) -> Result<()> {
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: itoa::Buffer::new().format(v),
-            style: ScalarStyle::Plain,
-        })
+        self.emit_scalar(
+            || Value::from(v),
+            Scalar {
+                tag: None,
+                value: itoa::Buffer::new().format(v),
+                style: ScalarStyle::Plain,
+            },
+        )
     }
 
     fn serialize_i64(self, v: i64) -> Result<()> {
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: itoa::Buffer::new().format(v),
-            style: ScalarStyle::Plain,
-        })
+        self.emit_scalar(
+            || Value::from(v),
+            Scalar {
+                tag: None,
+                value: itoa::Buffer::new().format(v),
+                style: ScalarStyle::Plain,
+            },
+        )
     }
 
     fn serialize_i128(self, v: i128) -> Result<()> {
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: itoa::Buffer::new().format(v),
-            style: ScalarStyle::Plain,
-        })
+        self.emit_scalar(
+            || Value::from(v),
+            Scalar {
+                tag: None,
+                value: itoa::Buffer::new().format(v),
+                style: ScalarStyle::Plain,
+            },
+        )
     }
 
     fn serialize_u8(self, v: u8) -> Result<()> {
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: itoa::Buffer::new().format(v),
-            style: ScalarStyle::Plain,
-        })
+        self.emit_scalar(
+            || Value::from(v),
+            Scalar {
+                tag: None,
+                value: itoa::Buffer::new().format(v),
+                style: ScalarStyle::Plain,
+            },
+        )
     }
 
     fn serialize_u16(self, v: u16) -> Result<()> {
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: itoa::Buffer::new().format(v),
-            style: ScalarStyle::Plain,
-        })
+        self.emit_scalar(
+            || Value::from(v),
+            Scalar {
+                tag: None,
+                value: itoa::Buffer::new().format(v),
+                style: ScalarStyle::Plain,
+            },
+        )
     }
 
     fn serialize_u32(self, v: u32) -> Result<()> {
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: itoa::Buffer::new().format(v),
-            style: ScalarStyle::Plain,
-        })
+        self.emit_scalar(
+            || Value::from(v),
+            Scalar {
+                tag: None,
+                value: itoa::Buffer::new().format(v),
+                style: ScalarStyle::Plain,
+            },
+        )
     }
 
     fn serialize_u64(self, v: u64) -> Result<()> {
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: itoa::Buffer::new().format(v),
-            style: ScalarStyle::Plain,
-        })
+        self.emit_scalar(
+            || Value::from(v),
+            Scalar {
+                tag: None,
+                value: itoa::Buffer::new().format(v),
+                style: ScalarStyle::Plain,
+            },
+        )
     }
 
     fn serialize_u128(self, v: u128) -> Result<()> {
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: itoa::Buffer::new().format(v),
-            style: ScalarStyle::Plain,
-        })
+        self.emit_scalar(
+            || Value::from(v),
+            Scalar {
+                tag: None,
+                value: itoa::Buffer::new().format(v),
+                style: ScalarStyle::Plain,
+            },
+        )
     }
 
     fn serialize_f32(self, v: f32) -> Result<()> {
+        if let Some(int) = self.integral_float(f64::from(v)) {
+            return self.serialize_i64(int);
+        }
         let mut buffer = ryu::Buffer::new();
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: match v.classify() {
-                num::FpCategory::Infinite if v.is_sign_positive() => ".inf",
-                num::FpCategory::Infinite => "-.inf",
-                num::FpCategory::Nan => ".nan",
-                _ => buffer.format_finite(v),
+        self.emit_scalar(
+            || Value::from(v),
+            Scalar {
+                tag: None,
+                value: match v.classify() {
+                    num::FpCategory::Infinite if v.is_sign_positive() => ".inf",
+                    num::FpCategory::Infinite => "-.inf",
+                    num::FpCategory::Nan => ".nan",
+                    _ => buffer.format_finite(v),
+                },
+                style: ScalarStyle::Plain,
             },
-            style: ScalarStyle::Plain,
-        })
+        )
     }
 
     fn serialize_f64(self, v: f64) -> Result<()> {
+        if let Some(int) = self.integral_float(v) {
+            return self.serialize_i64(int);
+        }
         let mut buffer = ryu::Buffer::new();
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: match v.classify() {
-                num::FpCategory::Infinite if v.is_sign_positive() => ".inf",
-                num::FpCategory::Infinite => "-.inf",
-                num::FpCategory::Nan => ".nan",
-                _ => buffer.format_finite(v),
+        self.emit_scalar(
+            || Value::from(v),
+            Scalar {
+                tag: None,
+                value: match v.classify() {
+                    num::FpCategory::Infinite if v.is_sign_positive() => ".inf",
+                    num::FpCategory::Infinite => "-.inf",
+                    num::FpCategory::Nan => ".nan",
+                    _ => buffer.format_finite(v),
+                },
+                style: ScalarStyle::Plain,
             },
-            style: ScalarStyle::Plain,
-        })
+        )
     }
 
     fn serialize_char(self, value: char) -> Result<()> {
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: value.encode_utf8(&mut [0u8; 4]),
-            style: ScalarStyle::SingleQuoted,
-        })
+        self.emit_scalar(
+            || Value::String(value.to_string()),
+            Scalar {
+                tag: None,
+                value: value.encode_utf8(&mut [0u8; 4]),
+                style: ScalarStyle::SingleQuoted,
+            },
+        )
     }
 
     fn serialize_str(self, value: &str) -> Result<()> {
@@ -353,7 +1364,11 @@ where
         }
 
         let style = if value.contains('\n') {
-            ScalarStyle::Literal
+            if self.multiline_strings {
+                ScalarStyle::Literal
+            } else {
+                ScalarStyle::DoubleQuoted
+            }
         } else {
             let result = crate::de::visit_untagged_scalar(
                 InferScalarStyle,
@@ -364,11 +1379,14 @@ where
             result.unwrap_or(ScalarStyle::Any)
         };
 
-        self.emit_scalar(Scalar {
-            tag: None,
-            value,
-            style,
-        })
+        self.emit_scalar(
+            || Value::String(value.to_owned()),
+            Scalar {
+                tag: None,
+                value,
+                style,
+            },
+        )
     }
 
     fn serialize_bytes(self, _value: &[u8]) -> Result<()> {
@@ -376,11 +1394,20 @@ where
     }
 
     fn serialize_unit(self) -> Result<()> {
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: "null",
-            style: ScalarStyle::Plain,
-        })
+        let value = match self.null_style {
+            NullStyle::Keyword => "null",
+            NullStyle::Tilde => "~",
+            NullStyle::Empty if self.flow_depth > 0 || self.in_key => "null",
+            NullStyle::Empty => "",
+        };
+        self.emit_scalar(
+            || Value::Null,
+            Scalar {
+                tag: None,
+                value,
+                style: ScalarStyle::Plain,
+            },
+        )
     }
 
     fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
@@ -534,7 +1561,7 @@ where
     where
         T: ?Sized + ser::Serialize,
     {
-        elem.serialize(&mut **self)
+        self.serialize_sequence_element(elem)
     }
 
     fn end(self) -> Result<()> {
@@ -553,7 +1580,7 @@ where
     where
         T: ?Sized + ser::Serialize,
     {
-        elem.serialize(&mut **self)
+        self.serialize_sequence_element(elem)
     }
 
     fn end(self) -> Result<()> {
@@ -572,7 +1599,7 @@ where
     where
         V: ?Sized + ser::Serialize,
     {
-        value.serialize(&mut **self)
+        self.serialize_sequence_element(value)
     }
 
     fn end(self) -> Result<()> {
@@ -591,7 +1618,7 @@ where
     where
         V: ?Sized + ser::Serialize,
     {
-        v.serialize(&mut **self)
+        self.serialize_sequence_element(v)
     }
 
     fn end(self) -> Result<()> {
@@ -611,14 +1638,14 @@ where
         T: ?Sized + ser::Serialize,
     {
         self.flush_mapping_start()?;
-        key.serialize(&mut **self)
+        self.serialize_mapping_key(key)
     }
 
     fn serialize_value<T>(&mut self, value: &T) -> Result<()>
     where
         T: ?Sized + ser::Serialize,
     {
-        value.serialize(&mut **self)
+        self.serialize_mapping_value(value)
     }
 
     fn serialize_entry<K, V>(&mut self, key: &K, value: &V) -> Result<(), Self::Error>
@@ -626,9 +1653,9 @@ where
         K: ?Sized + ser::Serialize,
         V: ?Sized + ser::Serialize,
     {
-        key.serialize(&mut **self)?;
+        self.serialize_mapping_key(key)?;
         let tagged = matches!(self.state, State::FoundTag(_));
-        value.serialize(&mut **self)?;
+        self.serialize_mapping_value(value)?;
         if tagged {
             self.state = State::AlreadyTagged;
         }
@@ -658,8 +1685,8 @@ where
     where
         V: ?Sized + ser::Serialize,
     {
-        self.serialize_str(key)?;
-        value.serialize(&mut **self)
+        self.serialize_mapping_key(key)?;
+        self.serialize_mapping_value(value)
     }
 
     fn end(self) -> Result<()> {
@@ -678,8 +1705,8 @@ where
     where
         V: ?Sized + ser::Serialize,
     {
-        self.serialize_str(field)?;
-        v.serialize(&mut **self)
+        self.serialize_mapping_key(field)?;
+        self.serialize_mapping_value(v)
     }
 
     fn end(self) -> Result<()> {
@@ -687,6 +1714,404 @@ where
     }
 }
 
+// The writer that the emitter writes into, in front of the caller's writer.
+//
+// libyaml always puts the dashes of a block sequence that is the value of a
+// mapping entry at the same indentation as the key. For
+// SequenceIndent::Indented this shifts every line of such a sequence, working
+// from the text one line at a time. Otherwise bytes pass straight through.
+struct Output<W> {
+    writer: W,
+    // Spaces to add per enclosing sequence, or 0 to leave the text alone.
+    shift: Rc<Cell<usize>>,
+    // Incomplete line, waiting for its newline.
+    line: Vec<u8>,
+    // Dash columns, as written by libyaml, of the sequences being shifted.
+    sequences: Vec<usize>,
+    // Column of the key if the previous line ended with a key whose value
+    // starts on the next line.
+    previous_key: Option<usize>,
+    // What the lines after the previous one belong to.
+    continuation: Continuation,
+    // Whether to align the colons of mappings, which needs the lines of a
+    // whole document. They are held in `pending` until the next flush.
+    align: Rc<Cell<bool>>,
+    pending: Vec<Vec<u8>>,
+}
+
+impl<W> Output<W>
+where
+    W: io::Write,
+{
+    fn write_line(&mut self, line: &[u8], shift: usize) -> io::Result<()> {
+        let indent = line.iter().take_while(|&&b| b == b' ').count();
+        let content = &line[indent..];
+        if content == b"\n" {
+            return self.output(line.to_vec());
+        }
+        let text = content.strip_suffix(b"\n").unwrap_or(content);
+
+        // The lines of a block scalar, or of a quoted scalar or flow
+        // collection spanning several lines, move along with the line they
+        // started on but are never read as dashes or keys.
+        let continued = match &mut self.continuation {
+            Continuation::None => false,
+            Continuation::Indented(parent) => indent > parent.unwrap_or(0),
+            Continuation::Open(scanner) => {
+                scanner.scan(text);
+                if !scanner.is_open() {
+                    self.continuation = Continuation::None;
+                }
+                true
+            }
+        };
+        if continued {
+            let mut shifted = vec![b' '; shift * self.sequences.len()];
+            shifted.extend_from_slice(line);
+            return self.output(shifted);
+        }
+        let is_dash = content.starts_with(b"- ") || content == b"-\n";
+        while let Some(&column) = self.sequences.last() {
+            if indent > column || indent == column && is_dash {
+                break;
+            }
+            self.sequences.pop();
+        }
+        if is_dash && self.previous_key == Some(indent) {
+            self.sequences.push(indent);
+        }
+
+        let mut column = indent;
+        let mut rest = &content[..content.len() - 1];
+        while let Some(after_dash) = rest.strip_prefix(b"- ") {
+            column += 2;
+            rest = after_dash;
+        }
+        self.previous_key = if ends_with_key(rest) {
+            Some(column)
+        } else {
+            None
+        };
+        self.continuation = if let Some(marker) = text
+            .strip_prefix(b"---")
+            .or_else(|| text.strip_prefix(b"..."))
+        {
+            let value = marker.strip_prefix(b" ").unwrap_or(marker);
+            Continuation::after(value, None)
+        } else if let Some(key_len) = key_len(rest) {
+            let after_colon = &rest[key_len + 1..];
+            let value = after_colon.strip_prefix(b" ").unwrap_or(after_colon);
+            Continuation::after(value, Some(column))
+        } else {
+            let parent = if column > indent { column - 2 } else { indent };
+            Continuation::after(rest, Some(parent))
+        };
+
+        let mut shifted = vec![b' '; shift * self.sequences.len()];
+        shifted.extend_from_slice(line);
+        self.output(shifted)
+    }
+
+    fn output(&mut self, line: Vec<u8>) -> io::Result<()> {
+        if self.align.get() {
+            self.pending.push(line);
+            Ok(())
+        } else {
+            self.writer.write_all(&line)
+        }
+    }
+}
+
+// Whether a line ends with `key:` or `key: !tag`, so that the value follows on
+// the next line.
+fn ends_with_key(line: &[u8]) -> bool {
+    if line.ends_with(b":") {
+        return true;
+    }
+    match line.iter().rposition(|&b| b == b' ') {
+        Some(space) => line[space + 1..].starts_with(b"!") && line[..space].ends_with(b":"),
+        None => false,
+    }
+}
+
+// Pads the keys of each block mapping in the lines of a document so that
+// their colons line up.
+fn align_colons(lines: &mut [Vec<u8>]) {
+    // A key in a block mapping: its line, the offset of its colon in the line,
+    // and its width in characters if its value is on the same line.
+    struct Key {
+        line: usize,
+        colon: usize,
+        width: Option<usize>,
+    }
+
+    // Mappings that are still open, as the column of their keys and the keys
+    // found so far, innermost last.
+    let mut groups: Vec<(usize, Vec<Key>)> = Vec::new();
+    let mut padding = Vec::new();
+    let mut continuation = Continuation::None;
+
+    for (i, line) in lines.iter().enumerate() {
+        let indent = line.iter().take_while(|&&b| b == b' ').count();
+        let content = &line[indent..];
+        let content = content.strip_suffix(b"\n").unwrap_or(content);
+        match &mut continuation {
+            Continuation::None => {}
+            Continuation::Indented(parent) => {
+                if content.is_empty() || parent.map_or(true, |parent| indent > parent) {
+                    continue;
+                }
+                continuation = Continuation::None;
+            }
+            Continuation::Open(scanner) => {
+                scanner.scan(content);
+                if !scanner.is_open() {
+                    continuation = Continuation::None;
+                }
+                continue;
+            }
+        }
+        if content.is_empty() {
+            continue;
+        }
+
+        if let Some(marker) = content
+            .strip_prefix(b"---")
+            .or_else(|| content.strip_prefix(b"..."))
+        {
+            close_groups(&mut groups, &mut padding, 0);
+            let value = marker.strip_prefix(b" ").unwrap_or(marker);
+            continuation = Continuation::after(value, None);
+            continue;
+        }
+
+        close_groups(&mut groups, &mut padding, indent + 1);
+        let mut column = indent;
+        let mut rest = content;
+        while let Some(after_dash) = rest.strip_prefix(b"- ") {
+            column += 2;
+            rest = after_dash;
+        }
+        if rest == b"-" {
+            continue;
+        }
+
+        let key_len = match key_len(rest) {
+            Some(key_len) => key_len,
+            None => {
+                let parent = if column > indent {
+                    Some(column - 2)
+                } else {
+                    Some(indent)
+                };
+                continuation = Continuation::after(rest, parent);
+                continue;
+            }
+        };
+        let after_colon = &rest[key_len + 1..];
+        let value = after_colon.strip_prefix(b" ").unwrap_or(after_colon);
+        let key = Key {
+            line: i,
+            colon: column + key_len,
+            width: if value.is_empty() {
+                None
+            } else {
+                str::from_utf8(&rest[..key_len])
+                    .ok()
+                    .map(|key| key.chars().count())
+            },
+        };
+        match groups.last_mut() {
+            Some((group_column, keys)) if *group_column == column => keys.push(key),
+            _ => groups.push((column, vec![key])),
+        }
+        continuation = Continuation::after(value, Some(column));
+    }
+    close_groups(&mut groups, &mut padding, 0);
+
+    for (line, colon, width) in padding {
+        lines[line].splice(colon..colon, iter::repeat(b' ').take(width));
+    }
+
+    // Ends the mappings whose keys are at or right of `column`, working out
+    // the padding for their keys.
+    fn close_groups(
+        groups: &mut Vec<(usize, Vec<Key>)>,
+        padding: &mut Vec<(usize, usize, usize)>,
+        column: usize,
+    ) {
+        while groups.last().map_or(false, |(c, _keys)| *c >= column) {
+            let (_column, keys) = groups.pop().unwrap();
+            let widths = || keys.iter().filter_map(|key| key.width);
+            if widths().count() < 2 {
+                continue;
+            }
+            let max = widths().max().unwrap_or(0);
+            for key in keys {
+                if let Some(width) = key.width {
+                    if width < max {
+                        padding.push((key.line, key.colon, max - width));
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Returns the length of the simple key at the start of `line`, if the line
+// holds a mapping entry.
+fn key_len(line: &[u8]) -> Option<usize> {
+    let end = match *line.first()? {
+        b'"' => {
+            let mut escaped = false;
+            let close = line[1..].iter().position(|&b| {
+                let close = !escaped && b == b'"';
+                escaped = !escaped && b == b'\\';
+                close
+            })?;
+            close + 2
+        }
+        b'\'' => {
+            let mut i = 1;
+            loop {
+                match (line.get(i)?, line.get(i + 1)) {
+                    (b'\'', Some(b'\'')) => i += 2,
+                    (b'\'', _) => break i + 1,
+                    _ => i += 1,
+                }
+            }
+        }
+        b'?' | b':' | b'-' if line.get(1).map_or(true, |&b| b == b' ') => return None,
+        b'[' | b'{' | b']' | b'}' | b',' | b'|' | b'>' | b'!' | b'&' | b'*' | b'#' | b'%'
+        | b'@' | b'`' => return None,
+        _ => (1..line.len())
+            .find(|&i| line[i] == b':' && line.get(i + 1).map_or(true, |&b| b == b' '))?,
+    };
+    match line.get(end..)? {
+        [b':'] | [b':', b' ', ..] => Some(end),
+        _ => None,
+    }
+}
+
+// Lines that belong to the value on an earlier line, and so hold no keys.
+enum Continuation {
+    None,
+    // A block scalar, whose lines are indented further than the given
+    // column, or than nothing at the root.
+    Indented(Option<usize>),
+    // A quoted scalar or flow collection that is not closed yet.
+    Open(Scanner),
+}
+
+impl Continuation {
+    fn after(value: &[u8], parent: Option<usize>) -> Self {
+        let mut value = value;
+        if value.starts_with(b"!") {
+            value = match value.iter().position(|&b| b == b' ') {
+                Some(space) => &value[space + 1..],
+                None => return Continuation::None,
+            };
+        }
+        match value.first() {
+            Some(b'|' | b'>') => Continuation::Indented(parent),
+            Some(b'"' | b'\'' | b'[' | b'{') => {
+                let mut scanner = Scanner::default();
+                scanner.scan(value);
+                if scanner.is_open() {
+                    Continuation::Open(scanner)
+                } else {
+                    Continuation::None
+                }
+            }
+            _ => Continuation::None,
+        }
+    }
+}
+
+// Tracks quotes and brackets across the lines of a quoted scalar or flow
+// collection.
+#[derive(Default)]
+struct Scanner {
+    quote: Option<u8>,
+    escaped: bool,
+    depth: usize,
+}
+
+impl Scanner {
+    fn scan(&mut self, text: &[u8]) {
+        // Quotes only start a scalar at the start of a token, not in the
+        // middle of a plain scalar like `don't`.
+        let mut token_start = true;
+        let mut i = 0;
+        while let Some(&b) = text.get(i) {
+            match self.quote {
+                Some(b'"') => {
+                    if self.escaped {
+                        self.escaped = false;
+                    } else if b == b'\\' {
+                        self.escaped = true;
+                    } else if b == b'"' {
+                        self.quote = None;
+                    }
+                }
+                Some(_) => {
+                    if b == b'\'' {
+                        if text.get(i + 1) == Some(&b'\'') {
+                            i += 1;
+                        } else {
+                            self.quote = None;
+                        }
+                    }
+                }
+                None => match b {
+                    b'"' | b'\'' if token_start => self.quote = Some(b),
+                    b'[' | b'{' => self.depth += 1,
+                    b']' | b'}' => self.depth = self.depth.saturating_sub(1),
+                    _ => {}
+                },
+            }
+            token_start = matches!(b, b' ' | b'[' | b'{' | b',');
+            i += 1;
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        self.quote.is_some() || self.depth > 0
+    }
+}
+
+impl<W> io::Write for Output<W>
+where
+    W: io::Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let shift = self.shift.get();
+        if shift == 0 && !self.align.get() {
+            return self.writer.write(buf);
+        }
+        let mut rest = buf;
+        while let Some(newline) = rest.iter().position(|&b| b == b'\n') {
+            let mut line = mem::take(&mut self.line);
+            line.extend_from_slice(&rest[..=newline]);
+            self.write_line(&line, shift)?;
+            rest = &rest[newline + 1..];
+        }
+        self.line.extend_from_slice(rest);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.pending.is_empty() {
+            let mut lines = mem::take(&mut self.pending);
+            align_colons(&mut lines);
+            for line in &lines {
+                self.writer.write_all(line)?;
+            }
+        }
+        self.writer.flush()
+    }
+}
+
 /// Serialize the given data structure as YAML into the IO stream.
 ///
 /// Serialization can fail if `T`'s implementation of `Serialize` decides to
@@ -712,3 +2137,325 @@ where
     to_writer(&mut vec, value)?;
     String::from_utf8(vec).map_err(|error| error::new(ErrorImpl::FromUtf8(error)))
 }
+
+/// Serialize the given data structure as a String of YAML, with the keys of
+/// every mapping sorted.
+///
+/// Struct fields count as mapping keys, so the output does not depend on the
+/// order in which they are declared. This makes for stable snapshots in tests.
+///
+/// ```
+/// use std::collections::HashMap;
+///
+/// let mut map = HashMap::new();
+/// map.insert("b", 2);
+/// map.insert("a", 1);
+/// assert_eq!(serde_yaml_ng::to_string_sorted(&map).unwrap(), "a: 1\nb: 2\n");
+/// ```
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// return an error.
+pub fn to_string_sorted<T>(value: &T) -> Result<String>
+where
+    T: ?Sized + ser::Serialize,
+{
+    let options = SerializeOptions::new().sort_keys(true);
+    crate::to_value(value)?.to_string_with(&options)
+}
+
+/// Serialize a `Value` as a String of YAML, with comments above some of its
+/// nodes.
+///
+/// The keys of `comments` are JSON Pointers, looked up like by
+/// [`Value::pointer`], and each comment is written on the line above the node
+/// it points to, at the same indentation. For a mapping entry that is the line
+/// of its key. A comment with several lines becomes several comment lines.
+/// The pointer `/`, like the empty pointer, stands for the whole document, so
+/// its comment goes at the top. Pointers that lead nowhere are ignored. When
+/// several nodes start on the same line, like a sequence element and the
+/// first entry of the mapping in it, all of their comments go above that
+/// line.
+///
+/// ```
+/// use serde_yaml_ng::Value;
+/// use std::collections::HashMap;
+///
+/// let value: Value = serde_yaml_ng::from_str("db: {host: localhost, port: 5432}").unwrap();
+/// let comments = HashMap::from([
+///     ("/".to_owned(), "Generated, do not edit.".to_owned()),
+///     ("/db/port".to_owned(), "Default PostgreSQL port.".to_owned()),
+/// ]);
+/// assert_eq!(
+///     serde_yaml_ng::to_string_with_comments(&value, &comments).unwrap(),
+///     "# Generated, do not edit.\ndb:\n  host: localhost\n  # Default PostgreSQL port.\n  port: 5432\n",
+/// );
+/// ```
+pub fn to_string_with_comments<S>(
+    value: &Value,
+    comments: &HashMap<String, String, S>,
+) -> Result<String>
+where
+    S: BuildHasher,
+{
+    let yaml = to_string(value)?;
+
+    let mut wanted: Vec<(Vec<usize>, &str, &str)> = comments
+        .iter()
+        .filter_map(|(pointer, comment)| {
+            let positions = if pointer == "/" {
+                Vec::new()
+            } else {
+                value::pointer_positions(value, pointer)?
+            };
+            Some((positions, pointer.as_str(), comment.as_str()))
+        })
+        .collect();
+    if wanted.is_empty() {
+        return Ok(yaml);
+    }
+    // Document order, which is also the order of the lines the nodes start on.
+    wanted.sort();
+
+    let lines = node_lines(&yaml)?;
+    let mut comments = wanted
+        .into_iter()
+        .filter_map(|(positions, _pointer, comment)| Some((*lines.get(&positions)?, comment)))
+        .peekable();
+
+    let mut out = String::with_capacity(yaml.len());
+    for (number, line) in yaml.split_inclusive('\n').enumerate() {
+        let indent = &line[..line.len() - line.trim_start_matches(' ').len()];
+        while let Some((_, comment)) = comments.next_if(|(start, _)| *start == number) {
+            for comment_line in comment_lines(comment) {
+                out.push_str(indent);
+                out.push('#');
+                if !comment_line.is_empty() {
+                    out.push(' ');
+                    out.push_str(comment_line);
+                }
+                out.push('\n');
+            }
+        }
+        out.push_str(line);
+    }
+    Ok(out)
+}
+
+// Splits a comment at every YAML line break, not only at `\n`, so that no part
+// of it is read back as anything other than a comment.
+fn comment_lines(comment: &str) -> impl Iterator<Item = &str> {
+    comment
+        .split("\r\n")
+        .flat_map(|line| line.split(['\n', '\r', '\u{85}', '\u{2028}', '\u{2029}']))
+}
+
+// The line on which each node of the YAML starts, keyed by the positions of
+// `value::pointer_positions`. For a mapping entry this is the line of its key.
+// Nodes inside of mapping keys are left out.
+fn node_lines(yaml: &str) -> Result<HashMap<Vec<usize>, usize>> {
+    struct Frame {
+        // None inside of a mapping key.
+        positions: Option<Vec<usize>>,
+        mapping: bool,
+        next: usize,
+        in_value: bool,
+    }
+
+    let mut parser = Parser::new(Cow::Borrowed(yaml.as_bytes()));
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut lines = HashMap::new();
+    loop {
+        let (event, mark) = parser.next()?;
+        let mapping = match event {
+            YamlEvent::MappingStart(_) => true,
+            YamlEvent::SequenceStart(_) | YamlEvent::Scalar(_) | YamlEvent::Alias(_) => false,
+            YamlEvent::SequenceEnd | YamlEvent::MappingEnd => {
+                stack.pop();
+                continue;
+            }
+            YamlEvent::StreamEnd => return Ok(lines),
+            YamlEvent::StreamStart | YamlEvent::DocumentStart(_) | YamlEvent::DocumentEnd(_) => {
+                continue;
+            }
+        };
+        let (positions, key, value) = match stack.last_mut() {
+            None => (Some(Vec::new()), false, false),
+            Some(frame) => {
+                let positions = frame
+                    .positions
+                    .as_ref()
+                    .map(|positions| [&positions[..], &[frame.next]].concat());
+                let key = frame.mapping && !frame.in_value;
+                let value = frame.mapping && frame.in_value;
+                if frame.mapping {
+                    frame.in_value = !frame.in_value;
+                }
+                if !key {
+                    frame.next += 1;
+                }
+                (positions, key, value)
+            }
+        };
+        // A mapping entry starts with its key.
+        if let (Some(positions), false) = (&positions, value) {
+            lines.insert(positions.clone(), mark.line() as usize);
+        }
+        if let YamlEvent::SequenceStart(_) | YamlEvent::MappingStart(_) = event {
+            stack.push(Frame {
+                positions: if key { None } else { positions },
+                mapping,
+                next: 0,
+                in_value: false,
+            });
+        }
+    }
+}
+
+// Whether the emitter, left to choose, would write the string in single
+// quotes, in a flow collection or not, as a mapping key or a value. This
+// follows libyaml's yaml_emitter_analyze_scalar and
+// yaml_emitter_select_scalar_style for an untagged scalar, with unicode output
+// on and canonical output off as this serializer always sets them.
+fn single_quoted_by_emitter(value: &str, flow: bool, key: bool) -> bool {
+    fn is_break(ch: char) -> bool {
+        matches!(ch, '\r' | '\n' | '\u{85}' | '\u{2028}' | '\u{2029}')
+    }
+
+    fn is_blankz(ch: Option<char>) -> bool {
+        match ch {
+            None | Some(' ' | '\t' | '\0') => true,
+            Some(ch) => is_break(ch),
+        }
+    }
+
+    fn is_printable(ch: char) -> bool {
+        matches!(ch,
+            '\n'
+            | ' '..='~'
+            | '\u{A0}'..='\u{D7FF}'
+            | '\u{E000}'..='\u{FFFD}'
+            | '\u{10000}'..='\u{10FFFF}'
+        ) && ch != '\u{FEFF}'
+    }
+
+    if value.is_empty() {
+        // Plain is allowed in block context, but not for an empty key.
+        return flow || key;
+    }
+
+    let mut block_indicators = value.starts_with("---") || value.starts_with("...");
+    let mut flow_indicators = block_indicators;
+    let mut line_breaks = false;
+    let mut special_characters = false;
+    let mut space_break = false;
+    let mut break_space = false;
+    let mut previous_space = false;
+    let mut previous_break = false;
+    let mut preceded_by_whitespace = true;
+
+    let mut chars = value.chars().peekable();
+    let mut first = true;
+    while let Some(ch) = chars.next() {
+        let followed_by_whitespace = is_blankz(chars.peek().copied());
+        if first {
+            match ch {
+                '#' | ',' | '[' | ']' | '{' | '}' | '&' | '*' | '!' | '|' | '>' | '\'' | '"'
+                | '%' | '@' | '`' => {
+                    flow_indicators = true;
+                    block_indicators = true;
+                }
+                '?' | ':' => {
+                    flow_indicators = true;
+                    block_indicators |= followed_by_whitespace;
+                }
+                '-' if followed_by_whitespace => {
+                    flow_indicators = true;
+                    block_indicators = true;
+                }
+                _ => {}
+            }
+        } else {
+            match ch {
+                ',' | '?' | '[' | ']' | '{' | '}' => flow_indicators = true,
+                ':' => {
+                    flow_indicators = true;
+                    block_indicators |= followed_by_whitespace;
+                }
+                '#' if preceded_by_whitespace => {
+                    flow_indicators = true;
+                    block_indicators = true;
+                }
+                _ => {}
+            }
+        }
+        if !is_printable(ch) {
+            special_characters = true;
+        }
+        if ch == ' ' {
+            break_space |= previous_break;
+            previous_space = true;
+            previous_break = false;
+        } else if is_break(ch) {
+            line_breaks = true;
+            space_break |= previous_space;
+            previous_space = false;
+            previous_break = true;
+        } else {
+            previous_space = false;
+            previous_break = false;
+        }
+        preceded_by_whitespace = is_blankz(Some(ch));
+        first = false;
+    }
+
+    let leading = value.starts_with(|ch| ch == ' ' || is_break(ch));
+    let trailing = value.ends_with(|ch| ch == ' ' || is_break(ch));
+    let plain_allowed = !(leading
+        || trailing
+        || break_space
+        || space_break
+        || special_characters
+        || line_breaks
+        || if flow {
+            flow_indicators
+        } else {
+            block_indicators
+        });
+    if plain_allowed {
+        return false;
+    }
+    !(break_space || space_break || special_characters)
+}
+
+/// Returns whether the given string would be quoted if it were serialized as
+/// a mapping key.
+///
+/// Strings that YAML would read back as something other than the same string,
+/// such as `true`, `1.0` or `~`, and strings that contain indicators like `: `
+/// or a leading `- `, are written quoted. Everything else is written as a plain
+/// scalar.
+///
+/// ```
+/// assert!(serde_yaml_ng::needs_quoting_as_key("a: b"));
+/// assert!(serde_yaml_ng::needs_quoting_as_key("- item"));
+/// assert!(serde_yaml_ng::needs_quoting_as_key("true"));
+/// assert!(!serde_yaml_ng::needs_quoting_as_key("plain"));
+/// ```
+pub fn needs_quoting_as_key(key: &str) -> bool {
+    fn emit_key(key: &str) -> Result<Vec<u8>> {
+        let mut serializer = Serializer::new(Vec::new());
+        // Unknown length, so that a key like "!x" is not taken for a tag.
+        let mut map = ser::Serializer::serialize_map(&mut serializer, None)?;
+        ser::SerializeMap::serialize_entry(&mut map, key, &())?;
+        ser::SerializeMap::end(map)?;
+        serializer.into_inner()
+    }
+
+    let yaml = match emit_key(key) {
+        Ok(yaml) => yaml,
+        Err(_) => return true,
+    };
+    // Keys that do not fit on one line use the explicit `? ` form.
+    let key = yaml.strip_prefix(b"? ").unwrap_or(&yaml);
+    matches!(key.first(), Some(b'\'' | b'"' | b'|' | b'>'))
+}
@@ -0,0 +1,257 @@
+//! Field types for quantities written with a unit, like `10MB` or `1.5h`.
+
+use serde::de::{self, Deserialize, Deserializer, Unexpected, Visitor};
+use serde::ser::{Serialize, Serializer};
+use std::fmt::{self, Display};
+use std::time::Duration;
+
+/// A number of bytes, written with a unit like `512B`, `10MB` or `4GiB`.
+///
+/// Deserializing accepts the decimal units `B`, `KB`, `MB`, `GB`, `TB` and
+/// `PB`, which are powers of 1000, and the binary units `KiB`, `MiB`, `GiB`,
+/// `TiB` and `PiB`, which are powers of 1024. The number may have a
+/// fractional part as long as the result is a whole number of bytes, and a
+/// plain integer is taken as a number of bytes. Serializing writes the
+/// largest unit that holds the size exactly. This type is available with the
+/// `units` feature.
+///
+/// ```
+/// use serde_derive::{Deserialize, Serialize};
+/// use serde_yaml_ng::ByteSize;
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Cache {
+///     capacity: ByteSize,
+/// }
+///
+/// let cache: Cache = serde_yaml_ng::from_str("capacity: 1.5GB").unwrap();
+/// assert_eq!(cache.capacity, ByteSize(1_500_000_000));
+/// assert_eq!(serde_yaml_ng::to_string(&cache).unwrap(), "capacity: 1500MB\n");
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ByteSize(pub u64);
+
+/// A span of time, written with a unit like `250ms`, `90s` or `1.5h`.
+///
+/// Deserializing accepts the units `ns`, `us`, `ms`, `s`, `m`, `h` and `d`.
+/// Several of them may follow one another, as in `1h30m`. The numbers may
+/// have a fractional part as long as the result is a whole number of
+/// nanoseconds. Serializing writes the largest single unit that holds the
+/// duration exactly. This type is available with the `units` feature.
+///
+/// ```
+/// use serde_derive::{Deserialize, Serialize};
+/// use serde_yaml_ng::HumanDuration;
+/// use std::time::Duration;
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Retry {
+///     backoff: HumanDuration,
+/// }
+///
+/// let retry: Retry = serde_yaml_ng::from_str("backoff: 1m30s").unwrap();
+/// assert_eq!(retry.backoff, HumanDuration(Duration::from_secs(90)));
+/// assert_eq!(serde_yaml_ng::to_string(&retry).unwrap(), "backoff: 90s\n");
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HumanDuration(pub Duration);
+
+// Largest first, so that formatting picks the largest unit that fits.
+const BYTE_UNITS: &[(&str, u128)] = &[
+    ("PiB", 1 << 50),
+    ("PB", 1_000_000_000_000_000),
+    ("TiB", 1 << 40),
+    ("TB", 1_000_000_000_000),
+    ("GiB", 1 << 30),
+    ("GB", 1_000_000_000),
+    ("MiB", 1 << 20),
+    ("MB", 1_000_000),
+    ("KiB", 1 << 10),
+    ("KB", 1_000),
+    ("B", 1),
+];
+
+// In nanoseconds.
+const DURATION_UNITS: &[(&str, u128)] = &[
+    ("d", 86_400_000_000_000),
+    ("h", 3_600_000_000_000),
+    ("m", 60_000_000_000),
+    ("s", 1_000_000_000),
+    ("ms", 1_000_000),
+    ("us", 1_000),
+    ("ns", 1),
+];
+
+impl Display for ByteSize {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write_quantity(formatter, self.0 as u128, BYTE_UNITS)
+    }
+}
+
+impl Display for HumanDuration {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write_quantity(formatter, self.0.as_nanos(), DURATION_UNITS)
+    }
+}
+
+fn write_quantity(
+    formatter: &mut fmt::Formatter,
+    amount: u128,
+    units: &[(&str, u128)],
+) -> fmt::Result {
+    let (name, size) = units
+        .iter()
+        .find(|(_name, size)| amount % size == 0 && amount != 0)
+        .unwrap_or(&units[units.len() - 1]);
+    write!(formatter, "{}{}", amount / size, name)
+}
+
+// Parses one or more numbers each followed by a unit, returning their total
+// in the smallest unit. With `single`, exactly one number is allowed.
+fn parse_quantity(s: &str, units: &[(&str, u128)], single: bool) -> Option<u128> {
+    let mut rest = s.trim();
+    if rest.is_empty() {
+        return None;
+    }
+    let mut total: u128 = 0;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|ch: char| !ch.is_ascii_digit() && ch != '.')
+            .unwrap_or(rest.len());
+        let (number, after) = rest.split_at(digits);
+        let unit_len = after
+            .find(|ch: char| ch.is_ascii_digit() || ch == '.')
+            .unwrap_or(after.len());
+        let (unit, after) = after.split_at(unit_len);
+        let size = units
+            .iter()
+            .find(|(name, _size)| *name == unit.trim())
+            .map(|(_name, size)| *size)?;
+        total = total.checked_add(scale(number, size)?)?;
+        rest = after;
+        if single && !rest.is_empty() {
+            return None;
+        }
+    }
+    Some(total)
+}
+
+// Multiplies a decimal number like `1.5` by `size`, exactly.
+fn scale(number: &str, size: u128) -> Option<u128> {
+    let (int, frac) = match number.split_once('.') {
+        Some((int, frac)) => (int, frac),
+        None => (number, ""),
+    };
+    if (int.is_empty() && frac.is_empty()) || frac.len() > 30 {
+        return None;
+    }
+    let int: u128 = if int.is_empty() { 0 } else { int.parse().ok()? };
+    let mut amount = int.checked_mul(size)?;
+    if !frac.is_empty() {
+        let denominator = 10u128.pow(frac.len() as u32);
+        let numerator = frac.parse::<u128>().ok()?.checked_mul(size)?;
+        if numerator % denominator != 0 {
+            return None;
+        }
+        amount = amount.checked_add(numerator / denominator)?;
+    }
+    Some(amount)
+}
+
+impl Serialize for ByteSize {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl Serialize for HumanDuration {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteSize {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ByteSizeVisitor;
+
+        impl<'de> Visitor<'de> for ByteSizeVisitor {
+            type Value = ByteSize;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a byte size like \"10MB\"")
+            }
+
+            fn visit_u64<E>(self, v: u64) -> Result<ByteSize, E>
+            where
+                E: de::Error,
+            {
+                Ok(ByteSize(v))
+            }
+
+            fn visit_i64<E>(self, v: i64) -> Result<ByteSize, E>
+            where
+                E: de::Error,
+            {
+                match u64::try_from(v) {
+                    Ok(v) => Ok(ByteSize(v)),
+                    Err(_) => Err(E::invalid_value(Unexpected::Signed(v), &self)),
+                }
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<ByteSize, E>
+            where
+                E: de::Error,
+            {
+                match parse_quantity(v, BYTE_UNITS, true).map(u64::try_from) {
+                    Some(Ok(bytes)) => Ok(ByteSize(bytes)),
+                    _ => Err(E::invalid_value(Unexpected::Str(v), &self)),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(ByteSizeVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for HumanDuration {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct HumanDurationVisitor;
+
+        impl<'de> Visitor<'de> for HumanDurationVisitor {
+            type Value = HumanDuration;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a duration like \"90s\"")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<HumanDuration, E>
+            where
+                E: de::Error,
+            {
+                let nanos = parse_quantity(v, DURATION_UNITS, false);
+                let secs = nanos.map(|nanos| u64::try_from(nanos / 1_000_000_000));
+                match (nanos, secs) {
+                    (Some(nanos), Some(Ok(secs))) => Ok(HumanDuration(Duration::new(
+                        secs,
+                        (nanos % 1_000_000_000) as u32,
+                    ))),
+                    _ => Err(E::invalid_value(Unexpected::Str(v), &self)),
+                }
+            }
+        }
+
+        deserializer.deserialize_str(HumanDurationVisitor)
+    }
+}
@@ -46,6 +46,20 @@ impl<'de> Deserialize<'de> for Value {
                 Ok(Value::Number(u.into()))
             }
 
+            fn visit_i128<E>(self, i: i128) -> Result<Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Value::Number(i.into()))
+            }
+
+            fn visit_u128<E>(self, u: u128) -> Result<Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Value::Number(u.into()))
+            }
+
             fn visit_f64<E>(self, f: f64) -> Result<Value, E>
             where
                 E: de::Error,
@@ -1220,7 +1234,7 @@ impl<'de> Deserializer<'de> for MapRefDeserializer<'de> {
 
 impl Value {
     #[cold]
-    fn invalid_type<E>(&self, exp: &dyn Expected) -> E
+    pub(crate) fn invalid_type<E>(&self, exp: &dyn Expected) -> E
     where
         E: de::Error,
     {
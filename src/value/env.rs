@@ -0,0 +1,210 @@
+use crate::error::{self, Error, ErrorImpl};
+use crate::value::{Mapping, Value};
+use std::env;
+
+impl Value {
+    /// Builds a mapping out of the environment variables whose names start
+    /// with `prefix`.
+    ///
+    /// See [`from_vars_prefixed`][Value::from_vars_prefixed], which this
+    /// calls with the variables of the current process. Variables whose name
+    /// or value is not valid Unicode are skipped.
+    pub fn from_env_prefixed(prefix: &str, separator: &str) -> Value {
+        Value::from_vars_prefixed(
+            env::vars_os()
+                .filter_map(|(k, v)| Some((k.into_string().ok()?, v.into_string().ok()?))),
+            prefix,
+            separator,
+        )
+    }
+
+    /// Builds a mapping out of the variables whose names start with `prefix`,
+    /// given as name and value pairs.
+    ///
+    /// The prefix is removed from each name, and what remains is split at each
+    /// `separator` into a path of lowercase keys. For example, with the prefix
+    /// `APP__` and the separator `__`, the variable `APP__DB__HOST=x` becomes
+    /// `{db: {host: x}}`. Empty parts of a name are ignored. All values are
+    /// strings.
+    ///
+    /// Variables are applied in order of name, so the result does not depend
+    /// on the order they are given in. If one variable names a path inside of
+    /// another, like `APP__DB` and `APP__DB__HOST`, the nested one wins.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let vars = [
+    ///     ("APP__DB__HOST", "localhost"),
+    ///     ("APP__DB__PORT", "5432"),
+    ///     ("APP__DEBUG", "true"),
+    ///     ("HOME", "/root"),
+    /// ];
+    /// let value = Value::from_vars_prefixed(vars, "APP__", "__");
+    /// let expected: Value = serde_yaml_ng::from_str("
+    /// db: {host: localhost, port: '5432'}
+    /// debug: 'true'
+    /// ").unwrap();
+    /// assert_eq!(value, expected);
+    /// ```
+    pub fn from_vars_prefixed<I, K, V>(vars: I, prefix: &str, separator: &str) -> Value
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: Into<String>,
+    {
+        let mut vars: Vec<(Vec<String>, String)> = vars
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let rest = name.as_ref().strip_prefix(prefix)?;
+                let path: Vec<String> = rest
+                    .split(separator)
+                    .filter(|part| !part.is_empty())
+                    .map(str::to_lowercase)
+                    .collect();
+                if path.is_empty() {
+                    None
+                } else {
+                    Some((path, value.into()))
+                }
+            })
+            .collect();
+        vars.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut root = Mapping::new();
+        for (path, value) in vars {
+            let (last, parents) = path.split_last().unwrap();
+            let mut mapping = &mut root;
+            for key in parents {
+                let entry = mapping
+                    .entry(Value::String(key.clone()))
+                    .or_insert(Value::Null);
+                if !entry.is_mapping() {
+                    *entry = Value::Mapping(Mapping::new());
+                }
+                mapping = match entry {
+                    Value::Mapping(mapping) => mapping,
+                    _ => unreachable!(),
+                };
+            }
+            let entry = mapping
+                .entry(Value::String(last.clone()))
+                .or_insert(Value::Null);
+            if !entry.is_mapping() {
+                *entry = Value::String(value);
+            }
+        }
+        Value::Mapping(root)
+    }
+
+    /// Replaces references to environment variables in the strings of this
+    /// value with the variables' values.
+    ///
+    /// See [`overlay_vars`][Value::overlay_vars], which this calls with the
+    /// variables of the current process. A variable whose value is not valid
+    /// Unicode counts as unset.
+    pub fn overlay_env_vars(&mut self) -> Result<(), Error> {
+        self.overlay_vars(|name| env::var(name).ok())
+    }
+
+    /// Replaces references to variables in the strings of this value, looking
+    /// each variable up with `lookup`.
+    ///
+    /// The references use shell syntax:
+    ///
+    /// - `${NAME}` is the value of `NAME`, or nothing if it is unset.
+    /// - `${NAME:-fallback}` is the value of `NAME`, or `fallback` if it is
+    ///   unset or empty.
+    /// - `${NAME:?message}` is the value of `NAME`, or an error carrying
+    ///   `message` if it is unset or empty.
+    ///
+    /// A literal `${` is written `$${`. Only string values are rewritten,
+    /// including those nested in sequences, mappings and tagged values;
+    /// mapping keys are left alone, and the results stay strings.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let mut value: Value = serde_yaml_ng::from_str("
+    /// url: http://${HOST}:${PORT:-8080}/
+    /// user: ${USER:?no user configured}
+    /// ").unwrap();
+    /// let lookup = |name: &str| match name {
+    ///     "HOST" => Some("example.com".to_owned()),
+    ///     _ => None,
+    /// };
+    /// let error = value.overlay_vars(lookup).unwrap_err();
+    /// assert_eq!(error.to_string(), "USER: no user configured");
+    /// assert_eq!(value["url"], "http://example.com:8080/");
+    /// ```
+    pub fn overlay_vars<F>(&mut self, lookup: F) -> Result<(), Error>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        overlay_vars(self, &lookup)
+    }
+}
+
+fn overlay_vars<F>(value: &mut Value, lookup: &F) -> Result<(), Error>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    match value {
+        Value::String(string) => {
+            if string.contains("${") {
+                *string = interpolate(string, lookup)?;
+            }
+            Ok(())
+        }
+        Value::Sequence(sequence) => sequence
+            .iter_mut()
+            .try_for_each(|value| overlay_vars(value, lookup)),
+        Value::Mapping(mapping) => mapping
+            .values_mut()
+            .try_for_each(|value| overlay_vars(value, lookup)),
+        Value::Tagged(tagged) => overlay_vars(&mut tagged.value, lookup),
+        Value::Null | Value::Bool(_) | Value::Number(_) => Ok(()),
+    }
+}
+
+fn interpolate<F>(string: &str, lookup: &F) -> Result<String, Error>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut out = String::with_capacity(string.len());
+    let mut rest = string;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            out.push_str(&rest[..start - 1]);
+            out.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        out.push_str(&rest[..start]);
+        let reference = &rest[start + 2..];
+        let end = match reference.find('}') {
+            Some(end) => end,
+            None => return Err(error::new(ErrorImpl::UnclosedVariable)),
+        };
+        let (name, default) = match reference[..end].split_once(':') {
+            Some((name, default)) => (name, Some(default)),
+            None => (&reference[..end], None),
+        };
+        let set = lookup(name).filter(|value| !value.is_empty());
+        match (set, default) {
+            (Some(value), _) => out.push_str(&value),
+            (None, None) => {}
+            (None, Some(default)) => {
+                if let Some(fallback) = default.strip_prefix('-') {
+                    out.push_str(fallback);
+                } else if let Some(message) = default.strip_prefix('?') {
+                    return Err(error::new(ErrorImpl::UnsetVariable(
+                        name.to_owned(),
+                        message.to_owned(),
+                    )));
+                }
+            }
+        }
+        rest = &reference[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
@@ -0,0 +1,104 @@
+use crate::value::tagged::nobang;
+use crate::value::Value;
+use crate::Number;
+
+impl Value {
+    /// Returns a hash of the content of this value, as 16 hex digits.
+    ///
+    /// Values that mean the same thing get the same fingerprint: the order of
+    /// entries in a mapping does not matter, and numbers are hashed by their
+    /// value so that `1`, `0x1` and `1.0` agree. The fingerprint is stable
+    /// across runs, platforms and versions of Rust, so it is suitable for
+    /// caching and change detection.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let a: Value = serde_yaml_ng::from_str("{x: 1, y: [true, null]}").unwrap();
+    /// let b: Value = serde_yaml_ng::from_str("{y: [true, ~], x: 1.0}").unwrap();
+    /// assert_eq!(a.fingerprint(), b.fingerprint());
+    /// assert_eq!(a.fingerprint().len(), 16);
+    /// ```
+    pub fn fingerprint(&self) -> String {
+        format!("{:016x}", fingerprint(self))
+    }
+}
+
+// 64-bit FNV-1a. Unlike std's DefaultHasher, its output is fixed by
+// definition rather than subject to change between releases.
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_str(&mut self, kind: u8, s: &str) {
+        self.write(&[kind]);
+        self.write_u64(s.len() as u64);
+        self.write(s.as_bytes());
+    }
+}
+
+fn fingerprint(value: &Value) -> u64 {
+    let mut hasher = Fnv::new();
+    match value {
+        Value::Null => hasher.write(b"~"),
+        Value::Bool(false) => hasher.write(b"f"),
+        Value::Bool(true) => hasher.write(b"t"),
+        Value::Number(number) => hasher.write_str(b'#', &canonical_number(number)),
+        Value::String(string) => hasher.write_str(b'"', string),
+        Value::Sequence(sequence) => {
+            hasher.write(b"[");
+            hasher.write_u64(sequence.len() as u64);
+            for element in sequence {
+                hasher.write_u64(fingerprint(element));
+            }
+        }
+        Value::Mapping(mapping) => {
+            let mut entries: Vec<u64> = mapping
+                .iter()
+                .map(|(k, v)| {
+                    let mut entry = Fnv::new();
+                    entry.write_u64(fingerprint(k));
+                    entry.write_u64(fingerprint(v));
+                    entry.0
+                })
+                .collect();
+            entries.sort_unstable();
+            hasher.write(b"{");
+            hasher.write_u64(entries.len() as u64);
+            for entry in entries {
+                hasher.write_u64(entry);
+            }
+        }
+        Value::Tagged(tagged) => {
+            hasher.write_str(b'!', nobang(&tagged.tag.string));
+            hasher.write_u64(fingerprint(&tagged.value));
+        }
+    }
+    hasher.0
+}
+
+// Floats that hold a whole number are written the same way as the equivalent
+// integer.
+fn canonical_number(number: &Number) -> String {
+    if number.is_f64() {
+        if let Some(float) = number.as_f64() {
+            if float.fract() == 0.0 && float.abs() < 1e18 {
+                return (float as i64).to_string();
+            }
+        }
+    }
+    number.to_string()
+}
@@ -16,8 +16,8 @@ macro_rules! from_number {
 }
 
 from_number! {
-    i8 i16 i32 i64 isize
-    u8 u16 u32 u64 usize
+    i8 i16 i32 i64 i128 isize
+    u8 u16 u32 u64 u128 usize
     f32 f64
 }
 
@@ -145,6 +145,28 @@ impl<'a, T: Clone + Into<Value>> From<&'a [T]> for Value {
     }
 }
 
+impl<T: Into<Value>> From<Option<T>> for Value {
+    /// Convert an `Option` to `Value`, with `None` becoming `Value::Null`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde_yaml_ng::Value;
+    ///
+    /// let x: Value = Some("lorem").into();
+    /// assert_eq!(x, "lorem");
+    ///
+    /// let x: Value = None::<i32>.into();
+    /// assert_eq!(x, Value::Null);
+    /// ```
+    fn from(f: Option<T>) -> Self {
+        match f {
+            Some(value) => value.into(),
+            None => Value::Null,
+        }
+    }
+}
+
 impl<T: Into<Value>> FromIterator<T> for Value {
     /// Convert an iteratable type to a YAML sequence
     ///
@@ -251,6 +251,14 @@ where
     /// object, it will be inserted with a value of null. Indexing into a value
     /// that is neither an object nor null will panic.
     ///
+    /// # Panics
+    ///
+    /// Panics with a message like `cannot access index 3 of YAML sequence of
+    /// length 2` if a numeric index is out of bounds of a sequence, and with
+    /// a message like `cannot access key "k" in YAML sequence` if the index
+    /// does not fit the type of the value. Use [`Value::get_mut`] to get an
+    /// `Option` instead.
+    ///
     /// # Examples
     ///
     /// ```
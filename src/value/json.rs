@@ -0,0 +1,209 @@
+use crate::error::{self, Error, ErrorImpl};
+use crate::mapping::Mapping;
+use crate::value::Value;
+use std::fmt::Write as _;
+
+impl Value {
+    /// Serializes this value as compact JSON text.
+    ///
+    /// The output depends only on the value: the entries of every mapping
+    /// are written sorted by key, and numbers are written as they appear in
+    /// the `Value`, so large integers keep all of their digits. YAML that has
+    /// no JSON counterpart is converted as follows:
+    ///
+    /// - A mapping key that is not a string becomes its own JSON text, so
+    ///   the key `1` becomes `"1"` and the key `[a, b]` becomes
+    ///   `"[\"a\",\"b\"]"`. If two keys of a mapping end up the same this
+    ///   way, an error is returned.
+    /// - Tags are dropped, leaving the tagged value.
+    /// - NaN and infinite floats become `null`.
+    ///
+    /// This method is available with the `json` feature.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let value: Value = serde_yaml_ng::from_str("
+    /// name: app
+    /// 1: one
+    /// ratio: .nan
+    /// tags: !set [b, a]
+    /// ").unwrap();
+    /// assert_eq!(
+    ///     value.to_json_string().unwrap(),
+    ///     r#"{"1":"one","name":"app","ratio":null,"tags":["b","a"]}"#,
+    /// );
+    /// ```
+    pub fn to_json_string(&self) -> Result<String, Error> {
+        let mut out = String::new();
+        write_json(&mut out, self)?;
+        Ok(out)
+    }
+}
+
+/// Converts a JSON value into the equivalent YAML value.
+///
+/// Every JSON value has a YAML counterpart, so this is lossless: objects
+/// become mappings with string keys in the same order, and numbers become
+/// integers if they are integers in the JSON value and floats otherwise.
+///
+/// This conversion is available with the `json` feature.
+///
+/// ```
+/// # use serde_yaml_ng::Value;
+/// let json = serde_json::json!({"name": "app", "ports": [80, 443], "ratio": 0.5});
+/// let yaml = Value::from(json);
+/// assert_eq!(yaml["ports"][1], 443);
+/// assert_eq!(yaml["ratio"], 0.5);
+/// ```
+impl From<serde_json::Value> for Value {
+    fn from(json: serde_json::Value) -> Self {
+        match json {
+            serde_json::Value::Null => Value::Null,
+            serde_json::Value::Bool(b) => Value::Bool(b),
+            serde_json::Value::Number(n) => {
+                if let Some(u) = n.as_u64() {
+                    Value::from(u)
+                } else if let Some(i) = n.as_i64() {
+                    Value::from(i)
+                } else {
+                    n.as_f64().map_or(Value::Null, Value::from)
+                }
+            }
+            serde_json::Value::String(s) => Value::String(s),
+            serde_json::Value::Array(array) => {
+                Value::Sequence(array.into_iter().map(Value::from).collect())
+            }
+            serde_json::Value::Object(object) => Value::Mapping(
+                object
+                    .into_iter()
+                    .map(|(key, value)| (Value::String(key), Value::from(value)))
+                    .collect::<Mapping>(),
+            ),
+        }
+    }
+}
+
+/// Converts a YAML value into a JSON value, by the same rules as
+/// [`Value::to_json_string`].
+///
+/// The conversion loses what JSON cannot express: tags are dropped, NaN and
+/// infinite floats become `null`, and a mapping key that is not a string
+/// becomes its own JSON text. It fails if two keys of a mapping end up the
+/// same that way, and for integers outside the range of `i64` and `u64`,
+/// which `serde_json::Value` cannot hold.
+///
+/// This conversion is available with the `json` feature.
+///
+/// ```
+/// # use serde_yaml_ng::Value;
+/// let yaml: Value = serde_yaml_ng::from_str("{1: one, tags: !set [a]}").unwrap();
+/// let json = serde_json::Value::try_from(yaml).unwrap();
+/// assert_eq!(json, serde_json::json!({"1": "one", "tags": ["a"]}));
+/// ```
+impl TryFrom<Value> for serde_json::Value {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        Ok(match value {
+            Value::Null => serde_json::Value::Null,
+            Value::Bool(b) => serde_json::Value::Bool(b),
+            Value::Number(n) => {
+                if let Some(u) = n.as_u64() {
+                    serde_json::Value::from(u)
+                } else if let Some(i) = n.as_i64() {
+                    serde_json::Value::from(i)
+                } else if n.is_f64() {
+                    n.as_f64()
+                        .and_then(serde_json::Number::from_f64)
+                        .map_or(serde_json::Value::Null, serde_json::Value::Number)
+                } else {
+                    return Err(error::new(ErrorImpl::JsonNumberOutOfRange(n.to_string())));
+                }
+            }
+            Value::String(s) => serde_json::Value::String(s),
+            Value::Sequence(sequence) => serde_json::Value::Array(
+                sequence
+                    .into_iter()
+                    .map(serde_json::Value::try_from)
+                    .collect::<Result<_, _>>()?,
+            ),
+            Value::Mapping(mapping) => {
+                let mut object = serde_json::Map::new();
+                for (key, value) in mapping {
+                    let key = match key.untag() {
+                        Value::String(key) => key,
+                        key => key.to_json_string()?,
+                    };
+                    if object.contains_key(&key) {
+                        return Err(error::new(ErrorImpl::DuplicateJsonKey(key)));
+                    }
+                    object.insert(key, serde_json::Value::try_from(value)?);
+                }
+                serde_json::Value::Object(object)
+            }
+            Value::Tagged(tagged) => serde_json::Value::try_from(tagged.value)?,
+        })
+    }
+}
+
+fn write_json(out: &mut String, value: &Value) -> Result<(), Error> {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) if n.is_finite() => write!(out, "{}", n).unwrap(),
+        Value::Number(_) => out.push_str("null"),
+        Value::String(s) => write_string(out, s),
+        Value::Sequence(sequence) => {
+            out.push('[');
+            for (i, element) in sequence.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_json(out, element)?;
+            }
+            out.push(']');
+        }
+        Value::Mapping(mapping) => {
+            let mut entries = Vec::with_capacity(mapping.len());
+            for (key, value) in mapping {
+                let key = match key.untag_ref() {
+                    Value::String(key) => key.clone(),
+                    key => key.to_json_string()?,
+                };
+                entries.push((key, value));
+            }
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            out.push('{');
+            for (i, (key, value)) in entries.iter().enumerate() {
+                if i > 0 {
+                    if *key == entries[i - 1].0 {
+                        return Err(error::new(ErrorImpl::DuplicateJsonKey(key.clone())));
+                    }
+                    out.push(',');
+                }
+                write_string(out, key);
+                out.push(':');
+                write_json(out, value)?;
+            }
+            out.push('}');
+        }
+        Value::Tagged(tagged) => write_json(out, &tagged.value)?,
+    }
+    Ok(())
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{0}'..='\u{1f}' => write!(out, "\\u{:04x}", ch as u32).unwrap(),
+            _ => out.push(ch),
+        }
+    }
+    out.push('"');
+}
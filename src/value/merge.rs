@@ -0,0 +1,377 @@
+use crate::value::{Mapping, PathSegment, Sequence, TaggedValue, Value};
+use std::mem;
+
+/// Settings for [`Value::merge_with_options`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MergeOptions {
+    /// What to do where both sides have a sequence at the same path.
+    pub array_strategy: ArrayStrategy,
+}
+
+/// How [`Value::merge_with_options`] combines two sequences at the same path.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ArrayStrategy {
+    /// The sequence from `other` replaces the one in `self`, as in
+    /// [`Value::merge`].
+    #[default]
+    Replace,
+    /// The elements from `other` are added after those in `self`.
+    Append,
+    /// The elements from `other` are added before those in `self`.
+    Prepend,
+    /// Like `Append`, but elements equal to an earlier one are dropped, as by
+    /// [`Value::dedup_sequence_all`].
+    Unique,
+}
+
+/// Deeply merges all of the given values in order, so that later values take
+/// precedence over earlier ones.
+///
+/// This is [`Value::merge`] folded over the values, starting from the first.
+/// For layered configuration, pass the defaults first and the most specific
+/// overrides last. An empty input gives `Value::Null`.
+///
+/// ```
+/// # use serde_yaml_ng::Value;
+/// let defaults: Value = serde_yaml_ng::from_str("{host: localhost, port: 80}").unwrap();
+/// let file: Value = serde_yaml_ng::from_str("{port: 8080}").unwrap();
+/// let overrides: Value = serde_yaml_ng::from_str("{host: example.com}").unwrap();
+///
+/// let config = serde_yaml_ng::merge_all([defaults, file, overrides]);
+/// assert_eq!(config["host"], "example.com");
+/// assert_eq!(config["port"], 8080);
+/// ```
+pub fn merge_all<I>(values: I) -> Value
+where
+    I: IntoIterator<Item = Value>,
+{
+    let mut values = values.into_iter();
+    let mut merged = match values.next() {
+        Some(first) => first,
+        None => return Value::Null,
+    };
+    for value in values {
+        merged.merge(value);
+    }
+    merged
+}
+
+impl Value {
+    /// Deeply merges `other` into `self`.
+    ///
+    /// Mappings are merged key by key, recursively. Wherever both sides have
+    /// a value at the same path and they are not both mappings, the value
+    /// from `other` wins. Tags are looked through: two mappings are merged
+    /// underneath their tags, and the result keeps the tag of `other` if it
+    /// has one and the tag of `self` otherwise.
+    ///
+    /// An explicit null in `other`, whether written `key: null`, `key: ~` or
+    /// just `key:`, is such a value and replaces whatever `self` has at that
+    /// path. A key that is absent from `other` leaves `self` untouched. So
+    /// does an empty value, like `key:`, that was loaded with
+    /// [`Deserializer::mark_empty_values`][crate::Deserializer::mark_empty_values]:
+    /// see [`Value::is_empty_value`].
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let mut config: Value = serde_yaml_ng::from_str("
+    /// server: {host: localhost, port: 80}
+    /// debug: false
+    /// ").unwrap();
+    /// let overrides: Value = serde_yaml_ng::from_str("
+    /// server: {port: 8080}
+    /// ").unwrap();
+    ///
+    /// config.merge(overrides);
+    /// assert_eq!(config["server"]["host"], "localhost");
+    /// assert_eq!(config["server"]["port"], 8080);
+    /// assert_eq!(config["debug"], false);
+    /// ```
+    pub fn merge(&mut self, other: Value) {
+        self.merge_with(other, |_path, _old, new| new);
+    }
+
+    /// Deeply merges `other` into `self`, like [`Value::merge`], with
+    /// sequences combined according to `options`.
+    ///
+    /// The array strategy applies wherever both sides have a sequence at the
+    /// same path, including sequences nested inside of merged mappings. Tags
+    /// are looked through as for mappings: two tagged sequences are combined
+    /// underneath their tags, and the result keeps the tag of `other` if it
+    /// has one and the tag of `self` otherwise. Any other conflict is resolved
+    /// in favor of `other`, as in `merge`.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// use serde_yaml_ng::value::{ArrayStrategy, MergeOptions};
+    ///
+    /// let mut config: Value = serde_yaml_ng::from_str("
+    /// hosts: [a, b]
+    /// port: 80
+    /// ").unwrap();
+    /// let overrides: Value = serde_yaml_ng::from_str("
+    /// hosts: [b, c]
+    /// port: 8080
+    /// ").unwrap();
+    ///
+    /// let options = MergeOptions {
+    ///     array_strategy: ArrayStrategy::Unique,
+    /// };
+    /// config.merge_with_options(overrides, &options);
+    /// assert_eq!(config["hosts"], serde_yaml_ng::from_str::<Value>("[a, b, c]").unwrap());
+    /// assert_eq!(config["port"], 8080);
+    /// ```
+    pub fn merge_with_options(&mut self, other: Value, options: &MergeOptions) {
+        let strategy = options.array_strategy;
+        self.merge_with(other, |_path, old, new| merge_sequences(old, new, strategy));
+    }
+
+    /// Deeply merges `other` into `self`, treating a null in `other` as a
+    /// request to delete that key.
+    ///
+    /// This is like [`Value::merge`], except that wherever `other` has a
+    /// mapping entry whose value is null, the entry with the same key is
+    /// removed from `self` rather than overwritten with null. Nulls inside of
+    /// a value that is added to `self` wholesale, because `self` has nothing
+    /// at that path, are kept as they are. An empty value, as recognized by
+    /// [`Value::is_empty_value`], leaves the entry in `self` alone.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let mut config: Value = serde_yaml_ng::from_str("
+    /// proxy: http://proxy:3128
+    /// timeout: 30
+    /// ").unwrap();
+    /// let overlay: Value = serde_yaml_ng::from_str("
+    /// proxy: null
+    /// timeout: 60
+    /// ").unwrap();
+    ///
+    /// config.merge_with_delete(overlay);
+    /// assert_eq!(config.get("proxy"), None);
+    /// assert_eq!(config["timeout"], 60);
+    /// ```
+    pub fn merge_with_delete(&mut self, other: Value) {
+        let result = merge_mappings(self, other, |base, other| {
+            for (key, value) in other {
+                if let Value::Null = value {
+                    base.shift_remove(&key);
+                    continue;
+                }
+                match base.get_mut(&key) {
+                    Some(existing) => existing.merge_with_delete(value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        });
+        if let Err(other) = result {
+            if !other.is_empty_value() {
+                *self = other;
+            }
+        }
+    }
+
+    /// Deeply merges `other` into `self`, calling `resolve` to pick the result
+    /// wherever the two conflict.
+    ///
+    /// Mappings are merged key by key, recursively. Wherever both sides have
+    /// a value at the same path and they are not both mappings, `resolve` is
+    /// called with the path of the conflict, the value from `self` and the
+    /// value from `other`, and its return value is stored at that path. An
+    /// empty value in `other`, as recognized by [`Value::is_empty_value`], is
+    /// not a conflict and leaves `self` untouched.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let mut limits: Value = serde_yaml_ng::from_str("{cpu: 2, memory: 512}").unwrap();
+    /// let other: Value = serde_yaml_ng::from_str("{cpu: 4, memory: 256}").unwrap();
+    ///
+    /// // Keep the larger of the two.
+    /// limits.merge_with(other, |_path, old, new| {
+    ///     if new.as_u64() > old.as_u64() {
+    ///         new
+    ///     } else {
+    ///         old
+    ///     }
+    /// });
+    /// assert_eq!(limits["cpu"], 4);
+    /// assert_eq!(limits["memory"], 512);
+    /// ```
+    pub fn merge_with<F>(&mut self, other: Value, mut resolve: F)
+    where
+        F: FnMut(&[PathSegment], Value, Value) -> Value,
+    {
+        merge_at(&mut Vec::new(), self, other, &mut resolve);
+    }
+
+    /// Returns the part of `self` that is also in `other`.
+    ///
+    /// Values that are equal are kept whole. Two mappings are intersected key
+    /// by key, keeping the keys present in both whose values have something in
+    /// common. Two sequences are intersected by position: the elements at
+    /// index 0 are intersected with each other, then the elements at index 1,
+    /// and so on, and positions whose elements have nothing in common are
+    /// dropped. Values with the same tag are intersected underneath the tag.
+    /// Differing scalars have nothing in common.
+    ///
+    /// If `self` and `other` have nothing in common, the result is an empty
+    /// mapping or sequence if both are mappings or both are sequences, and
+    /// `Value::Null` otherwise.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let staging: Value = serde_yaml_ng::from_str("
+    /// image: app:1.2
+    /// replicas: 1
+    /// env: {LOG: debug, REGION: eu}
+    /// ").unwrap();
+    /// let production: Value = serde_yaml_ng::from_str("
+    /// image: app:1.2
+    /// replicas: 5
+    /// env: {LOG: warn, REGION: eu}
+    /// ").unwrap();
+    ///
+    /// let common: Value = serde_yaml_ng::from_str("
+    /// image: app:1.2
+    /// env: {REGION: eu}
+    /// ").unwrap();
+    /// assert_eq!(staging.intersect(&production), common);
+    /// ```
+    pub fn intersect(&self, other: &Value) -> Value {
+        match intersection(self, other) {
+            Some(common) => common,
+            None => match (self, other) {
+                (Value::Mapping(_), Value::Mapping(_)) => Value::Mapping(Mapping::new()),
+                (Value::Sequence(_), Value::Sequence(_)) => Value::Sequence(Sequence::new()),
+                _ => Value::Null,
+            },
+        }
+    }
+}
+
+fn intersection(a: &Value, b: &Value) -> Option<Value> {
+    if a == b {
+        return Some(a.clone());
+    }
+    let common = match (a, b) {
+        (Value::Mapping(a), Value::Mapping(b)) => {
+            let mut common = Mapping::new();
+            for (key, a) in a {
+                if let Some(b) = b.get(key) {
+                    if let Some(value) = intersection(a, b) {
+                        common.insert(key.clone(), value);
+                    }
+                }
+            }
+            if common.is_empty() {
+                return None;
+            }
+            Value::Mapping(common)
+        }
+        (Value::Sequence(a), Value::Sequence(b)) => {
+            let common: Sequence = a
+                .iter()
+                .zip(b)
+                .filter_map(|(a, b)| intersection(a, b))
+                .collect();
+            if common.is_empty() {
+                return None;
+            }
+            Value::Sequence(common)
+        }
+        (Value::Tagged(a), Value::Tagged(b)) if a.tag == b.tag => {
+            Value::Tagged(Box::new(TaggedValue {
+                tag: a.tag.clone(),
+                value: intersection(&a.value, &b.value)?,
+            }))
+        }
+        _ => return None,
+    };
+    Some(common)
+}
+
+fn merge_at<F>(path: &mut Vec<PathSegment>, base: &mut Value, other: Value, resolve: &mut F)
+where
+    F: FnMut(&[PathSegment], Value, Value) -> Value,
+{
+    let result = merge_mappings(base, other, |base, other| {
+        for (key, value) in other {
+            match base.get_mut(&key) {
+                Some(existing) => {
+                    path.push(PathSegment::Key(key));
+                    merge_at(path, existing, value, resolve);
+                    path.pop();
+                }
+                None => {
+                    base.insert(key, value);
+                }
+            }
+        }
+    });
+    if let Err(other) = result {
+        if other.is_empty_value() {
+            return;
+        }
+        let old = mem::take(base);
+        *base = resolve(path, old, other);
+    }
+}
+
+// If `old` and `new` are both sequences, possibly tagged, combines the two
+// sequences underneath the tags according to `strategy`. The result keeps the
+// tags of `new` if it has any, and otherwise those of `old`. Otherwise `new`
+// is returned untouched.
+fn merge_sequences(mut old: Value, mut new: Value, strategy: ArrayStrategy) -> Value {
+    if !old.untag_ref().is_sequence() || !new.untag_ref().is_sequence() {
+        return new;
+    }
+    let (mut old_seq, mut new_seq) = match (mem::take(old.untag_mut()), mem::take(new.untag_mut()))
+    {
+        (Value::Sequence(old), Value::Sequence(new)) => (old, new),
+        _ => unreachable!(),
+    };
+    let merged = match strategy {
+        ArrayStrategy::Replace => Value::Sequence(new_seq),
+        ArrayStrategy::Append => {
+            old_seq.append(&mut new_seq);
+            Value::Sequence(old_seq)
+        }
+        ArrayStrategy::Prepend => {
+            new_seq.append(&mut old_seq);
+            Value::Sequence(new_seq)
+        }
+        ArrayStrategy::Unique => {
+            old_seq.append(&mut new_seq);
+            let mut merged = Value::Sequence(old_seq);
+            merged.dedup_sequence_all();
+            merged
+        }
+    };
+    let mut result = if let Value::Tagged(_) = new { new } else { old };
+    *result.untag_mut() = merged;
+    result
+}
+
+// If `base` and `other` are both mappings, possibly tagged, calls `merge` with
+// the two mappings underneath the tags. The result keeps the tags of `other`
+// if it has any, and otherwise those of `base`. Otherwise `other` is handed
+// back untouched.
+fn merge_mappings<F>(base: &mut Value, mut other: Value, merge: F) -> Result<(), Value>
+where
+    F: FnOnce(&mut Mapping, Mapping),
+{
+    if !base.untag_ref().is_mapping() || !other.untag_ref().is_mapping() {
+        return Err(other);
+    }
+    if let Value::Tagged(_) = other {
+        let inner = mem::replace(other.untag_mut(), mem::take(base).untag());
+        *base = other;
+        other = inner;
+    }
+    if let (Value::Mapping(base), Value::Mapping(other)) = (base.untag_mut(), other) {
+        merge(base, other);
+    }
+    Ok(())
+}
@@ -2,27 +2,41 @@
 
 mod de;
 mod debug;
+mod env;
+mod fingerprint;
 mod from;
 mod index;
+#[cfg(feature = "json")]
+mod json;
+mod merge;
 mod partial_eq;
 mod ser;
 pub(crate) mod tagged;
+mod validate;
 
 use crate::error::{self, Error, ErrorImpl};
+use crate::ser::NullStyle;
+use serde::de::value::BorrowedStrDeserializer;
 use serde::de::{Deserialize, DeserializeOwned, IntoDeserializer};
 use serde::Serialize;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::hash::{Hash, Hasher};
 use std::mem;
+use std::ptr;
 
 pub use self::index::Index;
+pub use self::merge::{merge_all, ArrayStrategy, MergeOptions};
 pub use self::ser::Serializer;
 pub use self::tagged::{Tag, TaggedValue};
+pub use self::validate::Validator;
 #[doc(inline)]
 pub use crate::mapping::Mapping;
 pub use crate::number::Number;
 
 /// Represents any valid YAML value.
-#[derive(Clone, PartialEq, PartialOrd)]
+#[derive(Clone, PartialEq)]
 pub enum Value {
     /// Represents a YAML null value.
     Null,
@@ -81,6 +95,29 @@ impl Default for Value {
 /// A YAML sequence in which the elements are `serde_yaml_ng::Value`.
 pub type Sequence = Vec<Value>;
 
+/// One step of the path from the root of a `Value` to one of its nodes.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    /// The value of a mapping entry with this key.
+    Key(Value),
+    /// The element of a sequence at this index.
+    Index(usize),
+}
+
+/// The kinds of scalar a `Value` can be, as returned by
+/// [`Value::scalar_kind`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ScalarKind {
+    /// [`Value::Null`].
+    Null,
+    /// [`Value::Bool`].
+    Bool,
+    /// [`Value::Number`], whether integer or floating point.
+    Number,
+    /// [`Value::String`].
+    String,
+}
+
 /// Convert a `T` into `serde_yaml_ng::Value` which is an enum that can represent
 /// any valid YAML data.
 ///
@@ -189,6 +226,240 @@ impl Value {
         index.index_into_mut(self)
     }
 
+    /// Looks up a key in a YAML mapping, like [`get`][Value::get] but with an
+    /// error that says what went wrong instead of `None`, for use with `?`.
+    ///
+    /// Returns an error if `self` is not a mapping, or if it has no entry
+    /// with this string as its key.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let config: Value = serde_yaml_ng::from_str("server: {port: 80}").unwrap();
+    /// assert_eq!(config.try_get("server")?.try_get("port")?, 80);
+    ///
+    /// let err = config.try_get("client").unwrap_err();
+    /// assert_eq!(err.to_string(), "missing key 'client' in mapping");
+    /// # Ok::<(), serde_yaml_ng::Error>(())
+    /// ```
+    pub fn try_get(&self, key: &str) -> Result<&Value, Error> {
+        match self.untag_ref() {
+            Value::Mapping(mapping) => mapping.get(key).ok_or_else(|| {
+                serde::de::Error::custom(format_args!("missing key '{}' in mapping", key))
+            }),
+            other => Err(other.invalid_type(&"a mapping")),
+        }
+    }
+
+    /// Looks up a value by a JSON Pointer.
+    ///
+    /// JSON Pointer defines a string syntax for identifying a specific value
+    /// within a document. A pointer is a Unicode string with the reference
+    /// tokens separated by `/`. Inside tokens `/` is replaced by `~1` and `~`
+    /// is replaced by `~0`. The addressed value is returned and if there is no
+    /// such value `None` is returned.
+    ///
+    /// A token selects the mapping entry whose key is that string, or failing
+    /// that, whose key is that integer. In a sequence, a token selects the
+    /// element at that index. Tags are looked through.
+    ///
+    /// For more information read [RFC6901](https://tools.ietf.org/html/rfc6901).
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let data: Value = serde_yaml_ng::from_str("
+    /// x:
+    ///   y: [z, zz]
+    /// 1: one
+    /// ").unwrap();
+    ///
+    /// assert_eq!(data.pointer("/x/y/1").unwrap(), "zz");
+    /// assert_eq!(data.pointer("/1").unwrap(), "one");
+    /// assert_eq!(data.pointer("/a/b/c"), None);
+    /// ```
+    pub fn pointer(&self, pointer: &str) -> Option<&Value> {
+        if pointer.is_empty() {
+            return Some(self);
+        }
+        if !pointer.starts_with('/') {
+            return None;
+        }
+        pointer
+            .split('/')
+            .skip(1)
+            .map(unescape_pointer_token)
+            .try_fold(self, |target, token| match target.untag_ref() {
+                Value::Mapping(map) => match map.get(&*token) {
+                    Some(value) => Some(value),
+                    None => map.get(parse_integer_key(&token)?),
+                },
+                Value::Sequence(seq) => seq.get(parse_index(&token)?),
+                _ => None,
+            })
+    }
+
+    /// Looks up a value by a JSON Pointer and returns a mutable reference to
+    /// that value.
+    ///
+    /// See [`Value::pointer`] for the pointer syntax.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let mut data: Value = serde_yaml_ng::from_str("x: {y: [z, zz]}").unwrap();
+    ///
+    /// *data.pointer_mut("/x/y/0").unwrap() = Value::from("replaced");
+    /// assert_eq!(data["x"]["y"][0], "replaced");
+    /// ```
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Value> {
+        if pointer.is_empty() {
+            return Some(self);
+        }
+        if !pointer.starts_with('/') {
+            return None;
+        }
+        pointer
+            .split('/')
+            .skip(1)
+            .map(unescape_pointer_token)
+            .try_fold(self, |target, token| match target.untag_mut() {
+                Value::Mapping(map) => {
+                    if map.contains_key(&*token) {
+                        map.get_mut(&*token)
+                    } else {
+                        map.get_mut(parse_integer_key(&token)?)
+                    }
+                }
+                Value::Sequence(seq) => seq.get_mut(parse_index(&token)?),
+                _ => None,
+            })
+    }
+
+    /// Returns true if a value exists at the given JSON Pointer, even if that
+    /// value is `Null`.
+    ///
+    /// See [`Value::pointer`] for the pointer syntax.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let data: Value = serde_yaml_ng::from_str("a: {b: ~}").unwrap();
+    ///
+    /// assert!(data.path_exists("/a/b"));
+    /// assert!(!data.path_exists("/a/c"));
+    /// ```
+    pub fn path_exists(&self, pointer: &str) -> bool {
+        self.pointer(pointer).is_some()
+    }
+
+    /// Builds a copy of this value that contains only what the given JSON
+    /// Pointers select, along with the mappings and sequences on the way to
+    /// them.
+    ///
+    /// See [`Value::pointer`] for the pointer syntax. Pointers that select
+    /// nothing are ignored, and pointers that overlap are merged, so `/a` and
+    /// `/a/b` together select all of `/a`. Entries and elements keep their
+    /// original order, which means the index of an element in a projected
+    /// sequence can be lower than in this value. Tags on the way to a
+    /// selected value are kept. If nothing is selected, the result is the
+    /// empty counterpart of this value, or `Null` for a scalar.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let config: Value = serde_yaml_ng::from_str("
+    /// db: {host: localhost, password: hunter2}
+    /// debug: true
+    /// ").unwrap();
+    ///
+    /// let projected = config.project(&["/db/host", "/debug"]);
+    /// assert_eq!(
+    ///     projected,
+    ///     serde_yaml_ng::from_str::<Value>("{db: {host: localhost}, debug: true}").unwrap(),
+    /// );
+    /// ```
+    pub fn project(&self, paths: &[&str]) -> Value {
+        fn build(value: &Value, selection: &Selection) -> Value {
+            let children = match selection {
+                Selection::Whole => return value.clone(),
+                Selection::Part(children) => children,
+            };
+            match value {
+                Value::Mapping(map) => Value::Mapping(
+                    map.iter()
+                        .enumerate()
+                        .filter_map(|(i, (k, v))| Some((k.clone(), build(v, children.get(&i)?))))
+                        .collect(),
+                ),
+                Value::Sequence(seq) => Value::Sequence(
+                    seq.iter()
+                        .enumerate()
+                        .filter_map(|(i, v)| Some(build(v, children.get(&i)?)))
+                        .collect(),
+                ),
+                Value::Tagged(tagged) => Value::Tagged(Box::new(TaggedValue {
+                    tag: tagged.tag.clone(),
+                    value: build(&tagged.value, selection),
+                })),
+                Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => Value::Null,
+            }
+        }
+
+        let mut selection = Selection::Part(BTreeMap::new());
+        for pointer in paths {
+            let positions = match pointer_positions(self, pointer) {
+                Some(positions) => positions,
+                None => continue,
+            };
+            let mut node = &mut selection;
+            for index in positions {
+                node = match node {
+                    Selection::Whole => break,
+                    Selection::Part(children) => children
+                        .entry(index)
+                        .or_insert_with(|| Selection::Part(BTreeMap::new())),
+                };
+            }
+            *node = Selection::Whole;
+        }
+
+        build(self, &selection)
+    }
+
+    /// Returns true if the `Value` is a null, boolean, number or string.
+    /// Returns false for sequences and mappings. Tags are looked through.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let v: Value = serde_yaml_ng::from_str("!secret hunter2").unwrap();
+    /// assert!(v.is_scalar());
+    /// ```
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let v: Value = serde_yaml_ng::from_str("[1, 2]").unwrap();
+    /// assert!(!v.is_scalar());
+    /// ```
+    pub fn is_scalar(&self) -> bool {
+        self.scalar_kind().is_some()
+    }
+
+    /// Returns which kind of scalar the `Value` is, or None for sequences and
+    /// mappings. Tags are looked through.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// use serde_yaml_ng::value::ScalarKind;
+    ///
+    /// let v: Value = serde_yaml_ng::from_str("1.5").unwrap();
+    /// assert_eq!(v.scalar_kind(), Some(ScalarKind::Number));
+    /// ```
+    pub fn scalar_kind(&self) -> Option<ScalarKind> {
+        match self.untag_ref() {
+            Value::Null => Some(ScalarKind::Null),
+            Value::Bool(_) => Some(ScalarKind::Bool),
+            Value::Number(_) => Some(ScalarKind::Number),
+            Value::String(_) => Some(ScalarKind::String),
+            Value::Sequence(_) | Value::Mapping(_) | Value::Tagged(_) => None,
+        }
+    }
+
     /// Returns true if the `Value` is a Null. Returns false otherwise.
     ///
     /// For any Value on which `is_null` returns true, `as_null` is guaranteed
@@ -378,6 +649,48 @@ impl Value {
         }
     }
 
+    /// If the `Value` is an integer, represent it as i128 if possible. Returns
+    /// None otherwise.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let v: Value = serde_yaml_ng::from_str("-9223372036854775809").unwrap();
+    /// assert_eq!(v.as_i128(), Some(i64::MIN as i128 - 1));
+    /// ```
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let v: Value = serde_yaml_ng::from_str("false").unwrap();
+    /// assert_eq!(v.as_i128(), None);
+    /// ```
+    pub fn as_i128(&self) -> Option<i128> {
+        match self.untag_ref() {
+            Value::Number(n) => n.as_i128(),
+            _ => None,
+        }
+    }
+
+    /// If the `Value` is an integer, represent it as u128 if possible. Returns
+    /// None otherwise.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let v: Value = serde_yaml_ng::from_str("18446744073709551616").unwrap();
+    /// assert_eq!(v.as_u128(), Some(u64::MAX as u128 + 1));
+    /// ```
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let v: Value = serde_yaml_ng::from_str("false").unwrap();
+    /// assert_eq!(v.as_u128(), None);
+    /// ```
+    pub fn as_u128(&self) -> Option<u128> {
+        match self.untag_ref() {
+            Value::Number(n) => n.as_u128(),
+            _ => None,
+        }
+    }
+
     /// Returns true if the `Value` is a number that can be represented by f64.
     ///
     /// For any Value on which `is_f64` returns true, `as_f64` is guaranteed to
@@ -466,6 +779,27 @@ impl Value {
         }
     }
 
+    /// If the `Value` is a String, returns a deserializer that hands out the
+    /// associated str. Returns None otherwise.
+    ///
+    /// This is for passing a string to code that expects one of serde's
+    /// string deserializers. The str is borrowed from the `Value`, so the
+    /// target may be `&str` as well as any type that deserializes from a
+    /// string.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// use serde::Deserialize;
+    ///
+    /// let v: Value = serde_yaml_ng::from_str("'10.0.0.1'").unwrap();
+    /// let de = v.as_str_deserializer().unwrap();
+    /// let ip = std::net::Ipv4Addr::deserialize(de).unwrap();
+    /// assert_eq!(ip, std::net::Ipv4Addr::new(10, 0, 0, 1));
+    /// ```
+    pub fn as_str_deserializer(&self) -> Option<BorrowedStrDeserializer<'_, Error>> {
+        self.as_str().map(BorrowedStrDeserializer::new)
+    }
+
     /// Returns true if the `Value` is a sequence. Returns false otherwise.
     ///
     /// ```
@@ -527,6 +861,150 @@ impl Value {
         }
     }
 
+    /// Takes the sequence out of this `Value`, or returns an error describing
+    /// what was found instead.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let v: Value = serde_yaml_ng::from_str("[a, b]").unwrap();
+    /// assert_eq!(v.try_into_sequence().unwrap().len(), 2);
+    ///
+    /// let v: Value = serde_yaml_ng::from_str("a: 1").unwrap();
+    /// assert_eq!(
+    ///     v.try_into_sequence().unwrap_err().to_string(),
+    ///     "invalid type: map, expected a sequence",
+    /// );
+    /// ```
+    pub fn try_into_sequence(self) -> Result<Sequence, Error> {
+        match self.untag() {
+            Value::Sequence(sequence) => Ok(sequence),
+            other => Err(other.invalid_type(&"a sequence")),
+        }
+    }
+
+    /// Deserializes every element of a YAML sequence into `T`.
+    ///
+    /// Returns an error if the `Value` is not a sequence, or if any element
+    /// fails to deserialize, in which case the message starts with the index
+    /// of that element.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let v: Value = serde_yaml_ng::from_str("[1, 2, 3]").unwrap();
+    /// assert_eq!(v.into_vec::<u32>().unwrap(), [1, 2, 3]);
+    ///
+    /// let v: Value = serde_yaml_ng::from_str("[1, 2, x]").unwrap();
+    /// assert_eq!(
+    ///     v.into_vec::<u32>().unwrap_err().to_string(),
+    ///     "[2]: invalid type: string \"x\", expected u32",
+    /// );
+    /// ```
+    pub fn into_vec<T>(self) -> Result<Vec<T>, Error>
+    where
+        T: DeserializeOwned,
+    {
+        match self.untag() {
+            Value::Sequence(sequence) => sequence
+                .into_iter()
+                .enumerate()
+                .map(|(index, element)| {
+                    T::deserialize(element).map_err(|err| {
+                        serde::de::Error::custom(format_args!("[{}]: {}", index, err))
+                    })
+                })
+                .collect(),
+            other => Err(other.invalid_type(&"a sequence")),
+        }
+    }
+
+    /// Deserializes every element of a YAML sequence into `T`, without
+    /// consuming the `Value`.
+    ///
+    /// Returns `None` if the `Value` is not a sequence or if any element fails
+    /// to deserialize. Use [`into_vec`][Value::into_vec] to find out which
+    /// element failed and why.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let v: Value = serde_yaml_ng::from_str("ports: [80, 443]").unwrap();
+    /// assert_eq!(v["ports"].as_sequence_of::<u16>(), Some(vec![80, 443]));
+    /// assert_eq!(v.as_sequence_of::<u16>(), None);
+    /// ```
+    pub fn as_sequence_of<T>(&self) -> Option<Vec<T>>
+    where
+        T: DeserializeOwned,
+    {
+        self.as_sequence()?
+            .iter()
+            .map(|element| T::deserialize(element).ok())
+            .collect()
+    }
+
+    /// Removes consecutive repeated elements of a sequence, like
+    /// [`Vec::dedup`]. Elements are compared with `==`.
+    ///
+    /// Does nothing if the `Value` is not a sequence. A tagged sequence is
+    /// deduplicated under its tag.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let mut v: Value = serde_yaml_ng::from_str("[a, a, b, a]").unwrap();
+    /// v.dedup_sequence();
+    /// assert_eq!(v, serde_yaml_ng::from_str::<Value>("[a, b, a]").unwrap());
+    /// ```
+    pub fn dedup_sequence(&mut self) {
+        if let Some(sequence) = self.as_sequence_mut() {
+            sequence.dedup();
+        }
+    }
+
+    /// Removes every repeated element of a sequence, keeping the first
+    /// occurrence of each. Elements are compared with `==`.
+    ///
+    /// Does nothing if the `Value` is not a sequence. A tagged sequence is
+    /// deduplicated under its tag.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let mut v: Value = serde_yaml_ng::from_str("[a, a, b, a]").unwrap();
+    /// v.dedup_sequence_all();
+    /// assert_eq!(v, serde_yaml_ng::from_str::<Value>("[a, b]").unwrap());
+    /// ```
+    pub fn dedup_sequence_all(&mut self) {
+        if let Some(sequence) = self.as_sequence_mut() {
+            let mut seen = HashSet::new();
+            let keep: Vec<bool> = sequence
+                .iter()
+                .map(|element| seen.insert(element))
+                .collect();
+            let mut keep = keep.into_iter();
+            sequence.retain(|_| keep.next().unwrap());
+        }
+    }
+
+    /// Sorts the elements of a sequence by the key that `key_fn` extracts
+    /// from each, like [`slice::sort_by_key`]. The sort is stable, so
+    /// elements with equal keys keep their order.
+    ///
+    /// Does nothing if the `Value` is not a sequence. A tagged sequence is
+    /// sorted under its tag.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let mut v: Value = serde_yaml_ng::from_str("[{name: web}, {name: db}]").unwrap();
+    /// v.sort_sequence_by(|service| service["name"].as_str().map(str::to_owned));
+    /// assert_eq!(v[0]["name"], "db");
+    /// ```
+    pub fn sort_sequence_by<F, K>(&mut self, key_fn: F)
+    where
+        F: FnMut(&Value) -> K,
+        K: Ord,
+    {
+        if let Some(sequence) = self.as_sequence_mut() {
+            sequence.sort_by_key(key_fn);
+        }
+    }
+
     /// Returns true if the `Value` is a mapping. Returns false otherwise.
     ///
     /// ```
@@ -597,10 +1075,39 @@ impl Value {
         }
     }
 
+    /// Takes the mapping out of this `Value`, or returns an error describing
+    /// what was found instead.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let v: Value = serde_yaml_ng::from_str("a: 1").unwrap();
+    /// assert_eq!(v.try_into_mapping().unwrap()["a"], 1);
+    ///
+    /// let v: Value = serde_yaml_ng::from_str("[a, b]").unwrap();
+    /// assert_eq!(
+    ///     v.try_into_mapping().unwrap_err().to_string(),
+    ///     "invalid type: sequence, expected a mapping",
+    /// );
+    /// ```
+    pub fn try_into_mapping(self) -> Result<Mapping, Error> {
+        match self.untag() {
+            Value::Mapping(mapping) => Ok(mapping),
+            other => Err(other.invalid_type(&"a mapping")),
+        }
+    }
+
     /// Performs merging of `<<` keys into the surrounding mapping.
     ///
     /// The intended use of this in YAML is described in
-    /// <https://yaml.org/type/merge.html>.
+    /// <https://yaml.org/type/merge.html>. Keys written in a mapping take
+    /// precedence over merged ones, and when `<<` holds a list of mappings,
+    /// earlier ones take precedence over later ones. A merged mapping may
+    /// itself contain `<<`, which is resolved first. Mappings nested anywhere
+    /// in the value are resolved too.
+    ///
+    /// Deserializing straight from YAML into a struct sees `<<` as an ordinary
+    /// key. To have merges applied, deserialize into a `Value`, call this,
+    /// and then convert with [`from_value`][crate::from_value].
     ///
     /// ```
     /// use serde_yaml_ng::Value;
@@ -630,19 +1137,11 @@ impl Value {
             match node {
                 Value::Mapping(mapping) => {
                     match mapping.remove("<<") {
-                        Some(Value::Mapping(merge)) => {
-                            for (k, v) in merge {
-                                mapping.entry(k).or_insert(v);
-                            }
-                        }
+                        Some(Value::Mapping(merge)) => merge_into(mapping, merge)?,
                         Some(Value::Sequence(sequence)) => {
                             for value in sequence {
                                 match value {
-                                    Value::Mapping(merge) => {
-                                        for (k, v) in merge {
-                                            mapping.entry(k).or_insert(v);
-                                        }
-                                    }
+                                    Value::Mapping(merge) => merge_into(mapping, merge)?,
                                     Value::Sequence(_) => {
                                         return Err(error::new(ErrorImpl::SequenceInMergeElement));
                                     }
@@ -668,10 +1167,565 @@ impl Value {
         }
         Ok(())
     }
+
+    /// Returns every string scalar in this value, in document order.
+    ///
+    /// Strings used as mapping keys are included only if `include_keys` is
+    /// true, in which case each key comes right before its value.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let value: Value = serde_yaml_ng::from_str("
+    /// url: ${HOST}/api
+    /// retries: 3
+    /// paths: ['${HOME}', /tmp]
+    /// ").unwrap();
+    ///
+    /// assert_eq!(value.collect_strings(false), ["${HOST}/api", "${HOME}", "/tmp"]);
+    /// assert_eq!(
+    ///     value.collect_strings(true),
+    ///     ["url", "${HOST}/api", "retries", "paths", "${HOME}", "/tmp"],
+    /// );
+    /// ```
+    pub fn collect_strings(&self, include_keys: bool) -> Vec<&str> {
+        fn collect<'a>(value: &'a Value, include_keys: bool, strings: &mut Vec<&'a str>) {
+            match value {
+                Value::String(string) => strings.push(string),
+                Value::Sequence(sequence) => {
+                    for element in sequence {
+                        collect(element, include_keys, strings);
+                    }
+                }
+                Value::Mapping(mapping) => {
+                    for (k, v) in mapping {
+                        if include_keys {
+                            collect(k, include_keys, strings);
+                        }
+                        collect(v, include_keys, strings);
+                    }
+                }
+                Value::Tagged(tagged) => collect(&tagged.value, include_keys, strings),
+                Value::Null | Value::Bool(_) | Value::Number(_) => {}
+            }
+        }
+
+        let mut strings = Vec::new();
+        collect(self, include_keys, &mut strings);
+        strings
+    }
+
+    /// Converts every null, boolean and number in this value into a string,
+    /// for consumers that only understand string scalars.
+    ///
+    /// Booleans become `"true"` or `"false"` and numbers are written the way
+    /// YAML would write them, like `"1"`, `"2.5"` or `".inf"`. Nulls are
+    /// spelled according to `null_style`. Strings and the structure of
+    /// sequences, mappings and tags are left alone, as are mapping keys.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::{NullStyle, Value};
+    /// let mut value: Value = serde_yaml_ng::from_str("[1, true, null, x]").unwrap();
+    /// value.stringify_scalars(NullStyle::Empty);
+    /// assert_eq!(value, serde_yaml_ng::from_str::<Value>("['1', 'true', '', x]").unwrap());
+    /// ```
+    pub fn stringify_scalars(&mut self, null_style: NullStyle) {
+        match self {
+            Value::Null => {
+                *self = Value::String(
+                    match null_style {
+                        NullStyle::Keyword => "null",
+                        NullStyle::Tilde => "~",
+                        NullStyle::Empty => "",
+                    }
+                    .to_owned(),
+                );
+            }
+            Value::Bool(boolean) => *self = Value::String(boolean.to_string()),
+            Value::Number(number) => *self = Value::String(number.to_string()),
+            Value::String(_) => {}
+            Value::Sequence(sequence) => {
+                for element in sequence {
+                    element.stringify_scalars(null_style);
+                }
+            }
+            Value::Mapping(mapping) => {
+                for value in mapping.values_mut() {
+                    value.stringify_scalars(null_style);
+                }
+            }
+            Value::Tagged(tagged) => tagged.value.stringify_scalars(null_style),
+        }
+    }
+
+    /// Recursively converts the null, boolean and number keys of every
+    /// mapping into strings, so that the value can be written to formats like
+    /// JSON where keys must be strings.
+    ///
+    /// Keys are spelled the way they are written in YAML: `null`, `true`,
+    /// `false`, and numbers in their decimal form. Tags on these keys, and on
+    /// string keys, are dropped. Sequence and mapping keys have no such
+    /// spelling and are left as they are.
+    ///
+    /// If two keys of a mapping turn into the same string, like `1` and `"1"`,
+    /// the last of them wins: the entry keeps the position of the first and
+    /// the value of the last.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let mut value: Value = serde_yaml_ng::from_str("{1: a, true: b, x: {2: c}}").unwrap();
+    /// value.stringify_keys();
+    /// assert_eq!(value, serde_yaml_ng::from_str::<Value>("{'1': a, 'true': b, x: {'2': c}}").unwrap());
+    /// ```
+    pub fn stringify_keys(&mut self) {
+        match self {
+            Value::Sequence(sequence) => {
+                for element in sequence {
+                    element.stringify_keys();
+                }
+            }
+            Value::Mapping(mapping) => {
+                let entries = mem::take(mapping);
+                for (key, mut value) in entries {
+                    let key = match key.untag_ref() {
+                        Value::Null => Value::String("null".to_owned()),
+                        Value::Bool(boolean) => Value::String(boolean.to_string()),
+                        Value::Number(number) => Value::String(number.to_string()),
+                        Value::String(string) => Value::String(string.clone()),
+                        Value::Sequence(_) | Value::Mapping(_) | Value::Tagged(_) => key,
+                    };
+                    value.stringify_keys();
+                    mapping.insert(key, value);
+                }
+            }
+            Value::Tagged(tagged) => tagged.value.stringify_keys(),
+            Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => {}
+        }
+    }
+
+    /// Counts how many times `needle` appears in this value, as this value
+    /// itself, a mapping key, a mapping value, a sequence element, or the
+    /// value under a tag.
+    ///
+    /// Values are compared with `==`, so a needle that is a sequence or
+    /// mapping matches equal subtrees, and an untagged needle does not match
+    /// a tagged value.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let value: Value = serde_yaml_ng::from_str("
+    /// eu: {region: eu, replicas: [eu, us]}
+    /// us: {region: us}
+    /// ").unwrap();
+    ///
+    /// assert_eq!(value.count_occurrences(&Value::from("eu")), 3);
+    /// assert_eq!(value.count_occurrences(&Value::from("asia")), 0);
+    /// ```
+    pub fn count_occurrences(&self, needle: &Value) -> usize {
+        let nested = match self {
+            Value::Sequence(sequence) => sequence
+                .iter()
+                .map(|element| element.count_occurrences(needle))
+                .sum(),
+            Value::Mapping(mapping) => mapping
+                .iter()
+                .map(|(k, v)| k.count_occurrences(needle) + v.count_occurrences(needle))
+                .sum(),
+            Value::Tagged(tagged) => tagged.value.count_occurrences(needle),
+            Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => 0,
+        };
+        usize::from(self == needle) + nested
+    }
+
+    /// Returns every string used as a mapping key anywhere in this value,
+    /// without duplicates.
+    ///
+    /// Keys that are not strings, like `1` or `[a, b]`, are skipped, but the
+    /// strings nested inside a non-string key are not treated as keys either.
+    /// A tagged string key counts as its string.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let value: Value = serde_yaml_ng::from_str("
+    /// server: {host: localhost, port: 80}
+    /// clients: [{host: a}, {name: b}]
+    /// ").unwrap();
+    ///
+    /// let keys: Vec<String> = value.all_keys().into_iter().collect();
+    /// assert_eq!(keys, ["clients", "host", "name", "port", "server"]);
+    /// ```
+    pub fn all_keys(&self) -> BTreeSet<String> {
+        fn collect(value: &Value, keys: &mut BTreeSet<String>) {
+            match value {
+                Value::Sequence(sequence) => {
+                    for element in sequence {
+                        collect(element, keys);
+                    }
+                }
+                Value::Mapping(mapping) => {
+                    for (k, v) in mapping {
+                        if let Value::String(key) = k.untag_ref() {
+                            if !keys.contains(key) {
+                                keys.insert(key.clone());
+                            }
+                        }
+                        collect(v, keys);
+                    }
+                }
+                Value::Tagged(tagged) => collect(&tagged.value, keys),
+                Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => {}
+            }
+        }
+
+        let mut keys = BTreeSet::new();
+        collect(self, &mut keys);
+        keys
+    }
+
+    /// Flattens nested mappings and sequences into a single-level mapping,
+    /// for systems that expect flat key-value pairs.
+    ///
+    /// Each scalar becomes an entry whose key is the path to it, with the
+    /// mapping keys and sequence indices along the way joined by `separator`.
+    /// Mapping keys that are nulls, booleans or numbers are spelled the way
+    /// YAML writes them, while entries whose key is a sequence or mapping are
+    /// skipped. Empty sequences and mappings are kept as values, and a scalar
+    /// at the root is keyed by the empty string. Tags on sequences and
+    /// mappings are looked through; tagged scalars are kept with their tag.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let value: Value = serde_yaml_ng::from_str("
+    /// db:
+    ///   hosts: [x, y]
+    ///   port: 5432
+    /// ").unwrap();
+    ///
+    /// let flat = value.flatten_to_map('.');
+    /// assert_eq!(flat["db.hosts.0"], "x");
+    /// assert_eq!(flat["db.hosts.1"], "y");
+    /// assert_eq!(flat["db.port"], 5432);
+    /// ```
+    pub fn flatten_to_map(&self, separator: char) -> Mapping {
+        fn flatten(
+            value: &Value,
+            prefix: &mut String,
+            root: bool,
+            separator: char,
+            flat: &mut Mapping,
+        ) {
+            let len = prefix.len();
+            match value.untag_ref() {
+                Value::Sequence(sequence) if !sequence.is_empty() => {
+                    for (index, element) in sequence.iter().enumerate() {
+                        if !root {
+                            prefix.push(separator);
+                        }
+                        prefix.push_str(&index.to_string());
+                        flatten(element, prefix, false, separator, flat);
+                        prefix.truncate(len);
+                    }
+                    return;
+                }
+                Value::Mapping(mapping) if !mapping.is_empty() => {
+                    for (k, v) in mapping {
+                        let key = match k.untag_ref() {
+                            Value::Null => "null".to_owned(),
+                            Value::Bool(boolean) => boolean.to_string(),
+                            Value::Number(number) => number.to_string(),
+                            Value::String(string) => string.clone(),
+                            Value::Sequence(_) | Value::Mapping(_) | Value::Tagged(_) => continue,
+                        };
+                        if !root {
+                            prefix.push(separator);
+                        }
+                        prefix.push_str(&key);
+                        flatten(v, prefix, false, separator, flat);
+                        prefix.truncate(len);
+                    }
+                    return;
+                }
+                _ => {}
+            }
+            flat.insert(Value::String(prefix.clone()), value.clone());
+        }
+
+        let mut flat = Mapping::new();
+        flatten(self, &mut String::new(), true, separator, &mut flat);
+        flat
+    }
+
+    /// Calls `f` on every node of this value, together with the path from the
+    /// root to that node.
+    ///
+    /// Nodes are visited parent first, so `f` may replace a sequence or
+    /// mapping and the replacement's children are visited next. The root is
+    /// visited with an empty path. A tagged value is visited once, and its
+    /// children are reached through the tag without adding to the path.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::value::PathSegment;
+    /// # use serde_yaml_ng::Value;
+    /// let mut value: Value = serde_yaml_ng::from_str("
+    /// db: {user: admin, password: hunter2}
+    /// cache: {password: swordfish}
+    /// ").unwrap();
+    ///
+    /// value.walk_paths_mut(|path, node| {
+    ///     if let Some(PathSegment::Key(key)) = path.last() {
+    ///         if key == "password" {
+    ///             *node = Value::from("***");
+    ///         }
+    ///     }
+    /// });
+    /// assert_eq!(value["db"]["password"], "***");
+    /// assert_eq!(value["cache"]["password"], "***");
+    /// assert_eq!(value["db"]["user"], "admin");
+    /// ```
+    pub fn walk_paths_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&[PathSegment], &mut Value),
+    {
+        fn walk<F>(path: &mut Vec<PathSegment>, value: &mut Value, f: &mut F)
+        where
+            F: FnMut(&[PathSegment], &mut Value),
+        {
+            f(path, value);
+            match value.untag_mut() {
+                Value::Sequence(sequence) => {
+                    for (index, element) in sequence.iter_mut().enumerate() {
+                        path.push(PathSegment::Index(index));
+                        walk(path, element, f);
+                        path.pop();
+                    }
+                }
+                Value::Mapping(mapping) => {
+                    for (key, value) in mapping {
+                        path.push(PathSegment::Key(key.clone()));
+                        walk(path, value, f);
+                        path.pop();
+                    }
+                }
+                Value::Null
+                | Value::Bool(_)
+                | Value::Number(_)
+                | Value::String(_)
+                | Value::Tagged(_) => {}
+            }
+        }
+
+        walk(&mut Vec::new(), self, &mut f);
+    }
+
+    /// Replaces every node for which `pred` returns true with the result of
+    /// calling `replace` on it.
+    ///
+    /// Nodes are visited parent first, and a replacement may have any shape.
+    /// After a node is replaced, the children of the replacement are visited
+    /// next, but the replacement itself is not tested again. So a replacement
+    /// that contains the node it replaced, like a sequence wrapping it, will
+    /// be replaced again inside, without end, unless `pred` no longer matches
+    /// there. Mapping keys are left alone. A tagged value is tested once, and
+    /// its children are reached through the tag.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let mut value: Value = serde_yaml_ng::from_str("[1, -2, [-3, 4]]").unwrap();
+    /// value.replace_if(
+    ///     |node| node.as_i64().map_or(false, |n| n < 0),
+    ///     |_node| Value::from(0),
+    /// );
+    /// assert_eq!(value, serde_yaml_ng::from_str::<Value>("[1, 0, [0, 4]]").unwrap());
+    /// ```
+    pub fn replace_if<P, F>(&mut self, mut pred: P, mut replace: F)
+    where
+        P: FnMut(&Value) -> bool,
+        F: FnMut(Value) -> Value,
+    {
+        fn walk<P, F>(value: &mut Value, pred: &mut P, replace: &mut F)
+        where
+            P: FnMut(&Value) -> bool,
+            F: FnMut(Value) -> Value,
+        {
+            if pred(value) {
+                *value = replace(mem::take(value));
+            }
+            match value.untag_mut() {
+                Value::Sequence(sequence) => {
+                    for element in sequence {
+                        walk(element, pred, replace);
+                    }
+                }
+                Value::Mapping(mapping) => {
+                    for value in mapping.values_mut() {
+                        walk(value, pred, replace);
+                    }
+                }
+                Value::Null
+                | Value::Bool(_)
+                | Value::Number(_)
+                | Value::String(_)
+                | Value::Tagged(_) => {}
+            }
+        }
+
+        walk(self, &mut pred, &mut replace);
+    }
+
+    /// Estimates the number of bytes of heap memory owned by this value,
+    /// including everything nested inside it.
+    ///
+    /// The estimate counts the capacity of strings and sequences, the entries
+    /// and hash index of mappings, and the box holding a tagged value. It does
+    /// not include the `Value` itself, nor any overhead of the allocator, so
+    /// it is a lower bound that is good for budgeting rather than exact.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let value = Value::String("x".repeat(1000));
+    /// assert!(value.deep_size_bytes() >= 1000);
+    /// assert_eq!(Value::Null.deep_size_bytes(), 0);
+    /// ```
+    pub fn deep_size_bytes(&self) -> usize {
+        match self {
+            Value::Null | Value::Bool(_) | Value::Number(_) => 0,
+            Value::String(string) => string.capacity(),
+            Value::Sequence(sequence) => {
+                let elements: usize = sequence.iter().map(Value::deep_size_bytes).sum();
+                sequence.capacity() * mem::size_of::<Value>() + elements
+            }
+            Value::Mapping(mapping) => {
+                // Each entry stores its key, value and hash, plus an index into
+                // the entries from the hash table.
+                let entry = 2 * mem::size_of::<Value>() + 2 * mem::size_of::<usize>();
+                let entries: usize = mapping
+                    .iter()
+                    .map(|(k, v)| k.deep_size_bytes() + v.deep_size_bytes())
+                    .sum();
+                mapping.capacity() * entry + entries
+            }
+            Value::Tagged(tagged) => {
+                mem::size_of::<TaggedValue>()
+                    + tagged.tag.string.capacity()
+                    + tagged.value.deep_size_bytes()
+            }
+        }
+    }
+}
+
+// The parts of a value picked out by `Value::project`, with the children of a
+// partly selected mapping or sequence keyed by their position.
+enum Selection {
+    Whole,
+    Part(BTreeMap<usize, Selection>),
+}
+
+// The index of each mapping entry or sequence element on the way to the
+// value at a JSON Pointer, looked up the same way as by `Value::pointer`.
+pub(crate) fn pointer_positions(value: &Value, pointer: &str) -> Option<Vec<usize>> {
+    fn child<'a>(value: &'a Value, token: &str) -> Option<(usize, &'a Value)> {
+        match value.untag_ref() {
+            Value::Mapping(map) => {
+                let (key, value) = match map.get_key_value(token) {
+                    Some(entry) => entry,
+                    None => map.get_key_value(parse_integer_key(token)?)?,
+                };
+                Some((map.keys().position(|k| ptr::eq(k, key))?, value))
+            }
+            Value::Sequence(seq) => {
+                let index = parse_index(token)?;
+                Some((index, seq.get(index)?))
+            }
+            _ => None,
+        }
+    }
+
+    if !pointer.is_empty() && !pointer.starts_with('/') {
+        return None;
+    }
+    let mut positions = Vec::new();
+    let mut target = value;
+    for token in pointer.split('/').skip(1).map(unescape_pointer_token) {
+        let (index, value) = child(target, &token)?;
+        positions.push(index);
+        target = value;
+    }
+    Some(positions)
+}
+
+fn unescape_pointer_token(token: &str) -> Cow<'_, str> {
+    if token.contains('~') {
+        Cow::Owned(token.replace("~1", "/").replace("~0", "~"))
+    } else {
+        Cow::Borrowed(token)
+    }
+}
+
+fn parse_index(token: &str) -> Option<usize> {
+    if token.starts_with('+') || (token.starts_with('0') && token.len() != 1) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+fn parse_integer_key(token: &str) -> Option<Value> {
+    if let Ok(n) = token.parse::<u64>() {
+        Some(Value::Number(n.into()))
+    } else if let Ok(n) = token.parse::<i64>() {
+        Some(Value::Number(n.into()))
+    } else {
+        None
+    }
+}
+
+fn merge_into(mapping: &mut Mapping, merge: Mapping) -> Result<(), Error> {
+    let mut merge = Value::Mapping(merge);
+    merge.apply_merge()?;
+    if let Value::Mapping(merge) = merge {
+        for (k, v) in merge {
+            mapping.entry(k).or_insert(v);
+        }
+    }
+    Ok(())
 }
 
 impl Eq for Value {}
 
+/// Values are totally ordered, so they can be sorted or kept in a
+/// `BTreeSet`.
+///
+/// Values of different kinds are ordered null, booleans, numbers, strings,
+/// sequences, mappings, then tagged values. Within each kind:
+///
+/// - `false` is less than `true`.
+/// - Numbers are compared by their numeric value, integers and floats alike.
+///   An integer is less than a float of the same value, so `1 < 1.0 < 2`.
+///   `-0.0` and `0.0` are equal, and NaN is greater than every other number.
+/// - Strings are compared by their bytes.
+/// - Sequences are compared element by element, and a sequence that is a
+///   prefix of another is less than it.
+/// - Mappings are compared as their entries sorted by key would be, first by
+///   key and then by value, since the order of entries does not matter for
+///   equality.
+/// - Tagged values are compared by tag, then by the value under the tag.
+///
+/// ```
+/// # use serde_yaml_ng::Value;
+/// let mut values: Vec<Value> = serde_yaml_ng::from_str("[b, 2.5, [a], ~, 1, a, true, {k: v}]").unwrap();
+/// values.sort();
+/// assert_eq!(values, serde_yaml_ng::from_str::<Vec<Value>>("[~, true, 1, 2.5, a, b, [a], {k: v}]").unwrap());
+/// ```
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        crate::mapping::total_cmp(self, other)
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 // NOTE: This impl must be kept consistent with HashLikeValue's Hash impl in
 // mapping.rs in order for value[str] indexing to work.
 impl Hash for Value {
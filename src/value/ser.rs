@@ -1,8 +1,11 @@
 use crate::error::{self, Error, ErrorImpl};
 use crate::value::tagged::{self, MaybeTag};
 use crate::value::{to_value, Mapping, Number, Sequence, Tag, TaggedValue, Value};
+use crate::{mapping, SerializeOptions};
 use serde::ser::{self, Serialize};
+use std::borrow::Cow;
 use std::fmt::Display;
+use std::io;
 use std::mem;
 
 type Result<T, E = Error> = std::result::Result<T, E>;
@@ -31,6 +34,80 @@ impl Serialize for Value {
     }
 }
 
+impl Value {
+    /// Serializes this value as YAML into the IO stream, formatted according
+    /// to `options`.
+    ///
+    /// ```
+    /// use serde_yaml_ng::{SerializeOptions, Value};
+    ///
+    /// let value: Value = serde_yaml_ng::from_str("{z: 1, a: {y: 2}}").unwrap();
+    /// let mut buffer = Vec::new();
+    /// let options = SerializeOptions::new().sort_keys(true);
+    /// value.pretty_print_to(&mut buffer, &options).unwrap();
+    /// assert_eq!(buffer, b"a:\n  y: 2\nz: 1\n");
+    /// ```
+    pub fn pretty_print_to<W>(&self, writer: W, options: &SerializeOptions) -> Result<()>
+    where
+        W: io::Write,
+    {
+        let mut value = Cow::Borrowed(self);
+        if options.sorts_keys() || options.sorts_all_sequences() {
+            value
+                .to_mut()
+                .sort_recursive(options.sorts_keys(), options.sorts_all_sequences());
+        }
+        let mut serializer = crate::Serializer::new(writer).apply_options(options);
+        value.serialize(&mut serializer)
+    }
+
+    /// Serializes this value as a String of YAML, formatted according to
+    /// `options`.
+    ///
+    /// See [`SerializeOptions`] for an example.
+    pub fn to_string_with(&self, options: &SerializeOptions) -> Result<String> {
+        let mut vec = Vec::with_capacity(128);
+        self.pretty_print_to(&mut vec, options)?;
+        String::from_utf8(vec).map_err(|error| error::new(ErrorImpl::FromUtf8(error)))
+    }
+
+    fn sort_recursive(&mut self, keys: bool, sequences: bool) {
+        match self {
+            Value::Sequence(sequence) => {
+                for element in sequence.iter_mut() {
+                    element.sort_recursive(keys, sequences);
+                }
+                if sequences {
+                    sequence.sort_by(mapping::total_cmp);
+                }
+            }
+            Value::Mapping(mapping) => {
+                // Keys are sorted after their own contents, so that keys that
+                // are collections compare by their sorted form.
+                if sequences {
+                    *mapping = mem::take(mapping)
+                        .into_iter()
+                        .map(|(mut k, mut v)| {
+                            k.sort_recursive(keys, sequences);
+                            v.sort_recursive(keys, sequences);
+                            (k, v)
+                        })
+                        .collect();
+                } else {
+                    for value in mapping.values_mut() {
+                        value.sort_recursive(keys, sequences);
+                    }
+                }
+                if keys {
+                    mapping.sort_keys();
+                }
+            }
+            Value::Tagged(tagged) => tagged.value.sort_recursive(keys, sequences),
+            Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => {}
+        }
+    }
+}
+
 /// Serializer whose output is a `Value`.
 ///
 /// This is the serializer that backs [`serde_yaml_ng::to_value`][crate::to_value].
@@ -86,13 +163,7 @@ impl ser::Serializer for Serializer {
     }
 
     fn serialize_i128(self, v: i128) -> Result<Value> {
-        if let Ok(v) = u64::try_from(v) {
-            self.serialize_u64(v)
-        } else if let Ok(v) = i64::try_from(v) {
-            self.serialize_i64(v)
-        } else {
-            Ok(Value::String(v.to_string()))
-        }
+        Ok(Value::Number(Number::from(v)))
     }
 
     fn serialize_u8(self, v: u8) -> Result<Value> {
@@ -112,11 +183,7 @@ impl ser::Serializer for Serializer {
     }
 
     fn serialize_u128(self, v: u128) -> Result<Value> {
-        if let Ok(v) = u64::try_from(v) {
-            self.serialize_u64(v)
-        } else {
-            Ok(Value::String(v.to_string()))
-        }
+        Ok(Value::Number(Number::from(v)))
     }
 
     fn serialize_f32(self, v: f32) -> Result<Value> {
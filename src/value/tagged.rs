@@ -55,6 +55,8 @@ pub struct TaggedValue {
     pub value: Value,
 }
 
+const EMPTY_VALUE_TAG: &str = "!empty";
+
 impl Tag {
     /// Create tag.
     ///
@@ -92,6 +94,87 @@ impl Tag {
 }
 
 impl Value {
+    /// Returns the tag attached to this value, if any.
+    ///
+    /// A value tagged more than once returns the outermost tag.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let value: Value = serde_yaml_ng::from_str("!Ref MyBucket").unwrap();
+    /// assert_eq!(value.tag().unwrap(), "Ref");
+    /// assert_eq!(Value::from("MyBucket").tag(), None);
+    /// ```
+    pub fn tag(&self) -> Option<&Tag> {
+        match self {
+            Value::Tagged(tagged) => Some(&tagged.tag),
+            _ => None,
+        }
+    }
+
+    /// Returns true if this is an empty value, like `key:`, that was loaded
+    /// with [`Deserializer::mark_empty_values`][crate::Deserializer::mark_empty_values].
+    ///
+    /// Such a value is a null tagged `!empty`, so [`Value::is_null`] is true
+    /// for it as well. An explicit null, like `key: null`, is not an empty
+    /// value.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let value: Value = serde_yaml_ng::from_str("!empty").unwrap();
+    /// assert!(value.is_empty_value());
+    /// assert!(!Value::Null.is_empty_value());
+    /// ```
+    pub fn is_empty_value(&self) -> bool {
+        match self {
+            Value::Tagged(tagged) => tagged.tag == EMPTY_VALUE_TAG && tagged.value.is_null(),
+            _ => false,
+        }
+    }
+
+    pub(crate) fn empty_value() -> Value {
+        Value::Tagged(Box::new(TaggedValue {
+            tag: Tag::new(EMPTY_VALUE_TAG),
+            value: Value::Null,
+        }))
+    }
+
+    /// Recursively replaces every tagged value in this tree with the value
+    /// inside it, discarding the tags.
+    ///
+    /// Tags on mapping keys are removed too. If that makes two keys equal,
+    /// the later entry's value is kept at the earlier entry's position.
+    ///
+    /// ```
+    /// # use serde_yaml_ng::Value;
+    /// let mut value: Value = serde_yaml_ng::from_str("{a: !x 1, b: !y [!z c]}").unwrap();
+    /// value.strip_tags();
+    /// assert_eq!(value, serde_yaml_ng::from_str::<Value>("{a: 1, b: [c]}").unwrap());
+    /// ```
+    pub fn strip_tags(&mut self) {
+        match self {
+            Value::Tagged(_) => {
+                *self = mem::take(self).untag();
+                self.strip_tags();
+            }
+            Value::Sequence(sequence) => {
+                for element in sequence {
+                    element.strip_tags();
+                }
+            }
+            Value::Mapping(mapping) => {
+                *mapping = mem::take(mapping)
+                    .into_iter()
+                    .map(|(mut k, mut v)| {
+                        k.strip_tags();
+                        v.strip_tags();
+                        (k, v)
+                    })
+                    .collect();
+            }
+            Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => {}
+        }
+    }
+
     pub(crate) fn untag(self) -> Self {
         let mut cur = self;
         while let Value::Tagged(tagged) = cur {
@@ -0,0 +1,176 @@
+use crate::error::Error;
+use crate::value::{Mapping, Sequence, Value};
+use serde::de::{self, Deserialize, Expected};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Checks the fields of a [`Value`] one at a time, collecting an error for
+/// every field that is missing or has the wrong type rather than stopping at
+/// the first.
+///
+/// A validator is created by [`Value::validator`] and points at one node of
+/// the value. [`require`][Validator::require] and
+/// [`optional`][Validator::optional] step into a mapping entry, and accessors
+/// like [`as_str`][Validator::as_str] read the node. On failure they return
+/// `None` and record an error that starts with the path to the field, like
+/// `server.port: invalid type: ...`. Once a field is missing, nothing below it
+/// records further errors. [`finish`][Validator::finish] hands over every
+/// error recorded through the validator and the ones derived from it.
+///
+/// ```
+/// # use serde_yaml_ng::Value;
+/// let config: Value = serde_yaml_ng::from_str("
+/// server: {host: localhost, port: http}
+/// ").unwrap();
+///
+/// let v = config.validator();
+/// let server = v.require("server");
+/// assert_eq!(server.require("host").as_str(), Some("localhost"));
+/// assert_eq!(server.require("port").as_u64(), None);
+/// assert_eq!(server.optional("timeout").as_u64(), None);
+/// assert_eq!(v.require("name").as_str(), None);
+///
+/// let errors: Vec<String> = v.finish().unwrap_err().iter().map(ToString::to_string).collect();
+/// assert_eq!(errors, [
+///     "server.port: invalid type: string \"http\", expected an unsigned integer",
+///     "missing field `name`",
+/// ]);
+/// ```
+pub struct Validator<'a> {
+    value: Option<&'a Value>,
+    path: String,
+    errors: Rc<RefCell<Vec<Error>>>,
+}
+
+impl Value {
+    /// Starts checking the fields of this value. See [`Validator`].
+    pub fn validator(&self) -> Validator<'_> {
+        Validator {
+            value: Some(self),
+            path: String::new(),
+            errors: Rc::new(RefCell::new(Vec::new())),
+        }
+    }
+}
+
+impl<'a> Validator<'a> {
+    /// Steps into the entry with this key, recording an error if there is no
+    /// such entry.
+    pub fn require(&self, key: &str) -> Validator<'a> {
+        self.entry(key, true)
+    }
+
+    /// Steps into the entry with this key, which may be absent. If it is,
+    /// the accessors of the returned validator return `None` without
+    /// recording an error.
+    pub fn optional(&self, key: &str) -> Validator<'a> {
+        self.entry(key, false)
+    }
+
+    fn entry(&self, key: &str, required: bool) -> Validator<'a> {
+        let value = self.value.and_then(|value| match value.untag_ref() {
+            Value::Mapping(mapping) => {
+                let entry = mapping.get(key);
+                if entry.is_none() && required {
+                    self.record(de::Error::custom(format_args!("missing field `{}`", key)));
+                }
+                entry
+            }
+            other => {
+                self.record(other.invalid_type(&"a mapping"));
+                None
+            }
+        });
+        Validator {
+            value,
+            path: if self.path.is_empty() {
+                key.to_owned()
+            } else {
+                format!("{}.{}", self.path, key)
+            },
+            errors: Rc::clone(&self.errors),
+        }
+    }
+
+    /// The node this validator points at, if it is present.
+    pub fn value(&self) -> Option<&'a Value> {
+        self.value
+    }
+
+    /// The node as a string, recording an error if it is something else.
+    pub fn as_str(&self) -> Option<&'a str> {
+        self.check(Value::as_str, &"a string")
+    }
+
+    /// The node as a boolean, recording an error if it is something else.
+    pub fn as_bool(&self) -> Option<bool> {
+        self.check(Value::as_bool, &"a boolean")
+    }
+
+    /// The node as an `i64`, recording an error if it is something else.
+    pub fn as_i64(&self) -> Option<i64> {
+        self.check(Value::as_i64, &"an integer")
+    }
+
+    /// The node as a `u64`, recording an error if it is something else.
+    pub fn as_u64(&self) -> Option<u64> {
+        self.check(Value::as_u64, &"an unsigned integer")
+    }
+
+    /// The node as an `f64`, recording an error if it is not a number.
+    pub fn as_f64(&self) -> Option<f64> {
+        self.check(Value::as_f64, &"a number")
+    }
+
+    /// The node as a sequence, recording an error if it is something else.
+    pub fn as_sequence(&self) -> Option<&'a Sequence> {
+        self.check(Value::as_sequence, &"a sequence")
+    }
+
+    /// The node as a mapping, recording an error if it is something else.
+    pub fn as_mapping(&self) -> Option<&'a Mapping> {
+        self.check(Value::as_mapping, &"a mapping")
+    }
+
+    /// Deserializes the node into `T`, recording the error if that fails.
+    pub fn deserialize<T>(&self) -> Option<T>
+    where
+        T: Deserialize<'a>,
+    {
+        match T::deserialize(self.value?) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.record(err);
+                None
+            }
+        }
+    }
+
+    /// Returns every error recorded so far, or `Ok` if there were none.
+    pub fn finish(self) -> Result<(), Vec<Error>> {
+        let errors = self.errors.take();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn check<T>(&self, get: fn(&'a Value) -> Option<T>, expected: &dyn Expected) -> Option<T> {
+        let value = self.value?;
+        let result = get(value);
+        if result.is_none() {
+            self.record(value.untag_ref().invalid_type(expected));
+        }
+        result
+    }
+
+    fn record(&self, err: Error) {
+        let err = if self.path.is_empty() {
+            err
+        } else {
+            de::Error::custom(format_args!("{}: {}", self.path, err))
+        };
+        self.errors.borrow_mut().push(err);
+    }
+}
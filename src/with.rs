@@ -2174,3 +2174,397 @@ pub mod singleton_map_recursive {
         }
     }
 }
+
+/// Serialize/deserialize a C-like enum as the integer index of its variant.
+///
+/// Variants are numbered in the order they are declared, starting at 0, which
+/// is the `variant_index` that Serde's derive passes to the serializer.
+/// Explicit discriminants like `Green = 10` are not visible to Serde and have
+/// no effect. Only unit variants are supported.
+///
+/// # Example
+///
+/// ```
+/// # use serde_derive::{Deserialize, Serialize};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize, PartialEq, Debug)]
+/// enum Level {
+///     Low,
+///     Medium,
+///     High,
+/// }
+///
+/// #[derive(Serialize, Deserialize, PartialEq, Debug)]
+/// struct Struct {
+///     #[serde(with = "serde_yaml_ng::with::enum_as_int")]
+///     level: Level,
+/// }
+///
+/// fn main() {
+///     let object = Struct { level: Level::High };
+///
+///     let yaml = serde_yaml_ng::to_string(&object).unwrap();
+///     assert_eq!(yaml, "level: 2\n");
+///
+///     let deserialized: Struct = serde_yaml_ng::from_str(&yaml).unwrap();
+///     assert_eq!(object, deserialized);
+/// }
+/// ```
+pub mod enum_as_int {
+    use crate::error::Error;
+    use serde::de::{self, Deserialize, Deserializer, IntoDeserializer, Unexpected, Visitor};
+    use serde::ser::{self, Impossible, Serialize, Serializer};
+    use std::fmt::{self, Display};
+    use std::marker::PhantomData;
+
+    type Result<T, E = Error> = std::result::Result<T, E>;
+
+    #[allow(missing_docs)]
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Serialize,
+        S: Serializer,
+    {
+        let index = value
+            .serialize(UnitVariantIndex)
+            .map_err(ser::Error::custom)?;
+        serializer.serialize_u32(index)
+    }
+
+    #[allow(missing_docs)]
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_u32(VariantIndexVisitor(PhantomData))
+    }
+
+    struct VariantIndexVisitor<T>(PhantomData<T>);
+
+    impl<'de, T> Visitor<'de> for VariantIndexVisitor<T>
+    where
+        T: Deserialize<'de>,
+    {
+        type Value = T;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a variant index")
+        }
+
+        fn visit_u64<E>(self, v: u64) -> Result<T, E>
+        where
+            E: de::Error,
+        {
+            let index = match u32::try_from(v) {
+                Ok(index) => index,
+                Err(_) => return Err(de::Error::invalid_value(Unexpected::Unsigned(v), &self)),
+            };
+            T::deserialize(VariantIndex {
+                index,
+                error: PhantomData,
+            })
+        }
+    }
+
+    fn not_unit_variant() -> Error {
+        ser::Error::custom("enum_as_int can only serialize a unit variant of an enum")
+    }
+
+    // Serializer that produces the index of a unit variant and rejects
+    // everything else.
+    struct UnitVariantIndex;
+
+    impl Serializer for UnitVariantIndex {
+        type Ok = u32;
+        type Error = Error;
+
+        type SerializeSeq = Impossible<u32, Error>;
+        type SerializeTuple = Impossible<u32, Error>;
+        type SerializeTupleStruct = Impossible<u32, Error>;
+        type SerializeTupleVariant = Impossible<u32, Error>;
+        type SerializeMap = Impossible<u32, Error>;
+        type SerializeStruct = Impossible<u32, Error>;
+        type SerializeStructVariant = Impossible<u32, Error>;
+
+        fn serialize_bool(self, _v: bool) -> Result<u32> {
+            Err(not_unit_variant())
+        }
+        fn serialize_i8(self, _v: i8) -> Result<u32> {
+            Err(not_unit_variant())
+        }
+        fn serialize_i16(self, _v: i16) -> Result<u32> {
+            Err(not_unit_variant())
+        }
+        fn serialize_i32(self, _v: i32) -> Result<u32> {
+            Err(not_unit_variant())
+        }
+        fn serialize_i64(self, _v: i64) -> Result<u32> {
+            Err(not_unit_variant())
+        }
+        fn serialize_i128(self, _v: i128) -> Result<u32> {
+            Err(not_unit_variant())
+        }
+        fn serialize_u8(self, _v: u8) -> Result<u32> {
+            Err(not_unit_variant())
+        }
+        fn serialize_u16(self, _v: u16) -> Result<u32> {
+            Err(not_unit_variant())
+        }
+        fn serialize_u32(self, _v: u32) -> Result<u32> {
+            Err(not_unit_variant())
+        }
+        fn serialize_u64(self, _v: u64) -> Result<u32> {
+            Err(not_unit_variant())
+        }
+        fn serialize_u128(self, _v: u128) -> Result<u32> {
+            Err(not_unit_variant())
+        }
+        fn serialize_f32(self, _v: f32) -> Result<u32> {
+            Err(not_unit_variant())
+        }
+        fn serialize_f64(self, _v: f64) -> Result<u32> {
+            Err(not_unit_variant())
+        }
+        fn serialize_char(self, _v: char) -> Result<u32> {
+            Err(not_unit_variant())
+        }
+        fn serialize_str(self, _v: &str) -> Result<u32> {
+            Err(not_unit_variant())
+        }
+        fn serialize_bytes(self, _v: &[u8]) -> Result<u32> {
+            Err(not_unit_variant())
+        }
+
+        fn serialize_unit(self) -> Result<u32> {
+            Err(not_unit_variant())
+        }
+
+        fn serialize_unit_struct(self, _name: &'static str) -> Result<u32> {
+            Err(not_unit_variant())
+        }
+
+        fn serialize_unit_variant(
+            self,
+            _name: &'static str,
+            variant_index: u32,
+            _variant: &'static str,
+        ) -> Result<u32> {
+            Ok(variant_index)
+        }
+
+        fn serialize_newtype_struct<T>(self, _name: &'static str, _value: &T) -> Result<u32>
+        where
+            T: ?Sized + Serialize,
+        {
+            Err(not_unit_variant())
+        }
+
+        fn serialize_newtype_variant<T>(
+            self,
+            _name: &'static str,
+            _variant_index: u32,
+            _variant: &'static str,
+            _value: &T,
+        ) -> Result<u32>
+        where
+            T: ?Sized + Serialize,
+        {
+            Err(not_unit_variant())
+        }
+
+        fn serialize_none(self) -> Result<u32> {
+            Err(not_unit_variant())
+        }
+
+        fn serialize_some<T>(self, _value: &T) -> Result<u32>
+        where
+            T: ?Sized + Serialize,
+        {
+            Err(not_unit_variant())
+        }
+
+        fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+            Err(not_unit_variant())
+        }
+
+        fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+            Err(not_unit_variant())
+        }
+
+        fn serialize_tuple_struct(
+            self,
+            _name: &'static str,
+            _len: usize,
+        ) -> Result<Self::SerializeTupleStruct> {
+            Err(not_unit_variant())
+        }
+
+        fn serialize_tuple_variant(
+            self,
+            _name: &'static str,
+            _variant_index: u32,
+            _variant: &'static str,
+            _len: usize,
+        ) -> Result<Self::SerializeTupleVariant> {
+            Err(not_unit_variant())
+        }
+
+        fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+            Err(not_unit_variant())
+        }
+
+        fn serialize_struct(
+            self,
+            _name: &'static str,
+            _len: usize,
+        ) -> Result<Self::SerializeStruct> {
+            Err(not_unit_variant())
+        }
+
+        fn serialize_struct_variant(
+            self,
+            _name: &'static str,
+            _variant_index: u32,
+            _variant: &'static str,
+            _len: usize,
+        ) -> Result<Self::SerializeStructVariant> {
+            Err(not_unit_variant())
+        }
+
+        fn collect_str<T>(self, _value: &T) -> Result<u32>
+        where
+            T: ?Sized + Display,
+        {
+            Err(not_unit_variant())
+        }
+    }
+
+    // Deserializer that hands out the variant at the given index of whichever
+    // enum asks for it.
+    struct VariantIndex<E> {
+        index: u32,
+        error: PhantomData<E>,
+    }
+
+    impl<'de, E> Deserializer<'de> for VariantIndex<E>
+    where
+        E: de::Error,
+    {
+        type Error = E;
+
+        fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, E>
+        where
+            V: Visitor<'de>,
+        {
+            visitor.visit_u32(self.index)
+        }
+
+        fn deserialize_enum<V>(
+            self,
+            _name: &'static str,
+            variants: &'static [&'static str],
+            visitor: V,
+        ) -> Result<V::Value, E>
+        where
+            V: Visitor<'de>,
+        {
+            match variants.get(self.index as usize) {
+                Some(variant) => visitor.visit_enum(variant.into_deserializer()),
+                None => {
+                    let expected = format!("variant index 0 <= i < {}", variants.len());
+                    Err(de::Error::invalid_value(
+                        Unexpected::Unsigned(u64::from(self.index)),
+                        &expected.as_str(),
+                    ))
+                }
+            }
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map struct identifier ignored_any
+        }
+    }
+}
+
+/// Serialize/deserialize a list of key-value pairs as a YAML mapping.
+///
+/// Entries are kept in document order and duplicate keys are preserved, which
+/// a map type like `BTreeMap` or [`Mapping`][crate::Mapping] cannot do.
+///
+/// # Example
+///
+/// ```
+/// # use serde_derive::{Deserialize, Serialize};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize, PartialEq, Debug)]
+/// struct Struct {
+///     #[serde(with = "serde_yaml_ng::with::pairs")]
+///     env: Vec<(String, String)>,
+/// }
+///
+/// fn main() {
+///     let yaml = "env:\n  PATH: /bin\n  PATH: /usr/bin\n";
+///
+///     let deserialized: Struct = serde_yaml_ng::from_str(yaml).unwrap();
+///     assert_eq!(deserialized.env.len(), 2);
+///
+///     let serialized = serde_yaml_ng::to_string(&deserialized).unwrap();
+///     assert_eq!(serialized, yaml);
+/// }
+/// ```
+pub mod pairs {
+    use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
+    use serde::ser::{Serialize, Serializer};
+    use std::fmt;
+    use std::marker::PhantomData;
+
+    #[allow(missing_docs)]
+    pub fn serialize<K, V, S>(pairs: &[(K, V)], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        K: Serialize,
+        V: Serialize,
+        S: Serializer,
+    {
+        serializer.collect_map(pairs.iter().map(|(k, v)| (k, v)))
+    }
+
+    #[allow(missing_docs)]
+    pub fn deserialize<'de, K, V, D>(deserializer: D) -> Result<Vec<(K, V)>, D::Error>
+    where
+        K: Deserialize<'de>,
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(PairsVisitor(PhantomData))
+    }
+
+    struct PairsVisitor<K, V>(PhantomData<(K, V)>);
+
+    impl<'de, K, V> Visitor<'de> for PairsVisitor<K, V>
+    where
+        K: Deserialize<'de>,
+        V: Deserialize<'de>,
+    {
+        type Value = Vec<(K, V)>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a map")
+        }
+
+        fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+        where
+            A: MapAccess<'de>,
+        {
+            let mut pairs = Vec::new();
+            while let Some(pair) = map.next_entry()? {
+                pairs.push(pair);
+            }
+            Ok(pairs)
+        }
+    }
+}
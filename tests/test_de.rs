@@ -7,8 +7,9 @@
 )]
 
 use indoc::indoc;
+use serde::Deserialize as _;
 use serde_derive::Deserialize;
-use serde_yaml_ng::{Deserializer, Number, Value};
+use serde_yaml_ng::{Deserializer, Mapping, Number, Value};
 use std::collections::BTreeMap;
 use std::fmt::Debug;
 
@@ -69,6 +70,107 @@ fn test_borrowed() {
     test_de_no_value(yaml, &expected);
 }
 
+#[test]
+fn test_from_str_multi() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Resource {
+        kind: String,
+        name: String,
+    }
+
+    let yaml = indoc! {"
+        kind: Service
+        name: web
+        ---
+        kind: Deployment
+        name: web
+        ---
+        # comment only
+        kind: ConfigMap
+        name: settings
+    "};
+    let resources: Vec<Resource> = serde_yaml_ng::from_str_multi(yaml)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    let expected = [
+        ("Service", "web"),
+        ("Deployment", "web"),
+        ("ConfigMap", "settings"),
+    ]
+    .map(|(kind, name)| Resource {
+        kind: kind.to_owned(),
+        name: name.to_owned(),
+    });
+    assert_eq!(resources, expected);
+
+    let values: Vec<Value> = serde_yaml_ng::from_str_multi("--- 1\n--- two\n--- [3]\n")
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        values,
+        [
+            Value::from(1),
+            Value::from("two"),
+            Value::Sequence(vec![Value::from(3)])
+        ]
+    );
+}
+
+#[test]
+fn test_predefined_anchors() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Job {
+        region: String,
+        retries: u32,
+        limits: BTreeMap<String, u32>,
+    }
+
+    let mut limits = Mapping::new();
+    limits.insert(Value::from("cpu"), Value::from(2));
+    let mut anchors = std::collections::HashMap::new();
+    anchors.insert("region".to_owned(), Value::from("eu-west-1"));
+    anchors.insert("retries".to_owned(), Value::from(1));
+    anchors.insert("limits".to_owned(), Value::Mapping(limits));
+
+    let yaml = indoc! {"
+        region: *region
+        retries: &retries 3
+        again: *retries
+        limits: *limits
+    "};
+    let de = Deserializer::from_str(yaml).predefined_anchors(anchors.clone());
+    let value = Value::deserialize(de).unwrap();
+    assert_eq!(value["region"], "eu-west-1");
+    // An anchor in the document shadows the predefined one.
+    assert_eq!(value["again"], 3);
+    assert_eq!(value["limits"]["cpu"], 2);
+
+    let de = Deserializer::from_str(yaml).predefined_anchors(anchors.clone());
+    let job = Job::deserialize(de).unwrap();
+    let expected = Job {
+        region: "eu-west-1".to_owned(),
+        retries: 3,
+        limits: BTreeMap::from([("cpu".to_owned(), 2)]),
+    };
+    assert_eq!(job, expected);
+
+    let de = Deserializer::from_str("region: *zone").predefined_anchors(anchors);
+    let error = Value::deserialize(de).unwrap_err();
+    assert_eq!(error.to_string(), "unknown anchor at line 1 column 9");
+
+    // A large predefined value can be used, but not copied without limit.
+    let big = Value::Sequence(vec![Value::from("x"); 1000]);
+    let anchors = std::collections::HashMap::from([("big".to_owned(), big)]);
+    let de = Deserializer::from_str("*big").predefined_anchors(anchors.clone());
+    let value = Value::deserialize(de).unwrap();
+    assert_eq!(value.as_sequence().unwrap().len(), 1000);
+
+    let yaml = format!("[{}]", vec!["*big"; 1000].join(", "));
+    let de = Deserializer::from_str(&yaml).predefined_anchors(anchors);
+    let error = Value::deserialize(de).unwrap_err();
+    assert_eq!(error.to_string(), "repetition limit exceeded");
+}
+
 #[test]
 fn test_alias() {
     let yaml = indoc! {"
@@ -86,6 +188,39 @@ fn test_alias() {
     test_de(yaml, &expected);
 }
 
+#[test]
+fn test_alias_into_renamed_field() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Settings {
+        #[serde(rename = "base-url")]
+        base_url: String,
+        #[serde(rename = "mirrorUrl")]
+        mirror_url: String,
+        #[serde(rename = "retry-policy")]
+        retry_policy: Retry,
+        fallback: Retry,
+    }
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Retry {
+        #[serde(rename = "max-attempts")]
+        max_attempts: u32,
+    }
+    let yaml = indoc! {"
+        base-url: &url https://example.com
+        mirrorUrl: *url
+        retry-policy: &retry
+          max-attempts: 3
+        fallback: *retry
+    "};
+    let expected = Settings {
+        base_url: "https://example.com".to_owned(),
+        mirror_url: "https://example.com".to_owned(),
+        retry_policy: Retry { max_attempts: 3 },
+        fallback: Retry { max_attempts: 3 },
+    };
+    test_de(yaml, &expected);
+}
+
 #[test]
 fn test_option() {
     #[derive(Deserialize, PartialEq, Debug)]
@@ -304,6 +439,43 @@ fn test_u128_big() {
     assert_eq!(expected, serde_yaml_ng::from_str::<u128>(octal).unwrap());
 }
 
+#[test]
+fn test_big_int_value() {
+    let value: Value = serde_yaml_ng::from_str("18446744073709551615").unwrap();
+    assert_eq!(value, Value::Number(Number::from(u64::MAX)));
+    assert_eq!(value.as_u64(), Some(u64::MAX));
+    assert_eq!(value.as_i64(), None);
+    assert_eq!(u64::deserialize(&value).unwrap(), u64::MAX);
+    assert_eq!(
+        serde_yaml_ng::to_string(&value).unwrap(),
+        "18446744073709551615\n"
+    );
+
+    let value: Value = serde_yaml_ng::from_str("18446744073709551616").unwrap();
+    assert_eq!(value, Value::Number(Number::from(u64::MAX as u128 + 1)));
+    assert_eq!(value.as_u64(), None);
+    assert_eq!(value.as_u128(), Some(u64::MAX as u128 + 1));
+
+    let value: Value = serde_yaml_ng::from_str("-170141183460469231731687303715884105728").unwrap();
+    assert_eq!(value, Value::Number(Number::from(i128::MIN)));
+    assert_eq!(value.as_i128(), Some(i128::MIN));
+
+    let value: Value = serde_yaml_ng::from_str("340282366920938463463374607431768211455").unwrap();
+    assert_eq!(value, Value::Number(Number::from(u128::MAX)));
+
+    let yaml = "340282366920938463463374607431768211456";
+    let value: Value = serde_yaml_ng::from_str(yaml).unwrap();
+    assert_eq!(value, Value::String(yaml.to_owned()));
+    assert_eq!(
+        serde_yaml_ng::to_string(&value).unwrap(),
+        format!("{}\n", yaml)
+    );
+
+    let yaml = "-170141183460469231731687303715884105729";
+    let value: Value = serde_yaml_ng::from_str(yaml).unwrap();
+    assert_eq!(value, Value::String(yaml.to_owned()));
+}
+
 #[test]
 fn test_number_alias_as_string() {
     #[derive(Deserialize, PartialEq, Debug)]
@@ -417,6 +589,11 @@ fn test_numbers() {
         ("127", "127"),
         ("+127", "127"),
         ("-127", "-127"),
+        ("1_000", "1000"),
+        ("-1_000_000", "-1000000"),
+        ("0xFF_FF", "65535"),
+        ("-0b1010_1010", "-170"),
+        ("+0o7_7", "63"),
         (".inf", ".inf"),
         (".Inf", ".inf"),
         (".INF", ".inf"),
@@ -439,7 +616,8 @@ fn test_numbers() {
     // NOT numbers.
     let cases = [
         "0127", "+0127", "-0127", "++.inf", "+-.inf", "++1", "+-1", "-+1", "--1", "0x+1", "0x-1",
-        "-0x+1", "-0x-1", "++0x1", "+-0x1", "-+0x1", "--0x1",
+        "-0x+1", "-0x-1", "++0x1", "+-0x1", "-+0x1", "--0x1", "_1", "1_", "0x_1", "0_1", "1_a",
+        "0b1_2", "1_-1",
     ];
     for yaml in &cases {
         let value = serde_yaml_ng::from_str::<Value>(yaml).unwrap();
@@ -450,6 +628,39 @@ fn test_numbers() {
     }
 }
 
+#[test]
+fn test_integer_radix_and_separators() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Data {
+        mask: u8,
+        offset: i32,
+        count: u64,
+    }
+
+    let yaml = indoc! {"
+        mask: 0xFF
+        offset: -0o17
+        count: 1_000
+    "};
+    let expected = Data {
+        mask: 255,
+        offset: -15,
+        count: 1000,
+    };
+    assert_eq!(serde_yaml_ng::from_str::<Data>(yaml).unwrap(), expected);
+
+    let value: Value = serde_yaml_ng::from_str("0xFF").unwrap();
+    assert_eq!(value, Value::Number(255.into()));
+
+    // Strings that look like these are quoted so that they stay strings.
+    let yaml = serde_yaml_ng::to_string(&["0xFF", "1_000"]).unwrap();
+    assert_eq!(yaml, "- '0xFF'\n- '1_000'\n");
+    assert_eq!(
+        serde_yaml_ng::from_str::<Vec<String>>(&yaml).unwrap(),
+        ["0xFF", "1_000"],
+    );
+}
+
 #[test]
 fn test_nan() {
     // There is no negative NaN in YAML.
@@ -761,3 +972,628 @@ fn test_enum_untagged() {
         assert_eq!(expected, deserialized);
     }
 }
+
+#[test]
+fn test_untagged_enum_sequence() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    #[serde(untagged)]
+    enum Mixed {
+        Int(i64),
+        Bool(bool),
+        Str(String),
+        Pair { key: String, value: i64 },
+        List(Vec<Mixed>),
+    }
+
+    let yaml = indoc! {"
+        - 1
+        - a
+        - true
+        - {key: k, value: 2}
+        - [3, b]
+    "};
+    let expected = vec![
+        Mixed::Int(1),
+        Mixed::Str("a".to_owned()),
+        Mixed::Bool(true),
+        Mixed::Pair {
+            key: "k".to_owned(),
+            value: 2,
+        },
+        Mixed::List(vec![Mixed::Int(3), Mixed::Str("b".to_owned())]),
+    ];
+    test_de(yaml, &expected);
+
+    let value: Value = serde_yaml_ng::from_str("[1, \"a\", true]").unwrap();
+    let deserialized: Vec<Mixed> = value.into_vec().unwrap();
+    assert_eq!(
+        deserialized,
+        [Mixed::Int(1), Mixed::Str("a".to_owned()), Mixed::Bool(true)],
+    );
+}
+
+#[test]
+fn test_scalar_transform() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Login {
+        user: String,
+        password: String,
+        token: String,
+        port: u16,
+    }
+
+    let yaml = indoc! {"
+        user: admin
+        password: secret:hunter2
+        token: 'secret:quoted'
+        port: 8080
+    "};
+    let redact = |scalar: &str, _tag: Option<&str>| {
+        if scalar.starts_with("secret:") {
+            Some(Value::String("<redacted>".to_owned()))
+        } else {
+            None
+        }
+    };
+    let de = Deserializer::from_str(yaml).scalar_transform(redact);
+    let deserialized = Login::deserialize(de).unwrap();
+    let expected = Login {
+        user: "admin".to_owned(),
+        password: "<redacted>".to_owned(),
+        token: "secret:quoted".to_owned(),
+        port: 8080,
+    };
+    assert_eq!(expected, deserialized);
+
+    let yaml = "--- [!env a, secret:b]\n--- secret:c\n";
+    let mut tags = Vec::new();
+    let de = Deserializer::from_str(yaml).scalar_transform(|scalar, tag| {
+        tags.push(tag.map(str::to_owned));
+        redact(scalar, tag)
+    });
+    let documents: Vec<Value> = de.map(|doc| Value::deserialize(doc).unwrap()).collect();
+    let expected: Vec<Value> = vec![
+        serde_yaml_ng::from_str("[!env a, <redacted>]").unwrap(),
+        Value::String("<redacted>".to_owned()),
+    ];
+    assert_eq!(expected, documents);
+    assert_eq!(tags, [Some("!env".to_owned()), None, None]);
+}
+
+#[test]
+fn test_scalar_transform_called_once() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Config {
+        a: Option<String>,
+        b: Option<u32>,
+        c: Option<String>,
+    }
+
+    let yaml = "a: x\nb: 1\nc: ~\n";
+    let mut seen = Vec::new();
+    let de = Deserializer::from_str(yaml).scalar_transform(|scalar, _tag| {
+        seen.push(scalar.to_owned());
+        None
+    });
+    let config = Config::deserialize(de).unwrap();
+    let expected = Config {
+        a: Some("x".to_owned()),
+        b: Some(1),
+        c: None,
+    };
+    assert_eq!(expected, config);
+    assert_eq!(seen, ["a", "x", "b", "1", "c", "~"]);
+}
+
+#[test]
+fn test_coerce_single_element_seq() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Config {
+        name: String,
+        port: u16,
+        tags: Vec<String>,
+    }
+
+    let yaml = indoc! {"
+        name: [app]
+        port: [8080]
+        tags: [a]
+    "};
+    let de = Deserializer::from_str(yaml).coerce_single_element_seq(true);
+    let deserialized = Config::deserialize(de).unwrap();
+    let expected = Config {
+        name: "app".to_owned(),
+        port: 8080,
+        tags: vec!["a".to_owned()],
+    };
+    assert_eq!(expected, deserialized);
+
+    let de = Deserializer::from_str("[x]").coerce_single_element_seq(true);
+    assert_eq!(String::deserialize(de).unwrap(), "x");
+
+    let de = Deserializer::from_str("[x, y]").coerce_single_element_seq(true);
+    let err = String::deserialize(de).unwrap_err();
+    assert_eq!(err.to_string(), "invalid type: sequence, expected a string");
+
+    let err = String::deserialize(Deserializer::from_str("[x]")).unwrap_err();
+    assert_eq!(err.to_string(), "invalid type: sequence, expected a string");
+}
+
+#[test]
+fn test_numeric_bools() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Flags {
+        enabled: bool,
+        debug: bool,
+        verbose: bool,
+        count: u8,
+    }
+
+    let yaml = indoc! {"
+        enabled: 1
+        debug: 0
+        verbose: 1.0
+        count: 1
+    "};
+    let de = Deserializer::from_str(yaml).numeric_bools(true);
+    let expected = Flags {
+        enabled: true,
+        debug: false,
+        verbose: true,
+        count: 1,
+    };
+    assert_eq!(Flags::deserialize(de).unwrap(), expected);
+
+    let de = Deserializer::from_str("[true, 0.0, False]").numeric_bools(true);
+    assert_eq!(Vec::<bool>::deserialize(de).unwrap(), [true, false, false]);
+
+    let de = Deserializer::from_str("2").numeric_bools(true);
+    let err = bool::deserialize(de).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid type: integer `2`, expected a boolean"
+    );
+
+    let err = serde_yaml_ng::from_str::<Flags>(yaml).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "enabled: invalid type: integer `1`, expected a boolean at line 1 column 10",
+    );
+}
+
+#[test]
+fn test_strict_scalar_types() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Config {
+        name: String,
+        count: u32,
+    }
+
+    let yaml = indoc! {"
+        name: 5
+        count: 5
+    "};
+    let expected = Config {
+        name: "5".to_owned(),
+        count: 5,
+    };
+    assert_eq!(serde_yaml_ng::from_str::<Config>(yaml).unwrap(), expected);
+    let de = Deserializer::from_str(yaml).strict_scalar_types(true);
+    let err = Config::deserialize(de).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "name: invalid type: integer `5`, expected a string at line 1 column 7",
+    );
+
+    let yaml = indoc! {"
+        name: '5'
+        count: 5
+    "};
+    let de = Deserializer::from_str(yaml).strict_scalar_types(true);
+    assert_eq!(Config::deserialize(de).unwrap(), expected);
+
+    // A quoted number never deserializes into an integer.
+    let yaml = indoc! {"
+        name: five
+        count: \"5\"
+    "};
+    for strict in [false, true] {
+        let de = Deserializer::from_str(yaml).strict_scalar_types(strict);
+        let err = Config::deserialize(de).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "count: invalid type: string \"5\", expected u32 at line 2 column 8",
+        );
+    }
+
+    let yaml = indoc! {"
+        - plain
+        - !!str true
+        - |
+          block
+    "};
+    let de = Deserializer::from_str(yaml).strict_scalar_types(true);
+    assert_eq!(
+        Vec::<String>::deserialize(de).unwrap(),
+        ["plain", "true", "block\n"],
+    );
+
+    let de = Deserializer::from_str("[a, ~]").strict_scalar_types(true);
+    let err = Vec::<String>::deserialize(de).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        ".[1]: invalid type: unit value, expected a string at line 1 column 5",
+    );
+}
+
+#[test]
+fn test_flow_trailing_commas() {
+    // YAML allows a trailing comma in flow collections, so these are accepted
+    // without any lenient mode.
+    test_de("[1, 2, 3,]", &vec![1, 2, 3]);
+    let mut expected = BTreeMap::new();
+    expected.insert("a".to_owned(), 1);
+    test_de("{a: 1,}", &expected);
+
+    let yaml = "[[1, [2,],], {a: {b: 1,},},]";
+    let expected: Value = serde_yaml_ng::from_str("[[1, [2]], {a: {b: 1}}]").unwrap();
+    test_de(yaml, &expected);
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn test_from_gz_reader() {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write as _;
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Config {
+        name: String,
+        ports: Vec<u16>,
+    }
+
+    let yaml = indoc! {"
+        name: app
+        ports: [80, 443]
+    "};
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(yaml.as_bytes()).unwrap();
+    let compressed = encoder.finish().unwrap();
+    assert_ne!(compressed, yaml.as_bytes());
+
+    let deserialized: Config = serde_yaml_ng::from_gz_reader(compressed.as_slice()).unwrap();
+    let expected = Config {
+        name: "app".to_owned(),
+        ports: vec![80, 443],
+    };
+    assert_eq!(expected, deserialized);
+
+    let err = serde_yaml_ng::from_gz_reader::<_, Config>(yaml.as_bytes()).unwrap_err();
+    assert!(err.to_string().contains("header"), "{}", err);
+}
+
+#[test]
+fn test_sequence_stream() {
+    use std::cell::Cell;
+    use std::io::{self, Read};
+    use std::rc::Rc;
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Record {
+        id: u32,
+        level: String,
+    }
+
+    // Writes the records of a sequence as they are read, counting the bytes
+    // handed out so far.
+    struct Records {
+        next: u32,
+        count: u32,
+        pending: Vec<u8>,
+        read: Rc<Cell<usize>>,
+    }
+
+    impl Read for Records {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.pending.is_empty() && self.next < self.count {
+                let record = format!("- id: {}\n  level: info\n", self.next);
+                self.pending = record.into_bytes();
+                self.next += 1;
+            }
+            let n = buf.len().min(self.pending.len());
+            buf[..n].copy_from_slice(&self.pending[..n]);
+            self.pending.drain(..n);
+            self.read.set(self.read.get() + n);
+            Ok(n)
+        }
+    }
+
+    let count = 20_000;
+    let read = Rc::new(Cell::new(0));
+    let records = Records {
+        next: 0,
+        count,
+        pending: Vec::new(),
+        read: Rc::clone(&read),
+    };
+    let mut stream = serde_yaml_ng::sequence_stream::<_, Record>(records);
+    let first = stream.next().unwrap().unwrap();
+    assert_eq!(
+        first,
+        Record {
+            id: 0,
+            level: "info".to_owned(),
+        },
+    );
+    // The input is over 500KB, of which only the start has been read.
+    assert!(read.get() < 100_000, "{}", read.get());
+    let mut expected = 1;
+    for record in stream {
+        assert_eq!(record.unwrap().id, expected);
+        expected += 1;
+    }
+    assert_eq!(expected, count);
+
+    let yaml = "!list [1, {a: 2}, [3]]";
+    let elements: Vec<Value> = serde_yaml_ng::sequence_stream(yaml.as_bytes())
+        .map(Result::unwrap)
+        .collect();
+    let expected: Vec<Value> = serde_yaml_ng::from_str("[1, {a: 2}, [3]]").unwrap();
+    assert_eq!(elements, expected);
+
+    // Aliases may refer to anchors in earlier elements.
+    let yaml = indoc! {"
+        - &base {level: warn}
+        - {id: 1, parent: *base}
+        - *base
+    "};
+    let elements: Vec<Value> = serde_yaml_ng::sequence_stream(yaml.as_bytes())
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(elements[1]["parent"]["level"], "warn");
+    assert_eq!(elements[2], elements[0]);
+
+    // A bad element does not end the stream, but a syntax error does.
+    let yaml = "- 1\n- x\n- 3\n- [4\n- 5\n";
+    let elements: Vec<_> = serde_yaml_ng::sequence_stream::<_, u32>(yaml.as_bytes()).collect();
+    assert_eq!(elements.len(), 4);
+    assert_eq!(*elements[0].as_ref().unwrap(), 1);
+    assert!(elements[1].is_err());
+    assert_eq!(*elements[2].as_ref().unwrap(), 3);
+    assert!(elements[3].is_err());
+
+    let elements: Vec<_> = serde_yaml_ng::sequence_stream::<_, u32>("".as_bytes()).collect();
+    assert!(elements.is_empty());
+
+    let error = serde_yaml_ng::sequence_stream::<_, u32>("hello".as_bytes())
+        .next()
+        .unwrap()
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "invalid type: string \"hello\", expected a sequence",
+    );
+
+    let error = serde_yaml_ng::sequence_stream::<_, u32>("--- [1]\n--- [2]\n".as_bytes())
+        .nth(1)
+        .unwrap()
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "deserializing from YAML containing more than one document is not supported",
+    );
+}
+
+#[test]
+fn test_f32() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Point {
+        x: f32,
+        y: f32,
+    }
+
+    let yaml = "x: 0.1\ny: -2.5e3\n";
+    let expected = Point { x: 0.1, y: -2500.0 };
+    test_de(yaml, &expected);
+
+    let de = Deserializer::from_str(yaml).strict_f32(true);
+    assert_eq!(Point::deserialize(de).unwrap(), expected);
+
+    let point: Point = serde_yaml_ng::from_str("x: .inf\ny: 16\n").unwrap();
+    assert_eq!(
+        point,
+        Point {
+            x: f32::INFINITY,
+            y: 16.0
+        }
+    );
+
+    let lossy = "x: 0.123456789\ny: 1\n";
+    let point: Point = serde_yaml_ng::from_str(lossy).unwrap();
+    assert_eq!(point.x, 0.123_456_79);
+
+    let de = Deserializer::from_str(lossy).strict_f32(true);
+    let err = Point::deserialize(de).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "x: invalid value: string \"0.123456789\", expected a float that f32 can hold without losing precision at line 1 column 4",
+    );
+
+    let de = Deserializer::from_str("1e40").strict_f32(true);
+    assert!(f32::deserialize(de).is_err());
+    assert_eq!(
+        serde_yaml_ng::from_str::<f32>("1e40").unwrap(),
+        f32::INFINITY
+    );
+}
+
+#[test]
+fn test_parse_lenient() {
+    let yaml = indoc! {"
+        name: first
+        list: [1, 2
+        ---
+        name: second
+        ...
+        %YAML 1.2
+        ---
+        name: third
+          bad: indent
+        ---
+        name: fourth
+    "};
+    let (value, errors) = serde_yaml_ng::parse_lenient(yaml);
+    let expected: Value = serde_yaml_ng::from_str("[{name: second}, {name: fourth}]").unwrap();
+    assert_eq!(value, Some(expected));
+    assert_eq!(errors.len(), 2);
+
+    let first = errors[0].location().unwrap();
+    assert_eq!((first.line(), first.column()), (3, 1));
+    let second = errors[1].location().unwrap();
+    assert_eq!((second.line(), second.column()), (9, 6));
+    assert_eq!(&yaml[second.index()..second.index() + 1], ":");
+
+    let (value, errors) = serde_yaml_ng::parse_lenient("a: 1\n");
+    assert_eq!(value, Some(serde_yaml_ng::from_str("a: 1").unwrap()));
+    assert!(errors.is_empty());
+
+    let (value, errors) = serde_yaml_ng::parse_lenient("# nothing here\n");
+    assert_eq!(value, None);
+    assert!(errors.is_empty());
+
+    // One document holding a sequence looks the same as several documents.
+    let (single, _errors) = serde_yaml_ng::parse_lenient("[1, 2]\n");
+    let (several, _errors) = serde_yaml_ng::parse_lenient("--- 1\n--- 2\n");
+    assert_eq!(single, several);
+}
+
+#[test]
+fn test_flatten_into_mapping() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Config {
+        name: String,
+        #[serde(flatten)]
+        extra: Mapping,
+    }
+    let yaml = indoc! {"
+        name: app
+        int: 1
+        neg: -2
+        float: 1.5
+        bool: true
+        nothing: ~
+        quoted: '1'
+        seq: [1, x]
+        map: {k: 2}
+    "};
+    let config: Config = serde_yaml_ng::from_str(yaml).unwrap();
+    assert_eq!(config.name, "app");
+    assert_eq!(config.extra.len(), 8);
+    assert_eq!(config.extra["int"], Value::Number(Number::from(1)));
+    assert_eq!(config.extra["neg"], Value::Number(Number::from(-2)));
+    assert_eq!(config.extra["float"], Value::Number(Number::from(1.5)));
+    assert_eq!(config.extra["bool"], Value::Bool(true));
+    assert_eq!(config.extra["nothing"], Value::Null);
+    assert_eq!(config.extra["quoted"], Value::String("1".to_owned()));
+    assert_eq!(
+        config.extra["seq"],
+        Value::Sequence(vec![Value::from(1), Value::from("x")]),
+    );
+    let mut map = Mapping::new();
+    map.insert(Value::from("k"), Value::from(2));
+    assert_eq!(config.extra["map"], Value::Mapping(map));
+
+    let expected = config;
+    test_de(yaml, &expected);
+}
+
+#[test]
+fn test_flatten_rest_into_value() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Server {
+        host: String,
+        port: u16,
+        #[serde(flatten)]
+        rest: Value,
+    }
+    let yaml = indoc! {"
+        host: localhost
+        timeout: 30
+        port: 8080
+        ratio: 0.5
+        tls: false
+        labels: [web, 2]
+        limits: {cpu: 1.5, memory: ~}
+    "};
+    let server: Server = serde_yaml_ng::from_str(yaml).unwrap();
+    assert_eq!(server.host, "localhost");
+    assert_eq!(server.port, 8080);
+
+    let expected: Value = serde_yaml_ng::from_str(indoc! {"
+        timeout: 30
+        ratio: 0.5
+        tls: false
+        labels: [web, 2]
+        limits: {cpu: 1.5, memory: ~}
+    "})
+    .unwrap();
+    assert_eq!(server.rest, expected);
+    assert!(server.rest["timeout"].is_u64());
+    assert!(server.rest["ratio"].is_f64());
+    assert!(server.rest["tls"].is_bool());
+    assert!(server.rest["limits"]["memory"].is_null());
+
+    let expected = server;
+    test_de(yaml, &expected);
+}
+
+#[test]
+fn test_heterogeneous_tuple() {
+    let yaml = "[1, \"two\", 3.0]";
+    let expected = (1i64, "two".to_owned(), 3.0f64);
+    test_de(yaml, &expected);
+
+    let yaml = indoc! {"
+        - -1
+        - two
+        - [3, x]
+        - {k: v}
+    "};
+    let mut map = BTreeMap::new();
+    map.insert("k".to_owned(), "v".to_owned());
+    let expected = (-1i8, "two".to_owned(), (3u8, 'x'), map);
+    test_de(yaml, &expected);
+}
+
+#[test]
+fn test_mapping_into_pairs() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    #[serde(bound = "V: serde::Deserialize<'de>")]
+    struct Pairs<V> {
+        #[serde(with = "serde_yaml_ng::with::pairs")]
+        pairs: Vec<(String, V)>,
+    }
+
+    let yaml = "pairs: {a: 1, b: 2, a: 3}";
+    let expected = [("a", 1), ("b", 2), ("a", 3)].map(|(k, v)| (k.to_owned(), v));
+    let value: Pairs<i64> = serde_yaml_ng::from_str(yaml).unwrap();
+    assert_eq!(value.pairs, expected);
+
+    let yaml = indoc! {"
+        pairs:
+          x: &v [1, 2]
+          x: *v
+    "};
+    let value: Pairs<Vec<u8>> = serde_yaml_ng::from_str(yaml).unwrap();
+    assert_eq!(
+        value.pairs,
+        [("x".to_owned(), vec![1, 2]), ("x".to_owned(), vec![1, 2])]
+    );
+
+    let value: Pairs<i64> = serde_yaml_ng::from_str("pairs: {}").unwrap();
+    assert!(value.pairs.is_empty());
+
+    let error = serde_yaml_ng::from_str::<Vec<(String, i64)>>("a: 1").unwrap_err();
+    assert_eq!(error.to_string(), "invalid type: map, expected a sequence",);
+}
@@ -6,12 +6,13 @@ use serde::de::Deserialize;
 use serde::de::{SeqAccess, Visitor};
 use serde_derive::{Deserialize, Serialize};
 use serde_yaml_ng::value::{Tag, TaggedValue};
-use serde_yaml_ng::{Deserializer, Value};
+use serde_yaml_ng::{Deserializer, Location, Value};
 #[cfg(not(miri))]
 use std::collections::BTreeMap;
 #[cfg(not(miri))]
 use std::fmt;
 use std::fmt::Debug;
+use std::io;
 
 fn test_error<'de, T>(yaml: &'de str, expected: &str)
 where
@@ -104,6 +105,16 @@ fn test_unknown_anchor() {
     test_error::<String>(yaml, expected);
 }
 
+#[test]
+fn test_self_referential_anchor() {
+    let yaml = indoc! {"
+        a: &a
+          b: *a
+    "};
+    let expected = "recursion limit exceeded at line 1 column 4";
+    test_error::<Value>(yaml, expected);
+}
+
 #[test]
 fn test_ignored_unknown_anchor() {
     #[derive(Deserialize, Debug)]
@@ -158,6 +169,76 @@ fn test_second_document_syntax_error() {
     assert_eq!(expected, result.unwrap_err().to_string());
 }
 
+#[test]
+fn test_recover_after_document_error() {
+    let yaml = indoc! {"
+        --- 0
+        --- 'one
+        --- 2
+        --- *unknown
+        --- 4
+    "};
+
+    let results: Vec<String> = serde_yaml_ng::from_str_multi::<usize>(yaml)
+        .map(|result| match result {
+            Ok(n) => n.to_string(),
+            Err(err) => err.to_string(),
+        })
+        .collect();
+    let expected = [
+        "0",
+        "found unexpected document indicator at line 3 column 1, while scanning a quoted scalar at line 2 column 5",
+        "2",
+        "unknown anchor at line 4 column 5",
+        "4",
+    ];
+    assert_eq!(results, expected);
+}
+
+#[test]
+fn test_max_documents() {
+    let yaml = indoc! {"
+        --- 0
+        --- 1
+        --- 2
+        --- 3
+    "};
+
+    let mut de = Deserializer::from_str(yaml).max_documents(2);
+    for expected in 0..2 {
+        let doc = de.next().unwrap();
+        let result = <usize as serde::Deserialize>::deserialize(doc);
+        assert_eq!(expected, result.unwrap());
+    }
+
+    let third_doc = de.next().unwrap();
+    let result = <usize as serde::Deserialize>::deserialize(third_doc);
+    assert_eq!(
+        "document limit of 2 exceeded",
+        result.unwrap_err().to_string()
+    );
+    assert!(de.next().is_none());
+
+    let de = Deserializer::from_str(yaml).max_documents(4);
+    assert_eq!(de.count(), 4);
+
+    // The document over the limit is rejected where it starts, before any of
+    // it is parsed.
+    let mut de = Deserializer::from_str("--- 0\n--- [unclosed\n").max_documents(1);
+    let first = de.next().unwrap();
+    assert_eq!(
+        0,
+        <usize as serde::Deserialize>::deserialize(first).unwrap()
+    );
+    let second = de.next().unwrap();
+    let result = <usize as serde::Deserialize>::deserialize(second);
+    assert_eq!(
+        "document limit of 1 exceeded",
+        result.unwrap_err().to_string()
+    );
+    assert!(de.next().is_none());
+}
+
 #[test]
 fn test_missing_enum_tag() {
     #[derive(Deserialize, Debug)]
@@ -334,6 +415,16 @@ fn test_long_tuple() {
     test_error::<(u8, u8)>(yaml, expected);
 }
 
+#[test]
+fn test_tuple_element_type() {
+    let yaml = indoc! {"
+        ---
+        [1, two, 3.0]
+    "};
+    let expected = ".[1]: invalid type: string \"two\", expected i64 at line 2 column 5";
+    test_error::<(i64, i64, f64)>(yaml, expected);
+}
+
 #[test]
 fn test_invalid_scalar_type() {
     #[derive(Deserialize, Debug)]
@@ -414,6 +505,34 @@ fn test_finite_recursion_arrays() {
     test_error::<S>(&yaml, expected);
 }
 
+#[cfg(not(miri))]
+#[test]
+fn test_serialize_depth_limit() {
+    let mut value = Value::Null;
+    for depth in 0..5_000 {
+        value = if depth % 2 == 0 {
+            Value::Sequence(vec![value])
+        } else {
+            let mut mapping = serde_yaml_ng::Mapping::new();
+            mapping.insert(Value::from("k"), value);
+            Value::Mapping(mapping)
+        };
+    }
+
+    let mut buffer = Vec::new();
+    let mut ser = serde_yaml_ng::Serializer::new(&mut buffer).max_depth(100);
+    let err = serde::Serialize::serialize(&value, &mut ser).unwrap_err();
+    assert_eq!(err.to_string(), "nesting depth limit of 100 exceeded");
+
+    let shallow: Value = serde_yaml_ng::from_str("[{k: [1]}]").unwrap();
+    for (max_depth, ok) in [(2, false), (3, true)] {
+        let mut buffer = Vec::new();
+        let mut ser = serde_yaml_ng::Serializer::new(&mut buffer).max_depth(max_depth);
+        let result = serde::Serialize::serialize(&shallow, &mut ser);
+        assert_eq!(result.is_ok(), ok);
+    }
+}
+
 #[cfg(not(miri))]
 #[test]
 fn test_billion_laughs() {
@@ -464,6 +583,21 @@ fn test_billion_laughs() {
     test_error::<BTreeMap<String, X>>(yaml, expected);
 }
 
+#[test]
+fn test_billion_laughs_sequence_stream() {
+    // Each element repeats the one before it ten times through an alias to
+    // its anchor, which sequence_stream has to copy.
+    let mut yaml = format!("- &a0 [{}]\n", ["x"; 10].join(", "));
+    for level in 1..=6 {
+        let aliases = vec![format!("*a{}", level - 1); 10].join(", ");
+        yaml += &format!("- &a{} [{}]\n", level, aliases);
+    }
+    let error = serde_yaml_ng::sequence_stream::<_, Value>(yaml.as_bytes())
+        .find_map(Result::err)
+        .unwrap();
+    assert_eq!(error.to_string(), "repetition limit exceeded");
+}
+
 #[test]
 fn test_duplicate_keys() {
     let yaml = indoc! {"
@@ -498,3 +632,97 @@ fn test_duplicate_keys() {
     let expected = "duplicate entry in YAML map at line 2 column 1";
     test_error::<Value>(yaml, expected);
 }
+
+#[test]
+fn test_custom_at() {
+    let error = serde_yaml_ng::Error::custom_at("invalid port 99999", Location::new(14, 2, 7));
+    assert_eq!(error.to_string(), "invalid port 99999 at line 2 column 7");
+    assert_eq!(
+        format!("{:?}", error),
+        "Error(\"invalid port 99999\", line: 2, column: 7)",
+    );
+    let location = error.location().unwrap();
+    assert_eq!(location.index(), 14);
+    assert_eq!(location.line(), 2);
+    assert_eq!(location.column(), 7);
+}
+
+#[test]
+fn test_location_deep_in_document() {
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct Config {
+        entries: BTreeMap<String, Entry>,
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct Entry {
+        port: u16,
+    }
+
+    let mut yaml = String::from("entries:\n");
+    for i in 0..20 {
+        yaml.push_str(&format!("  e{}:\n    port: {}\n", i, 8000 + i));
+    }
+    yaml.push_str("  bad:\n    port: http\n");
+
+    let error = serde_yaml_ng::from_str::<Config>(&yaml).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "entries.bad.port: invalid type: string \"http\", expected u16 at line 43 column 11",
+    );
+    let location = error.location().unwrap();
+    assert_eq!(location.line(), 43);
+    assert_eq!(location.column(), 11);
+    assert_eq!(&yaml[location.index()..], "http\n");
+
+    yaml.push_str("  worse: [unclosed\n");
+    let error = serde_yaml_ng::from_str::<Value>(&yaml).unwrap_err();
+    let location = error.location().unwrap();
+    assert_eq!(location.line(), 45);
+    assert_eq!(
+        error.to_string(),
+        "did not find expected ',' or ']' at line 45 column 1, while parsing a flow sequence at line 44 column 10",
+    );
+}
+
+#[test]
+fn test_io_error() {
+    struct Failing;
+
+    impl io::Read for Failing {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::PermissionDenied, "no access"))
+        }
+    }
+
+    impl io::Write for Failing {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::WriteZero, "disk full"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let error = serde_yaml_ng::from_reader::<_, Value>(Failing).unwrap_err();
+    assert_eq!(error.to_string(), "error reading YAML input: no access");
+    let io_error = error.io_error().unwrap();
+    assert_eq!(io_error.kind(), io::ErrorKind::PermissionDenied);
+
+    let error = serde_yaml_ng::to_writer(Failing, &vec!["a"; 1000]).unwrap_err();
+    assert_eq!(error.to_string(), "disk full");
+    assert_eq!(error.io_error().unwrap().kind(), io::ErrorKind::WriteZero);
+
+    let mut de = Deserializer::from_reader(Failing);
+    let error = Value::deserialize(de.next().unwrap()).unwrap_err();
+    assert_eq!(
+        error.io_error().unwrap().kind(),
+        io::ErrorKind::PermissionDenied
+    );
+
+    let error = serde_yaml_ng::from_reader::<_, Value>("[".as_bytes()).unwrap_err();
+    assert!(error.io_error().is_none());
+}
@@ -6,12 +6,20 @@
 )]
 
 use indoc::indoc;
-use serde::ser::SerializeMap;
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Deserialize as _, Serialize as _};
 use serde_derive::{Deserialize, Serialize};
-use serde_yaml_ng::{Mapping, Number, Value};
-use std::collections::BTreeMap;
+use serde_yaml_ng::value::PathSegment;
+use serde_yaml_ng::{
+    Directives, Document, Mapping, NullStyle, Number, QuoteStyle, SequenceIndent, SerializeOptions,
+    Style, TagDirective, Value, VersionDirective,
+};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Debug;
 use std::iter;
+use std::rc::Rc;
+use std::str;
 
 fn test_serde<T>(thing: &T, yaml: &str)
 where
@@ -20,6 +28,10 @@ where
     let serialized = serde_yaml_ng::to_string(&thing).unwrap();
     assert_eq!(yaml, serialized);
 
+    let mut buffer = Vec::new();
+    serde_yaml_ng::to_writer(&mut buffer, thing).unwrap();
+    assert_eq!(yaml.as_bytes(), buffer);
+
     let value = serde_yaml_ng::to_value(thing).unwrap();
     let serialized = serde_yaml_ng::to_string(&value).unwrap();
     assert_eq!(yaml, serialized);
@@ -27,6 +39,9 @@ where
     let deserialized: T = serde_yaml_ng::from_str(yaml).unwrap();
     assert_eq!(*thing, deserialized);
 
+    let deserialized: T = serde_yaml_ng::from_reader(yaml.as_bytes()).unwrap();
+    assert_eq!(*thing, deserialized);
+
     let value: Value = serde_yaml_ng::from_str(yaml).unwrap();
     let deserialized = T::deserialize(&value).unwrap();
     assert_eq!(*thing, deserialized);
@@ -156,6 +171,49 @@ fn test_float32() {
     assert!(single_float.is_nan());
 }
 
+#[test]
+fn test_non_finite_floats() {
+    #[derive(Serialize, Deserialize, Debug)]
+    struct Limits {
+        low: f64,
+        high: f32,
+        missing: f64,
+    }
+
+    let thing = Limits {
+        low: f64::NEG_INFINITY,
+        high: f32::INFINITY,
+        missing: f64::NAN,
+    };
+    let yaml = indoc! {"
+        low: -.inf
+        high: .inf
+        missing: .nan
+    "};
+    assert_eq!(serde_yaml_ng::to_string(&thing).unwrap(), yaml);
+    assert_eq!(serde_yaml_ng::to_string(&f32::NAN).unwrap(), ".nan\n");
+
+    let value = serde_yaml_ng::to_value(&thing).unwrap();
+    assert_eq!(serde_yaml_ng::to_string(&value).unwrap(), yaml);
+    assert_eq!(value["low"].as_f64(), Some(f64::NEG_INFINITY));
+    assert_eq!(value["high"].as_f64(), Some(f64::INFINITY));
+    assert!(value["missing"].as_f64().unwrap().is_nan());
+
+    let deserialized: Limits = serde_yaml_ng::from_str(yaml).unwrap();
+    assert_eq!(deserialized.low, f64::NEG_INFINITY);
+    assert_eq!(deserialized.high, f32::INFINITY);
+    assert!(deserialized.missing.is_nan());
+
+    let value: Value = serde_yaml_ng::from_str("[.NaN, +.Inf, -.INF]").unwrap();
+    assert!(value[0].as_f64().unwrap().is_nan());
+    assert_eq!(value[1].as_f64(), Some(f64::INFINITY));
+    assert_eq!(value[2].as_f64(), Some(f64::NEG_INFINITY));
+    assert_eq!(
+        serde_yaml_ng::to_string(&value).unwrap(),
+        "- .nan\n- .inf\n- -.inf\n"
+    );
+}
+
 #[test]
 fn test_char() {
     let ch = '.';
@@ -223,6 +281,37 @@ fn test_map_key_value() {
     assert_eq!(yaml, serde_yaml_ng::to_string(&Map).unwrap());
 }
 
+#[test]
+fn test_seq_unknown_len() {
+    struct Evens(u32);
+
+    impl serde::Serialize for Evens {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            // Test sequences whose length is not known up front.
+            let mut seq = serializer.serialize_seq(None)?;
+            for n in (0..self.0).filter(|n| n % 2 == 0) {
+                seq.serialize_element(&n)?;
+            }
+            seq.end()
+        }
+    }
+
+    let yaml = indoc! {"
+        - 0
+        - 2
+        - 4
+    "};
+    assert_eq!(yaml, serde_yaml_ng::to_string(&Evens(6)).unwrap());
+
+    let value = serde_yaml_ng::to_value(Evens(6)).unwrap();
+    assert_eq!(yaml, serde_yaml_ng::to_string(&value).unwrap());
+
+    assert_eq!("[]\n", serde_yaml_ng::to_string(&Evens(0)).unwrap());
+}
+
 #[test]
 fn test_basic_struct() {
     #[derive(Serialize, Deserialize, PartialEq, Debug)]
@@ -292,6 +381,61 @@ fn test_multiline_string() {
     test_serde(&thing, yaml);
 }
 
+#[test]
+fn test_multiline_string_block_headers() {
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Struct {
+        clip: String,
+        strip: String,
+        keep: String,
+        indented: String,
+        trailing_space: String,
+    }
+    let thing = Struct {
+        clip: "aaa\nbbb\n".to_owned(),
+        strip: "aaa\nbbb".to_owned(),
+        keep: "aaa\nbbb\n\n".to_owned(),
+        indented: "  aaa\nbbb\n".to_owned(),
+        trailing_space: "aaa \nbbb\n".to_owned(),
+    };
+    let yaml = indoc! {r#"
+        clip: |
+          aaa
+          bbb
+        strip: |-
+          aaa
+          bbb
+        keep: |+
+          aaa
+          bbb
+
+        indented: |2
+            aaa
+          bbb
+        trailing_space: "aaa \nbbb\n"
+    "#};
+    test_serde(&thing, yaml);
+}
+
+#[test]
+fn test_multiline_strings_disabled() {
+    let thing = BTreeMap::from([("clip", "aaa\nbbb\n"), ("strip", "aaa\nbbb")]);
+    let yaml = indoc! {r#"
+        clip: "aaa\nbbb\n"
+        strip: "aaa\nbbb"
+    "#};
+
+    let mut buffer = Vec::new();
+    let mut ser = serde_yaml_ng::Serializer::new(&mut buffer).multiline_strings(false);
+    thing.serialize(&mut ser).unwrap();
+    assert_eq!(yaml, str::from_utf8(&buffer).unwrap());
+
+    let value = serde_yaml_ng::to_value(&thing).unwrap();
+    let options = SerializeOptions::new().multiline_strings(false);
+    assert_eq!(value.to_string_with(&options).unwrap(), yaml);
+    assert_eq!(value, serde_yaml_ng::from_str::<Value>(yaml).unwrap());
+}
+
 #[test]
 fn test_strings_needing_quote() {
     #[derive(Serialize, Deserialize, PartialEq, Debug)]
@@ -576,3 +720,1090 @@ fn test_long_string() {
 
     test_serde(&thing, yaml);
 }
+
+#[test]
+fn test_collection_styles() {
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Data {
+        name: String,
+        tags: Vec<String>,
+        nested: BTreeMap<String, Vec<u32>>,
+    }
+
+    let thing = Data {
+        name: "x".to_owned(),
+        tags: vec!["a".to_owned(), "b".to_owned()],
+        nested: BTreeMap::from([("k".to_owned(), vec![1, 2])]),
+    };
+
+    let mut buffer = Vec::new();
+    let mut ser = serde_yaml_ng::Serializer::new(&mut buffer)
+        .mapping_style(Style::Block)
+        .sequence_style(Style::Flow);
+    thing.serialize(&mut ser).unwrap();
+    let yaml = indoc! {"
+        name: x
+        tags: [a, b]
+        nested:
+          k: [1, 2]
+    "};
+    assert_eq!(yaml, str::from_utf8(&buffer).unwrap());
+    assert_eq!(thing, serde_yaml_ng::from_str::<Data>(yaml).unwrap());
+
+    let mut buffer = Vec::new();
+    let mut ser = serde_yaml_ng::Serializer::new(&mut buffer).mapping_style(Style::Flow);
+    thing.serialize(&mut ser).unwrap();
+    let yaml = "{name: x, tags: [a, b], nested: {k: [1, 2]}}\n";
+    assert_eq!(yaml, str::from_utf8(&buffer).unwrap());
+    assert_eq!(thing, serde_yaml_ng::from_str::<Data>(yaml).unwrap());
+
+    let mut buffer = Vec::new();
+    let mut ser = serde_yaml_ng::Serializer::new(&mut buffer).mapping_style(Style::Block);
+    BTreeMap::from([("single", "entry")])
+        .serialize(&mut ser)
+        .unwrap();
+    assert_eq!("single: entry\n", str::from_utf8(&buffer).unwrap());
+}
+
+#[test]
+fn test_needs_quoting_as_key() {
+    for key in ["a:b", "plain", "with space", "a-b", "key_1"] {
+        assert!(!serde_yaml_ng::needs_quoting_as_key(key), "{:?}", key);
+    }
+    for key in [
+        "a: b",
+        "- item",
+        "-",
+        "? x",
+        "#comment",
+        "!tag",
+        "&anchor",
+        "*alias",
+        "",
+        " x",
+        "true",
+        "null",
+        "~",
+        "12",
+        "1.5",
+        "0x1F",
+        "multi\nline",
+        "[x]",
+        "{x}",
+    ] {
+        assert!(serde_yaml_ng::needs_quoting_as_key(key), "{:?}", key);
+    }
+
+    for key in ["a:b", "a: b", "- item", "!tag", "true", "12", "multi\nline"] {
+        let mut mapping = Mapping::new();
+        mapping.insert(Value::String(key.to_owned()), Value::Null);
+        let yaml = serde_yaml_ng::to_string(&mapping).unwrap();
+        let quoted = yaml.starts_with(['\'', '"']) || yaml.starts_with("? ");
+        assert_eq!(serde_yaml_ng::needs_quoting_as_key(key), quoted, "{}", yaml);
+        assert_eq!(mapping, serde_yaml_ng::from_str::<Mapping>(&yaml).unwrap());
+    }
+}
+
+#[test]
+fn test_directives_round_trip() {
+    let yaml = indoc! {"
+        %YAML 1.2
+        %TAG !e! tag:example.com,2000:
+        ---
+        widget: !e!widget
+          size: 1
+        plain: !local x
+    "};
+
+    let document = serde_yaml_ng::Deserializer::from_str(yaml)
+        .tag_shorthands(true)
+        .next()
+        .unwrap();
+    let directives = document.directives().unwrap().clone();
+    assert_eq!(
+        directives,
+        Directives {
+            version: Some(VersionDirective { major: 1, minor: 2 }),
+            tags: vec![TagDirective {
+                handle: "!e!".to_owned(),
+                prefix: "tag:example.com,2000:".to_owned(),
+            }],
+        },
+    );
+    let value = Value::deserialize(document).unwrap();
+    let widget = match &value["widget"] {
+        Value::Tagged(widget) => widget,
+        other => panic!("expected tagged value, got {:?}", other),
+    };
+    assert_eq!(widget.tag, "!e!widget");
+
+    let mut buffer = Vec::new();
+    let mut ser = serde_yaml_ng::Serializer::new(&mut buffer);
+    ser.set_directives(directives);
+    value.serialize(&mut ser).unwrap();
+    assert_eq!(yaml, str::from_utf8(&buffer).unwrap());
+
+    // Without the directives, the document still reads the same.
+    let value_without: Value =
+        serde_yaml_ng::from_str(&serde_yaml_ng::to_string(&value).unwrap()).unwrap();
+    assert_eq!(value, value_without);
+}
+
+#[test]
+fn test_directives_resolved_by_default() {
+    // The tag resolves to tag:example.com,2000:widget, which is not a local
+    // tag, so no `!e!` shorthand ends up in the value or its output.
+    let yaml = "%TAG !e! tag:example.com,2000:\n--- !e!widget x\n";
+    let value: Value = serde_yaml_ng::from_str(yaml).unwrap();
+    assert_eq!(value, "x");
+    let yaml = serde_yaml_ng::to_string(&value).unwrap();
+    assert_eq!(yaml, "x\n");
+}
+
+#[test]
+fn test_null_style() {
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Data {
+        a: Option<u8>,
+        b: Vec<Option<u8>>,
+        c: (),
+    }
+
+    let thing = Data {
+        a: None,
+        b: vec![None, Some(1)],
+        c: (),
+    };
+
+    for (style, yaml) in [
+        (NullStyle::Keyword, "a: null\nb:\n- null\n- 1\nc: null\n"),
+        (NullStyle::Tilde, "a: ~\nb:\n- ~\n- 1\nc: ~\n"),
+        (NullStyle::Empty, "a:\nb:\n-\n- 1\nc:\n"),
+    ] {
+        let mut buffer = Vec::new();
+        let mut ser = serde_yaml_ng::Serializer::new(&mut buffer).null_style(style);
+        thing.serialize(&mut ser).unwrap();
+        assert_eq!(yaml, str::from_utf8(&buffer).unwrap());
+        assert_eq!(thing, serde_yaml_ng::from_str::<Data>(yaml).unwrap());
+    }
+
+    // Empty nulls fall back to `null` where an empty scalar would not read
+    // back as null.
+    let value: Value = serde_yaml_ng::from_str("{~: [~], k: {x: ~}, top: ~}").unwrap();
+    let mut buffer = Vec::new();
+    let mut ser = serde_yaml_ng::Serializer::new(&mut buffer)
+        .null_style(NullStyle::Empty)
+        .sequence_style(Style::Flow);
+    value.serialize(&mut ser).unwrap();
+    let yaml = indoc! {"
+        null: [null]
+        k:
+          x:
+        top:
+    "};
+    assert_eq!(yaml, str::from_utf8(&buffer).unwrap());
+    assert_eq!(value, serde_yaml_ng::from_str::<Value>(yaml).unwrap());
+
+    let value: Value = serde_yaml_ng::from_str("{a: ~, b: [~, x], c: {d: ~}}").unwrap();
+    let mut buffer = Vec::new();
+    let mut ser = serde_yaml_ng::Serializer::new(&mut buffer)
+        .null_style(NullStyle::Empty)
+        .mapping_style(Style::Flow);
+    value.serialize(&mut ser).unwrap();
+    let yaml = str::from_utf8(&buffer).unwrap();
+    assert_eq!(yaml, "{a: null, b: [null, x], c: {d: null}}\n");
+    assert_eq!(value, serde_yaml_ng::from_str::<Value>(yaml).unwrap());
+
+    let mut buffer = Vec::new();
+    let mut ser = serde_yaml_ng::Serializer::new(&mut buffer).null_style(NullStyle::Empty);
+    Value::Null.serialize(&mut ser).unwrap();
+    let yaml = str::from_utf8(&buffer).unwrap();
+    assert_eq!(Value::Null, serde_yaml_ng::from_str::<Value>(yaml).unwrap());
+}
+
+#[test]
+fn test_to_string_sorted() {
+    #[derive(Serialize)]
+    struct Inner {
+        zeta: u8,
+        alpha: u8,
+    }
+
+    #[derive(Serialize)]
+    struct Snapshot {
+        version: u8,
+        name: &'static str,
+        inner: Inner,
+        args: Vec<&'static str>,
+    }
+
+    let snapshot = Snapshot {
+        version: 1,
+        name: "app",
+        inner: Inner { zeta: 2, alpha: 3 },
+        args: vec!["b", "a"],
+    };
+    let expected = indoc! {"
+        args:
+        - b
+        - a
+        inner:
+          alpha: 3
+          zeta: 2
+        name: app
+        version: 1
+    "};
+    assert_eq!(
+        serde_yaml_ng::to_string_sorted(&snapshot).unwrap(),
+        expected
+    );
+}
+
+#[test]
+fn test_enum_as_int() {
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum Color {
+        Red,
+        Green,
+        Blue,
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Pixel {
+        #[serde(with = "serde_yaml_ng::with::enum_as_int")]
+        a: Color,
+        #[serde(with = "serde_yaml_ng::with::enum_as_int")]
+        b: Color,
+        #[serde(with = "serde_yaml_ng::with::enum_as_int")]
+        c: Color,
+    }
+
+    let thing = Pixel {
+        a: Color::Red,
+        b: Color::Green,
+        c: Color::Blue,
+    };
+    let yaml = indoc! {"
+        a: 0
+        b: 1
+        c: 2
+    "};
+    test_serde(&thing, yaml);
+
+    let yaml = indoc! {"
+        a: 0
+        b: 1
+        c: 3
+    "};
+    let err = serde_yaml_ng::from_str::<Pixel>(yaml).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "c: invalid value: integer `3`, expected variant index 0 <= i < 3 at line 3 column 4",
+    );
+
+    #[derive(Serialize, Debug)]
+    enum Shape {
+        Circle(u32),
+    }
+
+    #[derive(Serialize, Debug)]
+    struct Drawing {
+        #[serde(with = "serde_yaml_ng::with::enum_as_int")]
+        shape: Shape,
+    }
+
+    let err = serde_yaml_ng::to_string(&Drawing {
+        shape: Shape::Circle(1),
+    })
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "enum_as_int can only serialize a unit variant of an enum",
+    );
+}
+
+#[test]
+fn test_smart_pointers() {
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Inner {
+        name: String,
+        tags: Vec<String>,
+    }
+
+    let inner = Inner {
+        name: "app".to_owned(),
+        tags: vec!["a".to_owned(), "b".to_owned()],
+    };
+    let yaml = indoc! {"
+        name: app
+        tags:
+        - a
+        - b
+    "};
+    test_serde(&Box::new(inner), yaml);
+
+    let inner = Inner {
+        name: "app".to_owned(),
+        tags: vec!["a".to_owned(), "b".to_owned()],
+    };
+    let value: Value = serde_yaml_ng::from_str(yaml).unwrap();
+    assert_eq!(serde_yaml_ng::to_string(&&inner).unwrap(), yaml);
+    assert_eq!(
+        serde_yaml_ng::to_string(&Arc::new(value.clone())).unwrap(),
+        yaml
+    );
+    assert_eq!(
+        serde_yaml_ng::to_string(&Rc::new(value.clone())).unwrap(),
+        yaml
+    );
+    assert_eq!(serde_yaml_ng::to_string(&Arc::new(&inner)).unwrap(), yaml);
+    assert_eq!(serde_yaml_ng::to_value(Arc::new(inner)).unwrap(), value);
+}
+
+#[test]
+fn test_quote_style() {
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Data {
+        quote: String,
+        forced: String,
+        control: String,
+        chars: Vec<char>,
+    }
+
+    let thing = Data {
+        quote: "it's: here".to_owned(),
+        forced: "1.0".to_owned(),
+        control: "bell\u{7}".to_owned(),
+        chars: vec!['\'', '"'],
+    };
+
+    for (style, yaml) in [
+        (
+            QuoteStyle::Single,
+            indoc! {r#"
+                quote: 'it''s: here'
+                forced: '1.0'
+                control: "bell\a"
+                chars:
+                - ''''
+                - '"'
+            "#},
+        ),
+        (
+            QuoteStyle::Double,
+            indoc! {r#"
+                quote: "it's: here"
+                forced: "1.0"
+                control: "bell\a"
+                chars:
+                - "'"
+                - "\""
+            "#},
+        ),
+    ] {
+        let mut buffer = Vec::new();
+        let mut ser = serde_yaml_ng::Serializer::new(&mut buffer).quote_style(style);
+        thing.serialize(&mut ser).unwrap();
+        assert_eq!(yaml, str::from_utf8(&buffer).unwrap());
+        assert_eq!(thing, serde_yaml_ng::from_str::<Data>(yaml).unwrap());
+    }
+
+    // Keys and flow collections, through SerializeOptions.
+    let value: Value = serde_yaml_ng::from_str(r#"{"k: '1'": ["[v]", plain]}"#).unwrap();
+    for (style, yaml) in [
+        (QuoteStyle::Single, "{'k: ''1''': ['[v]', plain]}\n"),
+        (QuoteStyle::Double, "{\"k: '1'\": [\"[v]\", plain]}\n"),
+    ] {
+        let options = SerializeOptions::new()
+            .mapping_style(Style::Flow)
+            .quote_style(style);
+        assert_eq!(value.to_string_with(&options).unwrap(), yaml);
+        assert_eq!(value, serde_yaml_ng::from_str::<Value>(yaml).unwrap());
+    }
+
+    // The same through a Serializer, with a flow mapping inside another.
+    let value: Value = serde_yaml_ng::from_str(r#"{k: "a, b", m: {"c, d": "[e]"}}"#).unwrap();
+    let mut buffer = Vec::new();
+    let mut ser = serde_yaml_ng::Serializer::new(&mut buffer)
+        .mapping_style(Style::Flow)
+        .quote_style(QuoteStyle::Double);
+    value.serialize(&mut ser).unwrap();
+    let yaml = str::from_utf8(&buffer).unwrap();
+    assert_eq!(yaml, "{k: \"a, b\", m: {\"c, d\": \"[e]\"}}\n");
+    assert_eq!(value, serde_yaml_ng::from_str::<Value>(yaml).unwrap());
+}
+
+#[test]
+fn test_quote_style_always() {
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Data {
+        word: String,
+        boolean: String,
+        real: bool,
+        number: u8,
+        control: String,
+        lines: String,
+    }
+
+    let thing = Data {
+        word: "plain".to_owned(),
+        boolean: "true".to_owned(),
+        real: true,
+        number: 1,
+        control: "bell\u{7}".to_owned(),
+        lines: "a\nb\n".to_owned(),
+    };
+
+    for (style, yaml) in [
+        (
+            QuoteStyle::AlwaysSingle,
+            indoc! {r#"
+                'word': 'plain'
+                'boolean': 'true'
+                'real': true
+                'number': 1
+                'control': "bell\a"
+                'lines': |
+                  a
+                  b
+            "#},
+        ),
+        (
+            QuoteStyle::AlwaysDouble,
+            indoc! {r#"
+                "word": "plain"
+                "boolean": "true"
+                "real": true
+                "number": 1
+                "control": "bell\a"
+                "lines": |
+                  a
+                  b
+            "#},
+        ),
+    ] {
+        let mut buffer = Vec::new();
+        let mut ser = serde_yaml_ng::Serializer::new(&mut buffer).quote_style(style);
+        thing.serialize(&mut ser).unwrap();
+        assert_eq!(yaml, str::from_utf8(&buffer).unwrap());
+        assert_eq!(thing, serde_yaml_ng::from_str::<Data>(yaml).unwrap());
+    }
+
+    let value = Value::String("true".to_owned());
+    let options = SerializeOptions::new().quote_style(QuoteStyle::AlwaysDouble);
+    let yaml = value.to_string_with(&options).unwrap();
+    assert_eq!(yaml, "\"true\"\n");
+    assert_eq!(serde_yaml_ng::from_str::<Value>(&yaml).unwrap(), value);
+}
+
+#[test]
+fn test_flow_threshold() {
+    fn to_string<T: serde::Serialize>(value: &T, max: usize) -> String {
+        let mut buffer = Vec::new();
+        let mut ser = serde_yaml_ng::Serializer::new(&mut buffer).flow_threshold(max);
+        value.serialize(&mut ser).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    assert_eq!(to_string(&[1, 2], 2), "[1, 2]\n");
+    assert_eq!(to_string(&[1, 2, 3], 2), "- 1\n- 2\n- 3\n");
+    assert_eq!(to_string(&Vec::<u8>::new(), 0), "[]\n");
+    assert_eq!(to_string(&[1], 0), "- 1\n");
+
+    let mapping = BTreeMap::from([("a", 1), ("b", 2)]);
+    assert_eq!(to_string(&mapping, 2), "{a: 1, b: 2}\n");
+    assert_eq!(to_string(&mapping, 1), "a: 1\nb: 2\n");
+
+    // The outer collections hold collections, so only the inner ones are
+    // flow, and only while they fit.
+    let nested = BTreeMap::from([
+        ("fits", vec![vec![1, 2], vec![3]]),
+        ("too_long", vec![vec![1, 2, 3]]),
+    ]);
+    let yaml = indoc! {"
+        fits:
+        - [1, 2]
+        - [3]
+        too_long:
+        - - 1
+          - 2
+          - 3
+    "};
+    assert_eq!(to_string(&nested, 2), yaml);
+    assert_eq!(nested, serde_yaml_ng::from_str(yaml).unwrap());
+
+    #[derive(Serialize)]
+    struct Struct {
+        point: (i32, i32),
+        lines: Vec<&'static str>,
+        tagged: Enum,
+    }
+    #[derive(Serialize)]
+    enum Enum {
+        Pair(u8, u8),
+    }
+    let thing = Struct {
+        point: (-1, 1),
+        lines: vec!["a", "b\nc"],
+        tagged: Enum::Pair(1, 2),
+    };
+    let yaml = indoc! {"
+        point: [-1, 1]
+        lines:
+        - a
+        - |-
+          b
+          c
+        tagged: !Pair [1, 2]
+    "};
+    assert_eq!(to_string(&thing, 3), yaml);
+
+    // Strings that need quotes only inside of flow collections get them.
+    let value: Value = serde_yaml_ng::from_str("[a, 'b, c', d]").unwrap();
+    for (style, yaml) in [
+        (QuoteStyle::Single, "[a, 'b, c', d]\n"),
+        (QuoteStyle::Double, "[a, \"b, c\", d]\n"),
+    ] {
+        let options = SerializeOptions::new().flow_threshold(3).quote_style(style);
+        assert_eq!(value.to_string_with(&options).unwrap(), yaml);
+        assert_eq!(value, serde_yaml_ng::from_str::<Value>(yaml).unwrap());
+    }
+    let options = SerializeOptions::new()
+        .flow_threshold(3)
+        .sequence_style(Style::Block);
+    assert_eq!(
+        value.to_string_with(&options).unwrap(),
+        "- a\n- b, c\n- d\n"
+    );
+}
+
+#[test]
+fn test_to_string_with_comments() {
+    let value: Value = serde_yaml_ng::from_str(indoc! {"
+        db:
+          host: localhost
+          port: 5432
+        servers:
+        - name: a
+          tags: [x, y]
+        - name: b
+    "})
+    .unwrap();
+    let comments = HashMap::from([
+        ("/".to_owned(), "Top level.\nSecond line.".to_owned()),
+        ("/db/host".to_owned(), "Database host.".to_owned()),
+        ("/servers".to_owned(), "".to_owned()),
+        ("/servers/1".to_owned(), "Second server.".to_owned()),
+        ("/servers/0/tags/1".to_owned(), "Second tag.".to_owned()),
+        ("/servers/0/tags".to_owned(), "Tags.".to_owned()),
+        ("/db/missing".to_owned(), "Ignored.".to_owned()),
+        ("/servers/7".to_owned(), "Ignored.".to_owned()),
+    ]);
+    let yaml = serde_yaml_ng::to_string_with_comments(&value, &comments).unwrap();
+    let expected = indoc! {"
+        # Top level.
+        # Second line.
+        db:
+          # Database host.
+          host: localhost
+          port: 5432
+        #
+        servers:
+        - name: a
+          # Tags.
+          tags:
+          - x
+          # Second tag.
+          - y
+        # Second server.
+        - name: b
+    "};
+    assert_eq!(yaml, expected);
+    assert_eq!(serde_yaml_ng::from_str::<Value>(&yaml).unwrap(), value);
+
+    let yaml = serde_yaml_ng::to_string_with_comments(&value, &HashMap::new()).unwrap();
+    assert_eq!(yaml, serde_yaml_ng::to_string(&value).unwrap());
+
+    let value: Value = serde_yaml_ng::from_str("a: 1\nb: 2").unwrap();
+    let comments = HashMap::from([(
+        "/b".to_owned(),
+        "x\ry: 2\r\nz\u{85}w\u{2028}v\u{2029}u".to_owned(),
+    )]);
+    let yaml = serde_yaml_ng::to_string_with_comments(&value, &comments).unwrap();
+    let expected = indoc! {"
+        a: 1
+        # x
+        # y: 2
+        # z
+        # w
+        # v
+        # u
+        b: 2
+    "};
+    assert_eq!(yaml, expected);
+    assert_eq!(serde_yaml_ng::from_str::<Value>(&yaml).unwrap(), value);
+}
+
+#[test]
+fn test_indent() {
+    let value: Value = serde_yaml_ng::from_str(indoc! {"
+        name: app
+        ports: [80, 443]
+        servers:
+        - host: a
+          tls:
+            ciphers: [c1, c2]
+        - [nested, seq]
+        script: |
+          echo one
+          echo two
+    "})
+    .unwrap();
+
+    let expected = indoc! {"
+        name: app
+        ports:
+        - 80
+        - 443
+        servers:
+        -   host: a
+            tls:
+                ciphers:
+                - c1
+                - c2
+        -   - nested
+            - seq
+        script: |
+            echo one
+            echo two
+    "};
+    let mut buffer = Vec::new();
+    let mut ser = serde_yaml_ng::Serializer::new(&mut buffer).indent(4);
+    value.serialize(&mut ser).unwrap();
+    let wide = str::from_utf8(&buffer).unwrap();
+    assert_eq!(wide, expected);
+    assert_eq!(serde_yaml_ng::from_str::<Value>(wide).unwrap(), value);
+
+    let options = SerializeOptions::new().indent(4);
+    assert_eq!(value.to_string_with(&options).unwrap(), expected);
+
+    // Out of range widths fall back to the default.
+    let mut buffer = Vec::new();
+    let mut ser = serde_yaml_ng::Serializer::new(&mut buffer).indent(1);
+    value.serialize(&mut ser).unwrap();
+    assert_eq!(
+        str::from_utf8(&buffer).unwrap(),
+        serde_yaml_ng::to_string(&value).unwrap(),
+    );
+}
+
+#[test]
+fn test_sequence_indent() {
+    let value: Value = serde_yaml_ng::from_str(indoc! {"
+        name: app
+        ports:
+        - 80
+        - 443
+        servers:
+        - host: a
+          aliases:
+          - x
+          - y
+          tls:
+            ciphers:
+            - c1
+        - - nested
+          - seq
+        script: !Shell
+        - |
+          echo one
+          echo two
+        - done
+        empty: []
+    "})
+    .unwrap();
+
+    let same = serde_yaml_ng::to_string(&value).unwrap();
+    let mut buffer = Vec::new();
+    let mut ser = serde_yaml_ng::Serializer::new(&mut buffer).sequence_indent(SequenceIndent::Same);
+    value.serialize(&mut ser).unwrap();
+    assert_eq!(str::from_utf8(&buffer).unwrap(), same);
+
+    let expected = indoc! {"
+        name: app
+        ports:
+          - 80
+          - 443
+        servers:
+          - host: a
+            aliases:
+              - x
+              - y
+            tls:
+              ciphers:
+                - c1
+          - - nested
+            - seq
+        script: !Shell
+          - |
+            echo one
+            echo two
+          - done
+        empty: []
+    "};
+    let mut buffer = Vec::new();
+    let mut ser =
+        serde_yaml_ng::Serializer::new(&mut buffer).sequence_indent(SequenceIndent::Indented);
+    value.serialize(&mut ser).unwrap();
+    let indented = str::from_utf8(&buffer).unwrap();
+    assert_eq!(indented, expected);
+    assert_eq!(serde_yaml_ng::from_str::<Value>(indented).unwrap(), value);
+
+    let options = SerializeOptions::new()
+        .indent(4)
+        .sequence_indent(SequenceIndent::Indented);
+    let wide = value["servers"][0].to_string_with(&options).unwrap();
+    let expected = indoc! {"
+        host: a
+        aliases:
+            - x
+            - y
+        tls:
+            ciphers:
+                - c1
+    "};
+    assert_eq!(wide, expected);
+
+    let root = vec![vec!["a"], vec!["b"]];
+    let mut buffer = Vec::new();
+    let mut ser =
+        serde_yaml_ng::Serializer::new(&mut buffer).sequence_indent(SequenceIndent::Indented);
+    root.serialize(&mut ser).unwrap();
+    assert_eq!(buffer, b"- - a\n- - b\n");
+}
+
+#[test]
+fn test_sequence_indent_block_scalars() {
+    // Lines inside block scalars that look like keys and dashes are text,
+    // which must not change the indentation of what follows.
+    let value: Value = serde_yaml_ng::from_str(indoc! {r#"
+        k: ["a:\n- b\nc\n", {m: "x:\n- y\n", n: [1]}]
+        j: [2]
+    "#})
+    .unwrap();
+    let expected = indoc! {"
+        k:
+          - |
+            a:
+            - b
+            c
+          - m: |
+              x:
+              - y
+            n:
+              - 1
+        j:
+          - 2
+    "};
+    let options = SerializeOptions::new().sequence_indent(SequenceIndent::Indented);
+    let yaml = value.to_string_with(&options).unwrap();
+    assert_eq!(yaml, expected);
+    assert_eq!(serde_yaml_ng::from_str::<Value>(&yaml).unwrap(), value);
+
+    let mut buffer = Vec::new();
+    let mut ser =
+        serde_yaml_ng::Serializer::new(&mut buffer).sequence_indent(SequenceIndent::Indented);
+    "a:\n- b\n".serialize(&mut ser).unwrap();
+    value.serialize(&mut ser).unwrap();
+    let yaml = str::from_utf8(&buffer).unwrap();
+    let documents: Vec<Value> = serde_yaml_ng::from_str_multi(yaml)
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(documents, [Value::from("a:\n- b\n"), value]);
+}
+
+#[test]
+fn test_line_width() {
+    let ids: Vec<u32> = (1000..1050).collect();
+
+    let mut buffer = Vec::new();
+    let mut ser = serde_yaml_ng::Serializer::new(&mut buffer)
+        .sequence_style(Style::Flow)
+        .line_width(40);
+    ids.serialize(&mut ser).unwrap();
+    let yaml = str::from_utf8(&buffer).unwrap();
+
+    let lines: Vec<&str> = yaml.lines().collect();
+    assert!(lines.len() > 1, "{}", yaml);
+    for line in &lines {
+        // Breaks happen after the entry that crosses the width.
+        assert!(line.len() <= 40 + ", 1000".len(), "{}", yaml);
+    }
+    assert!(lines[0].starts_with("[1000, 1001,"));
+    assert!(lines.last().unwrap().ends_with("1049]"));
+    let deserialized: Vec<u32> = serde_yaml_ng::from_str(yaml).unwrap();
+    assert_eq!(deserialized, ids);
+
+    let value = serde_yaml_ng::to_value(&ids).unwrap();
+    let options = SerializeOptions::new()
+        .sequence_style(Style::Flow)
+        .line_width(40);
+    assert_eq!(value.to_string_with(&options).unwrap(), yaml);
+
+    // Without a width everything stays on one line.
+    let options = SerializeOptions::new().sequence_style(Style::Flow);
+    assert_eq!(value.to_string_with(&options).unwrap().lines().count(), 1);
+}
+
+#[test]
+fn test_align_colons() {
+    let value: Value = serde_yaml_ng::from_str(indoc! {"
+        name: app
+        replicas: 3
+        env:
+          LOG: debug
+          REGION: eu
+        ports:
+        - port: 80
+          protocol: tcp
+        banner: |
+          key: value
+          longer_key: value
+        timeout: 30s
+    "})
+    .unwrap();
+
+    let mut buffer = Vec::new();
+    let mut ser = serde_yaml_ng::Serializer::new(&mut buffer).align_colons(true);
+    value.serialize(&mut ser).unwrap();
+    let yaml = str::from_utf8(&buffer).unwrap();
+    let expected = indoc! {"
+        name    : app
+        replicas: 3
+        env:
+          LOG   : debug
+          REGION: eu
+        ports:
+        - port    : 80
+          protocol: tcp
+        banner  : |
+          key: value
+          longer_key: value
+        timeout : 30s
+    "};
+    assert_eq!(yaml, expected);
+
+    let deserialized: Value = serde_yaml_ng::from_str(yaml).unwrap();
+    assert_eq!(deserialized, value);
+
+    let options = SerializeOptions::new().align_colons(true);
+    assert_eq!(value.to_string_with(&options).unwrap(), expected);
+}
+
+#[test]
+fn test_scalar_formatter() {
+    #[derive(Serialize)]
+    struct Rule {
+        name: String,
+        mask: u32,
+        ports: Vec<u16>,
+    }
+    #[derive(Serialize)]
+    enum Filter {
+        Masked { mask: u32 },
+    }
+    #[derive(Serialize)]
+    struct Config {
+        mask: u64,
+        count: u64,
+        rules: Vec<Rule>,
+        extra: BTreeMap<String, u8>,
+        filter: Filter,
+    }
+
+    let config = Config {
+        mask: 0xff00,
+        count: 255,
+        rules: vec![Rule {
+            name: "mask".to_owned(),
+            mask: 0x0f,
+            ports: vec![80, 443],
+        }],
+        extra: BTreeMap::from([("mask".to_owned(), 10)]),
+        filter: Filter::Masked { mask: 7 },
+    };
+
+    let paths = Rc::new(RefCell::new(Vec::new()));
+    let seen = Rc::clone(&paths);
+    let mut buffer = Vec::new();
+    let mut ser =
+        serde_yaml_ng::Serializer::new(&mut buffer).scalar_formatter(move |value, path| {
+            seen.borrow_mut().push(path.to_vec());
+            match (value.as_u64(), path.last()) {
+                (Some(n), Some(PathSegment::Key(key))) if key == "mask" => {
+                    Some(format!("0x{:x}", n))
+                }
+                _ => None,
+            }
+        });
+    config.serialize(&mut ser).unwrap();
+    let expected = indoc! {"
+        mask: 0xff00
+        count: 255
+        rules:
+        - name: mask
+          mask: 0xf
+          ports:
+          - 80
+          - 443
+        extra:
+          mask: 0xa
+        filter: !Masked
+          mask: 0x7
+    "};
+    assert_eq!(str::from_utf8(&buffer).unwrap(), expected);
+
+    let key = |k: &str| PathSegment::Key(Value::from(k));
+    let port = vec![
+        key("rules"),
+        PathSegment::Index(0),
+        key("ports"),
+        PathSegment::Index(1),
+    ];
+    assert!(paths.borrow().contains(&port));
+    assert!(paths.borrow().contains(&vec![key("filter"), key("mask")]));
+    assert_eq!(paths.borrow().len(), 8);
+}
+
+#[test]
+fn test_integral_floats_as_int() {
+    let floats = vec![3.0, -2.0, 0.5, 1e20, f64::NAN];
+    assert_eq!(
+        serde_yaml_ng::to_string(&floats).unwrap(),
+        "- 3.0\n- -2.0\n- 0.5\n- 1e20\n- .nan\n",
+    );
+
+    let mut buffer = Vec::new();
+    let mut ser = serde_yaml_ng::Serializer::new(&mut buffer).integral_floats_as_int(true);
+    floats.serialize(&mut ser).unwrap();
+    assert_eq!(
+        str::from_utf8(&buffer).unwrap(),
+        "- 3\n- -2\n- 0.5\n- 1e20\n- .nan\n",
+    );
+
+    let mut buffer = Vec::new();
+    let mut ser = serde_yaml_ng::Serializer::new(&mut buffer).integral_floats_as_int(true);
+    3.0f32.serialize(&mut ser).unwrap();
+    assert_eq!(buffer, b"3\n");
+
+    let value = serde_yaml_ng::to_value(3.0).unwrap();
+    let options = SerializeOptions::new().integral_floats_as_int(true);
+    assert_eq!(value.to_string_with(&options).unwrap(), "3\n");
+    assert_eq!(serde_yaml_ng::from_str::<f64>("3").unwrap(), 3.0);
+}
+
+#[test]
+fn test_document_round_trip() {
+    let yaml = indoc! {"
+        %YAML 1.2
+        %TAG !e! tag:example.com,2000:
+        ---
+        name: !e!widget x
+        ports:
+        - 80
+        ...
+    "};
+    let document = Document::from_str(yaml).unwrap();
+    assert_eq!(
+        document.directives.version,
+        Some(VersionDirective { major: 1, minor: 2 }),
+    );
+    assert_eq!(document.directives.tags[0].handle, "!e!");
+    assert!(document.explicit_start);
+    assert!(document.explicit_end);
+    assert_eq!(document.value["ports"][0], 80);
+    assert_eq!(document.to_string().unwrap(), yaml);
+
+    let yaml = "--- x\n";
+    let document = Document::from_str(yaml).unwrap();
+    assert!(document.directives.is_empty());
+    assert!(document.explicit_start);
+    assert!(!document.explicit_end);
+    assert_eq!(document.to_string().unwrap(), yaml);
+
+    let document = Document::from_str("x\n").unwrap();
+    assert!(!document.explicit_start);
+    assert_eq!(document.to_string().unwrap(), "x\n");
+
+    let document: Document = serde_yaml_ng::from_str("a: 1").unwrap();
+    assert_eq!(
+        document,
+        Document::new(serde_yaml_ng::from_str("a: 1").unwrap())
+    );
+    assert_eq!(serde_yaml_ng::to_string(&document).unwrap(), "a: 1\n");
+
+    let err = Document::from_str("--- 1\n--- 2\n").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "deserializing from YAML containing more than one document is not supported",
+    );
+}
+
+#[cfg(feature = "units")]
+#[test]
+fn test_units() {
+    use serde_yaml_ng::{ByteSize, HumanDuration};
+    use std::time::Duration;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Limits {
+        memory: ByteSize,
+        timeout: HumanDuration,
+    }
+
+    let yaml = indoc! {"
+        memory: 10MB
+        timeout: 90s
+    "};
+    let expected = Limits {
+        memory: ByteSize(10_000_000),
+        timeout: HumanDuration(Duration::from_secs(90)),
+    };
+    test_serde(&expected, yaml);
+
+    let limits: Limits = serde_yaml_ng::from_str("memory: 4GiB\ntimeout: 1.5h").unwrap();
+    assert_eq!(limits.memory, ByteSize(4 << 30));
+    assert_eq!(limits.timeout, HumanDuration(Duration::from_secs(5400)));
+    assert_eq!(
+        serde_yaml_ng::to_string(&limits).unwrap(),
+        "memory: 4GiB\ntimeout: 90m\n",
+    );
+
+    let limits: Limits = serde_yaml_ng::from_str("memory: 512\ntimeout: 1m30s250ms").unwrap();
+    assert_eq!(limits.memory, ByteSize(512));
+    assert_eq!(limits.timeout, HumanDuration(Duration::from_millis(90_250)));
+
+    let err = serde_yaml_ng::from_str::<Limits>("memory: ten MB\ntimeout: 1s").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "memory: invalid value: string \"ten MB\", expected a byte size like \"10MB\" at line 1 column 9",
+    );
+    let err = serde_yaml_ng::from_str::<Limits>("memory: 1.5B\ntimeout: 1s").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "memory: invalid value: string \"1.5B\", expected a byte size like \"10MB\" at line 1 column 9",
+    );
+    let err = serde_yaml_ng::from_str::<Limits>("memory: 1KB\ntimeout: 90").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "timeout: invalid value: string \"90\", expected a duration like \"90s\" at line 2 column 10",
+    );
+    let err = serde_yaml_ng::from_str::<Limits>("memory: 1KB\ntimeout: 5 fortnights").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "timeout: invalid value: string \"5 fortnights\", expected a duration like \"90s\" at line 2 column 10",
+    );
+}
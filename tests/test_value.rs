@@ -8,7 +8,12 @@ use indoc::indoc;
 use serde::de::IntoDeserializer;
 use serde::Deserialize;
 use serde_derive::{Deserialize, Serialize};
-use serde_yaml_ng::{Number, Value};
+use serde_yaml_ng::value::{
+    ArrayStrategy, MergeOptions, PathSegment, ScalarKind, Tag, TaggedValue,
+};
+use serde_yaml_ng::{Mapping, NullStyle, Number, SerializeOptions, Style, Value};
+use std::ptr;
+use std::str;
 
 #[test]
 fn test_nan() {
@@ -58,6 +63,21 @@ fn test_into_deserializer() {
     );
 }
 
+#[test]
+fn test_alias_expansion() {
+    let yaml = indoc! {"
+        primary: &db {host: db.internal, ports: [5432, 5433]}
+        replica: *db
+        backup: *db
+    "};
+    let value: Value = serde_yaml_ng::from_str(yaml).unwrap();
+    let expected: Value =
+        serde_yaml_ng::from_str("{host: db.internal, ports: [5432, 5433]}").unwrap();
+    assert_eq!(value["primary"], expected);
+    assert_eq!(value["replica"], expected);
+    assert_eq!(value["backup"], expected);
+}
+
 #[test]
 fn test_merge() {
     // From https://yaml.org/type/merge.html.
@@ -98,6 +118,31 @@ fn test_merge() {
     }
 }
 
+#[test]
+fn test_merge_chained() {
+    let yaml = indoc! {"
+        base: &base {a: 1, b: 1}
+        middle: &middle {<<: *base, b: 2, c: 2}
+        other: &other {c: 3, d: 3}
+        leaf:
+          <<: [*middle, *other]
+          e: 4
+    "};
+
+    let mut value: Value = serde_yaml_ng::from_str(yaml).unwrap();
+    value.apply_merge().unwrap();
+    let expected: Value = serde_yaml_ng::from_str("{e: 4, a: 1, b: 2, c: 2, d: 3}").unwrap();
+    assert_eq!(value["leaf"], expected);
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Leaf {
+        a: u8,
+        e: u8,
+    }
+    let leaf: Leaf = serde_yaml_ng::from_value(value["leaf"].clone()).unwrap();
+    assert_eq!(leaf, Leaf { a: 1, e: 4 });
+}
+
 #[test]
 fn test_debug() {
     let yaml = indoc! {"
@@ -151,3 +196,1962 @@ fn test_tagged() {
     let serialized = serde_yaml_ng::to_value(&value).unwrap();
     assert_eq!(value, serialized);
 }
+
+#[test]
+fn test_get_key_value() {
+    let mut mapping = Mapping::new();
+    mapping.insert(Value::String("k".to_owned()), Value::Bool(true));
+    mapping.insert(Value::Number(Number::from(1)), Value::Bool(false));
+
+    let lookup = Value::String("k".to_owned());
+    let (key, value) = mapping.get_key_value(&lookup).unwrap();
+    assert_eq!(*key, lookup);
+    assert!(!ptr::eq(key, &lookup));
+    assert!(ptr::eq(key, mapping.keys().next().unwrap()));
+    assert_eq!(*value, Value::Bool(true));
+
+    let (key, _) = mapping.get_key_value("k").unwrap();
+    assert!(ptr::eq(key, mapping.keys().next().unwrap()));
+
+    assert_eq!(mapping.get_key_value("missing"), None);
+}
+
+#[test]
+fn test_path_exists() {
+    let value: Value = serde_yaml_ng::from_str(indoc! {"
+        server:
+          listeners:
+            - port: 80
+            - port: 443
+              tls: ~
+        a/b: slash
+    "})
+    .unwrap();
+
+    assert!(value.path_exists(""));
+    assert!(value.path_exists("/server/listeners/1/port"));
+    assert_eq!(value.pointer("/server/listeners/1/port").unwrap(), 443);
+    assert!(value.path_exists("/server/listeners/1/tls"));
+    assert!(value.pointer("/server/listeners/1/tls").unwrap().is_null());
+    assert!(value.path_exists("/a~1b"));
+
+    assert!(!value.path_exists("/server/listeners/0/tls"));
+    assert!(!value.path_exists("/server/listeners/2"));
+    assert!(!value.path_exists("/server/listeners/01"));
+    assert!(!value.path_exists("/server/port/nested"));
+    assert!(!value.path_exists("server"));
+}
+
+#[test]
+fn test_pointer() {
+    let mut value: Value = serde_yaml_ng::from_str(indoc! {"
+        servers:
+          - port: 80
+          - !tls {port: 443}
+        a/b: slash
+        c~d: tilde
+        ~1: escaped tilde
+        '': empty
+        7: seven
+    "})
+    .unwrap();
+
+    assert_eq!(value.pointer(""), Some(&value));
+    assert_eq!(value.pointer("/servers/0/port").unwrap(), 80);
+    assert_eq!(value.pointer("/servers/1/port").unwrap(), 443);
+    assert_eq!(value.pointer("/a~1b").unwrap(), "slash");
+    assert_eq!(value.pointer("/c~0d").unwrap(), "tilde");
+    assert_eq!(value.pointer("/~01").unwrap(), "escaped tilde");
+    assert_eq!(value.pointer("/").unwrap(), "empty");
+    assert_eq!(value.pointer("/7").unwrap(), "seven");
+
+    assert_eq!(value.pointer("servers"), None);
+    assert_eq!(value.pointer("/servers/2"), None);
+    assert_eq!(value.pointer("/servers/-"), None);
+    assert_eq!(value.pointer("/servers/01"), None);
+    assert_eq!(value.pointer("/servers/port"), None);
+    assert_eq!(value.pointer("/a~1b/0"), None);
+    assert_eq!(value.pointer("/missing"), None);
+
+    *value.pointer_mut("/servers/1/port").unwrap() = Value::from(8443);
+    assert_eq!(value["servers"][1]["port"], 8443);
+    assert!(value.pointer_mut("/servers/9").is_none());
+}
+
+#[test]
+fn test_into_vec() {
+    let value: Value = serde_yaml_ng::from_str("[1, 2, 3]").unwrap();
+    assert_eq!(value.into_vec::<u32>().unwrap(), [1, 2, 3]);
+
+    let value: Value = serde_yaml_ng::from_str("[1, 2, three, 4]").unwrap();
+    let error = value.into_vec::<u32>().unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "[2]: invalid type: string \"three\", expected u32",
+    );
+
+    let value: Value = serde_yaml_ng::from_str("{a: 1}").unwrap();
+    let error = value.into_vec::<u32>().unwrap_err();
+    assert_eq!(error.to_string(), "invalid type: map, expected a sequence");
+}
+
+#[test]
+fn test_merge_with() {
+    let mut base: Value = serde_yaml_ng::from_str(indoc! {"
+        name: base
+        nested:
+          greeting: hello
+          count: 1
+        only_base: true
+    "})
+    .unwrap();
+    let other: Value = serde_yaml_ng::from_str(indoc! {"
+        name: other
+        nested:
+          greeting: world
+          extra: 2
+    "})
+    .unwrap();
+
+    let mut paths = Vec::new();
+    base.merge_with(other, |path, old, new| {
+        paths.push(path.to_vec());
+        match (old, new) {
+            (Value::String(old), Value::String(new)) => Value::String(old + "+" + &new),
+            (_, new) => new,
+        }
+    });
+
+    let expected: Value = serde_yaml_ng::from_str(indoc! {"
+        name: base+other
+        nested:
+          greeting: hello+world
+          count: 1
+          extra: 2
+        only_base: true
+    "})
+    .unwrap();
+    assert_eq!(base, expected);
+    assert_eq!(
+        paths,
+        [
+            vec![PathSegment::Key(Value::from("name"))],
+            vec![
+                PathSegment::Key(Value::from("nested")),
+                PathSegment::Key(Value::from("greeting")),
+            ],
+        ],
+    );
+}
+
+#[test]
+fn test_merge_tagged_mappings() {
+    let mut base: Value = serde_yaml_ng::from_str("!svc {host: a, port: 80}").unwrap();
+    let other: Value = serde_yaml_ng::from_str("{port: 8080}").unwrap();
+    base.merge(other);
+    let expected: Value = serde_yaml_ng::from_str("!svc {host: a, port: 8080}").unwrap();
+    assert_eq!(base, expected);
+
+    let other: Value = serde_yaml_ng::from_str("!web {tls: true}").unwrap();
+    base.merge(other);
+    let expected: Value = serde_yaml_ng::from_str("!web {host: a, port: 8080, tls: true}").unwrap();
+    assert_eq!(base, expected);
+
+    let other: Value = serde_yaml_ng::from_str("{host: ~}").unwrap();
+    base.merge_with_delete(other);
+    let expected: Value = serde_yaml_ng::from_str("!web {port: 8080, tls: true}").unwrap();
+    assert_eq!(base, expected);
+
+    // A tagged scalar is still replaced whole.
+    let mut base: Value = serde_yaml_ng::from_str("!t 1").unwrap();
+    base.merge(Value::from(2));
+    assert_eq!(base, 2);
+}
+
+#[test]
+fn test_deep_merge() {
+    let mut base: Value = serde_yaml_ng::from_str("{a: {b: 1, c: [1, 2]}, d: x}").unwrap();
+    let other: Value = serde_yaml_ng::from_str("{a: {c: [3]}, d: {e: y}}").unwrap();
+    base.merge(other);
+    let expected: Value = serde_yaml_ng::from_str("{a: {b: 1, c: [3]}, d: {e: y}}").unwrap();
+    assert_eq!(base, expected);
+}
+
+#[test]
+fn test_pretty_print_to() {
+    let value: Value = serde_yaml_ng::from_str(indoc! {"
+        zeta:
+          second: [b, a]
+          first:
+            inner: 1
+        alpha: true
+    "})
+    .unwrap();
+
+    let mut buffer = Vec::new();
+    let options = SerializeOptions::new().sort_keys(true).indent(4);
+    value.pretty_print_to(&mut buffer, &options).unwrap();
+    let expected = indoc! {"
+        alpha: true
+        zeta:
+            first:
+                inner: 1
+            second:
+            - b
+            - a
+    "};
+    assert_eq!(str::from_utf8(&buffer).unwrap(), expected);
+    assert_eq!(value.to_string_with(&options).unwrap(), expected);
+
+    // The value itself is left in its original order.
+    assert_eq!(value.as_mapping().unwrap().keys().next().unwrap(), "zeta");
+
+    let options = SerializeOptions::new().sequence_style(Style::Flow);
+    let expected = indoc! {"
+        zeta:
+          second: [b, a]
+          first:
+            inner: 1
+        alpha: true
+    "};
+    assert_eq!(value.to_string_with(&options).unwrap(), expected);
+}
+
+#[test]
+fn test_collect_strings() {
+    let value: Value = serde_yaml_ng::from_str(indoc! {"
+        name: ${NAME}
+        servers:
+          - host: a.example.com
+            port: 80
+          - host: !ip ${HOST}
+        flags: [true, ~, x]
+        1: one
+    "})
+    .unwrap();
+
+    assert_eq!(
+        value.collect_strings(false),
+        ["${NAME}", "a.example.com", "${HOST}", "x", "one"],
+    );
+    assert_eq!(
+        value.collect_strings(true),
+        [
+            "name",
+            "${NAME}",
+            "servers",
+            "host",
+            "a.example.com",
+            "port",
+            "host",
+            "${HOST}",
+            "flags",
+            "x",
+            "one",
+        ],
+    );
+}
+
+#[test]
+fn test_intersect() {
+    let a: Value = serde_yaml_ng::from_str(indoc! {"
+        name: service
+        port: 80
+        tags: [web, public, v1]
+        limits:
+          cpu: 2
+          memory: 512
+          disk: {size: 10}
+        only_a: true
+    "})
+    .unwrap();
+    let b: Value = serde_yaml_ng::from_str(indoc! {"
+        name: service
+        port: 8080
+        tags: [web, private, v1, extra]
+        limits:
+          cpu: 2
+          memory: 1024
+          disk: {size: 20}
+        only_b: true
+    "})
+    .unwrap();
+
+    let expected: Value = serde_yaml_ng::from_str(indoc! {"
+        name: service
+        tags: [web, v1]
+        limits:
+          cpu: 2
+    "})
+    .unwrap();
+    assert_eq!(a.intersect(&b), expected);
+    assert_eq!(a.intersect(&a), a);
+
+    let x: Value = serde_yaml_ng::from_str("{x: 1}").unwrap();
+    let y: Value = serde_yaml_ng::from_str("{y: 1}").unwrap();
+    assert_eq!(x.intersect(&y), Value::Mapping(Mapping::new()));
+    assert_eq!(x.intersect(&Value::from(1)), Value::Null);
+}
+
+#[test]
+fn test_partition() {
+    let mapping: Mapping = serde_yaml_ng::from_str(indoc! {"
+        _internal: 1
+        name: app
+        _debug: true
+        port: 80
+        _cache: null
+    "})
+    .unwrap();
+    let (private, public) = mapping.partition(|k, _v| k.as_str().unwrap().starts_with('_'));
+
+    let private_keys: Vec<&str> = private.keys().map(|k| k.as_str().unwrap()).collect();
+    assert_eq!(private_keys, ["_internal", "_debug", "_cache"]);
+    assert_eq!(private["_debug"], true);
+
+    let public_keys: Vec<&str> = public.keys().map(|k| k.as_str().unwrap()).collect();
+    assert_eq!(public_keys, ["name", "port"]);
+    assert_eq!(public["port"], 80);
+}
+
+#[test]
+fn test_fingerprint() {
+    let a: Value = serde_yaml_ng::from_str(indoc! {"
+        name: app
+        server:
+          host: localhost
+          port: 8080
+        features: [a, b]
+    "})
+    .unwrap();
+    let b: Value = serde_yaml_ng::from_str(indoc! {"
+        features: [a, b]
+        server:
+          port: 0x1f90
+          host: localhost
+        name: app
+    "})
+    .unwrap();
+    assert_eq!(a.fingerprint(), b.fingerprint());
+    assert_eq!(a.fingerprint(), a.clone().fingerprint());
+
+    let one: Value = serde_yaml_ng::from_str("1").unwrap();
+    let one_float: Value = serde_yaml_ng::from_str("1.0").unwrap();
+    assert_eq!(one.fingerprint(), one_float.fingerprint());
+
+    let reordered: Value = serde_yaml_ng::from_str("features: [b, a]").unwrap();
+    let original: Value = serde_yaml_ng::from_str("features: [a, b]").unwrap();
+    assert_ne!(reordered.fingerprint(), original.fingerprint());
+
+    let string: Value = serde_yaml_ng::from_str("'1'").unwrap();
+    assert_ne!(string.fingerprint(), one.fingerprint());
+    let tagged: Value = serde_yaml_ng::from_str("!x 1").unwrap();
+    assert_ne!(tagged.fingerprint(), one.fingerprint());
+
+    assert_eq!(Value::Null.fingerprint(), "af63f34c860213a1");
+}
+
+#[test]
+fn test_deep_size_bytes() {
+    let large = "x".repeat(100_000);
+    let mut value: Value = serde_yaml_ng::from_str("{name: app, items: [1, 2, {k: v}]}").unwrap();
+    let small = value.deep_size_bytes();
+    assert!(small > 0);
+
+    value["blob"] = Value::String(large.clone());
+    let size = value.deep_size_bytes();
+    assert!(size >= small + large.len());
+    assert!(size < 2 * large.len());
+
+    let tagged: Value = serde_yaml_ng::from_str("!big [a]").unwrap();
+    let untagged: Value = serde_yaml_ng::from_str("[a]").unwrap();
+    assert!(tagged.deep_size_bytes() > untagged.deep_size_bytes());
+
+    assert_eq!(Value::Null.deep_size_bytes(), 0);
+    assert_eq!(Value::from(1).deep_size_bytes(), 0);
+}
+
+#[test]
+fn test_strip_tags() {
+    let mut value: Value = serde_yaml_ng::from_str(indoc! {"
+        server: !Server
+          host: !Host localhost
+          ports: !Ports [!Port 80, 443]
+        ? !Key name
+        : !!str app
+        nested: !Outer
+          deep: [!A {x: !B 1}]
+    "})
+    .unwrap();
+    let inner = TaggedValue {
+        tag: Tag::new("Inner"),
+        value: Value::from(true),
+    };
+    value["twice"] = Value::Tagged(Box::new(TaggedValue {
+        tag: Tag::new("Outer"),
+        value: Value::Tagged(Box::new(inner)),
+    }));
+    value.strip_tags();
+
+    fn assert_untagged(value: &Value) {
+        match value {
+            Value::Tagged(tagged) => panic!("unexpected tag {}", tagged.tag),
+            Value::Sequence(sequence) => sequence.iter().for_each(assert_untagged),
+            Value::Mapping(mapping) => {
+                for (k, v) in mapping {
+                    assert_untagged(k);
+                    assert_untagged(v);
+                }
+            }
+            Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => {}
+        }
+    }
+    assert_untagged(&value);
+
+    let expected: Value = serde_yaml_ng::from_str(indoc! {"
+        server:
+          host: localhost
+          ports: [80, 443]
+        name: app
+        nested:
+          deep: [{x: 1}]
+        twice: true
+    "})
+    .unwrap();
+    assert_eq!(value, expected);
+}
+
+#[test]
+fn test_all_keys() {
+    let value: Value = serde_yaml_ng::from_str(indoc! {"
+        name: app
+        server:
+          host: localhost
+          tls: {cert: a.pem, key: a.key}
+        clients:
+          - name: first
+            retries: 3
+          - !Client {name: second, timeout: 5}
+        1: numeric key
+        [a, b]: sequence key
+        ? !Tagged key
+        : value
+    "})
+    .unwrap();
+    let keys: Vec<String> = value.all_keys().into_iter().collect();
+    assert_eq!(
+        keys,
+        ["cert", "clients", "host", "key", "name", "retries", "server", "timeout", "tls",],
+    );
+    assert!(Value::from("scalar").all_keys().is_empty());
+}
+
+#[test]
+fn test_try_into_containers() {
+    let value: Value = serde_yaml_ng::from_str(indoc! {"
+        map: {a: 1}
+        seq: !Tagged [x, y]
+        str: text
+    "})
+    .unwrap();
+
+    let mapping = value["map"].clone().try_into_mapping().unwrap();
+    assert_eq!(mapping["a"], 1);
+    let sequence = value["seq"].clone().try_into_sequence().unwrap();
+    assert_eq!(sequence, [Value::from("x"), Value::from("y")]);
+
+    let err = value["seq"].clone().try_into_mapping().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid type: sequence, expected a mapping"
+    );
+    let err = value["str"].clone().try_into_sequence().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid type: string \"text\", expected a sequence",
+    );
+    let err = Value::Null.try_into_mapping().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid type: unit value, expected a mapping"
+    );
+}
+
+#[test]
+fn test_stringify_scalars() {
+    let mut value: Value = serde_yaml_ng::from_str(indoc! {"
+        name: app
+        port: 8080
+        ratio: 0.5
+        big: -inf
+        neg: -.inf
+        debug: false
+        owner: null
+        tags: [1, true, ~, x]
+        1: !Tagged 2
+    "})
+    .unwrap();
+    value.stringify_scalars(NullStyle::Keyword);
+    let expected: Value = serde_yaml_ng::from_str(indoc! {"
+        name: app
+        port: '8080'
+        ratio: '0.5'
+        big: -inf
+        neg: '-.inf'
+        debug: 'false'
+        owner: 'null'
+        tags: ['1', 'true', 'null', x]
+        1: !Tagged '2'
+    "})
+    .unwrap();
+    assert_eq!(value, expected);
+
+    let mut value = Value::Null;
+    value.stringify_scalars(NullStyle::Empty);
+    assert_eq!(value, "");
+    let mut value = Value::Null;
+    value.stringify_scalars(NullStyle::Tilde);
+    assert_eq!(value, "~");
+}
+
+#[test]
+fn test_as_sequence_of() {
+    let value: Value = serde_yaml_ng::from_str(indoc! {"
+        numbers: [1, -2, 3]
+        tagged: !List [4, 5]
+        mixed: [1, x]
+        scalar: 1
+    "})
+    .unwrap();
+    assert_eq!(
+        value["numbers"].as_sequence_of::<i64>(),
+        Some(vec![1, -2, 3])
+    );
+    assert_eq!(value["tagged"].as_sequence_of::<i64>(), Some(vec![4, 5]));
+    assert_eq!(value["mixed"].as_sequence_of::<i64>(), None);
+    assert_eq!(value["scalar"].as_sequence_of::<i64>(), None);
+    assert_eq!(value.as_sequence_of::<i64>(), None);
+    assert!(value["numbers"].is_sequence());
+}
+
+#[test]
+fn test_sort_all_sequences() {
+    use std::collections::{HashMap, HashSet};
+
+    let options = SerializeOptions::new()
+        .sort_keys(true)
+        .sort_all_sequences(true);
+    let serialize = |set: &HashSet<i64>| {
+        let mut map = HashMap::new();
+        map.insert("set", set.clone());
+        map.insert("other", [9, 8].into_iter().collect());
+        serde_yaml_ng::to_value(&map)
+            .unwrap()
+            .to_string_with(&options)
+            .unwrap()
+    };
+
+    let first: HashSet<i64> = (0..20).collect();
+    let second: HashSet<i64> = (0..20).rev().collect();
+    let expected = serialize(&first);
+    assert_eq!(serialize(&second), expected);
+    assert!(expected.starts_with("other:\n- 8\n- 9\nset:\n- 0\n- 1\n- 2\n"));
+
+    let value: Value = serde_yaml_ng::from_str("[b, 2, [z, y], null, a, 1]").unwrap();
+    let sorted = value
+        .to_string_with(&SerializeOptions::new().sort_all_sequences(true))
+        .unwrap();
+    assert_eq!(sorted, "- null\n- 1\n- 2\n- a\n- b\n- - y\n  - z\n");
+}
+
+#[test]
+fn test_from_vars_prefixed() {
+    let vars = vec![
+        ("APP__SERVER__PORT".to_owned(), "8080".to_owned()),
+        ("APP__DB__HOST".to_owned(), "db.local".to_owned()),
+        ("APP__NAME".to_owned(), "demo".to_owned()),
+        (
+            "APP__SERVER__TLS__CERT".to_owned(),
+            "/etc/cert.pem".to_owned(),
+        ),
+        ("APP__DB".to_owned(), "shadowed".to_owned()),
+        ("APP__".to_owned(), "empty".to_owned()),
+        ("OTHER__NAME".to_owned(), "ignored".to_owned()),
+    ];
+    let value = Value::from_vars_prefixed(vars, "APP__", "__");
+    let expected: Value = serde_yaml_ng::from_str(indoc! {"
+        db:
+          host: db.local
+        name: demo
+        server:
+          port: '8080'
+          tls:
+            cert: /etc/cert.pem
+    "})
+    .unwrap();
+    assert_eq!(value, expected);
+
+    let value = Value::from_vars_prefixed([("X_A_B", "1")], "X", "_");
+    assert_eq!(
+        value,
+        serde_yaml_ng::from_str::<Value>("{a: {b: '1'}}").unwrap()
+    );
+
+    let value = Value::from_env_prefixed("SERDE_YAML_NG_TEST_UNSET_PREFIX__", "__");
+    assert_eq!(value, Value::Mapping(Mapping::new()));
+}
+
+#[test]
+fn test_overlay_vars() {
+    let lookup = |name: &str| match name {
+        "HOST" => Some("db.internal".to_owned()),
+        "EMPTY" => Some(String::new()),
+        _ => None,
+    };
+
+    let mut value: Value = serde_yaml_ng::from_str(indoc! {"
+        host: ${HOST:-localhost}
+        port: ${PORT:-5432}
+        user: ${EMPTY:-admin}
+        hosts:
+          - ${HOST}
+          - ${MISSING}
+          - !replica ${HOST:?unset}
+        ${HOST}: $${HOST} costs $5
+        count: 3
+    "})
+    .unwrap();
+    value.overlay_vars(lookup).unwrap();
+    let expected: Value = serde_yaml_ng::from_str(indoc! {"
+        host: db.internal
+        port: '5432'
+        user: admin
+        hosts: [db.internal, '', !replica db.internal]
+        ${HOST}: ${HOST} costs $5
+        count: 3
+    "})
+    .unwrap();
+    assert_eq!(value, expected);
+
+    let mut value = Value::from("${PORT:?port is required}");
+    let error = value.overlay_vars(lookup).unwrap_err();
+    assert_eq!(error.to_string(), "PORT: port is required");
+
+    let mut value = Value::from("${EMPTY:?}");
+    let error = value.overlay_vars(lookup).unwrap_err();
+    assert_eq!(error.to_string(), "EMPTY: parameter null or not set");
+
+    let mut value = Value::from("${HOST");
+    let error = value.overlay_vars(lookup).unwrap_err();
+    assert_eq!(error.to_string(), "unclosed `${` in variable reference");
+
+    let mut value = Value::from("${SERDE_YAML_NG_TEST_UNSET_VAR:-fallback}");
+    value.overlay_env_vars().unwrap();
+    assert_eq!(value, "fallback");
+}
+
+#[test]
+fn test_merge_all() {
+    let defaults: Value = serde_yaml_ng::from_str(indoc! {"
+        server: {host: localhost, port: 80, tls: false}
+        log: info
+        features: [a]
+    "})
+    .unwrap();
+    let file: Value = serde_yaml_ng::from_str(indoc! {"
+        server: {port: 8080, tls: true}
+        features: [b, c]
+    "})
+    .unwrap();
+    let overrides: Value = serde_yaml_ng::from_str(indoc! {"
+        server: {tls: false}
+        log: debug
+    "})
+    .unwrap();
+
+    let merged = serde_yaml_ng::merge_all(vec![defaults, file, overrides]);
+    let expected: Value = serde_yaml_ng::from_str(indoc! {"
+        server: {host: localhost, port: 8080, tls: false}
+        log: debug
+        features: [b, c]
+    "})
+    .unwrap();
+    assert_eq!(merged, expected);
+
+    assert_eq!(serde_yaml_ng::merge_all(Vec::new()), Value::Null);
+    assert_eq!(serde_yaml_ng::merge_all([Value::from(1)]), 1);
+}
+
+#[test]
+fn test_as_null() {
+    let value: Value = serde_yaml_ng::from_str(indoc! {"
+        x: ~
+        y: 1
+    "})
+    .unwrap();
+    assert!(value.get("x").and_then(Value::as_null).is_some());
+    assert!(value.get("y").and_then(Value::as_null).is_none());
+    assert!(value.get("z").and_then(Value::as_null).is_none());
+
+    assert_eq!(Value::Null.as_null(), Some(()));
+    let others = [
+        Value::Bool(false),
+        Value::Number(Number::from(0)),
+        Value::String(String::new()),
+        Value::Sequence(Vec::new()),
+        Value::Mapping(Mapping::new()),
+        Value::Tagged(Box::new(TaggedValue {
+            tag: Tag::new("x"),
+            value: Value::from(0),
+        })),
+    ];
+    for other in &others {
+        assert_eq!(other.as_null(), None, "{:?}", other);
+    }
+}
+
+#[test]
+fn test_walk_paths_mut() {
+    let mut value: Value = serde_yaml_ng::from_str(indoc! {"
+        secret: top
+        db:
+          user: admin
+          secret: hunter2
+        services:
+          - name: a
+            secret: {nested: x}
+          - !svc
+            name: b
+            secret: y
+        keys: [secret]
+    "})
+    .unwrap();
+
+    let mut paths = Vec::new();
+    value.walk_paths_mut(|path, node| {
+        paths.push(path.to_vec());
+        if let Some(PathSegment::Key(key)) = path.last() {
+            if key == "secret" {
+                *node = Value::Null;
+            }
+        }
+    });
+
+    let expected: Value = serde_yaml_ng::from_str(indoc! {"
+        secret: null
+        db:
+          user: admin
+          secret: null
+        services:
+          - name: a
+            secret: null
+          - !svc
+            name: b
+            secret: null
+        keys: [secret]
+    "})
+    .unwrap();
+    assert_eq!(value, expected);
+
+    // The nested mapping under services[0].secret was replaced before its
+    // children were visited.
+    assert!(paths.iter().all(|path| path.len() <= 3));
+    assert_eq!(paths[0], []);
+    assert!(paths.contains(&vec![
+        PathSegment::Key(Value::from("services")),
+        PathSegment::Index(1),
+        PathSegment::Key(Value::from("name")),
+    ]));
+    assert!(paths.contains(&vec![
+        PathSegment::Key(Value::from("keys")),
+        PathSegment::Index(0),
+    ]));
+}
+
+#[test]
+fn test_stringify_keys() {
+    let mut value: Value = serde_yaml_ng::from_str(indoc! {"
+        1: one
+        2: two
+        -3: minus three
+        1.5: one and a half
+        true: yes
+        null: nothing
+        nested:
+          - 10: ten
+        ? [a, b]
+        : list
+    "})
+    .unwrap();
+    value.stringify_keys();
+
+    let expected: Value = serde_yaml_ng::from_str(indoc! {"
+        '1': one
+        '2': two
+        '-3': minus three
+        '1.5': one and a half
+        'true': yes
+        'null': nothing
+        nested:
+          - '10': ten
+        ? [a, b]
+        : list
+    "})
+    .unwrap();
+    assert_eq!(value, expected);
+    assert!(value
+        .as_mapping()
+        .unwrap()
+        .keys()
+        .take(6)
+        .all(Value::is_string));
+
+    // Keys that collide after conversion keep the first position and the
+    // last value.
+    let mut value: Value = serde_yaml_ng::from_str("{1: a, x: b, '1': c}").unwrap();
+    value.stringify_keys();
+    let expected: Value = serde_yaml_ng::from_str("{'1': c, x: b}").unwrap();
+    assert_eq!(value, expected);
+    let keys: Vec<&Value> = value.as_mapping().unwrap().keys().collect();
+    assert_eq!(keys, ["1", "x"]);
+}
+
+#[test]
+fn test_merge_explicit_null() {
+    let base: Value = serde_yaml_ng::from_str(indoc! {"
+        proxy: http://proxy:3128
+        timeout: 30
+        retries: 3
+        tls: {verify: true}
+    "})
+    .unwrap();
+    let overrides: Value = serde_yaml_ng::from_str(indoc! {"
+        proxy:
+        timeout: ~
+        tls: {verify: null}
+    "})
+    .unwrap();
+
+    let merged = serde_yaml_ng::merge_all([base, overrides]);
+    let expected: Value = serde_yaml_ng::from_str(indoc! {"
+        proxy: null
+        timeout: null
+        retries: 3
+        tls: {verify: null}
+    "})
+    .unwrap();
+    assert_eq!(merged, expected);
+    assert!(merged.get("proxy").unwrap().is_null());
+    assert_eq!(merged["retries"], 3);
+
+    // By default an empty value and an explicit null are indistinguishable
+    // once loaded.
+    let empty: Value = serde_yaml_ng::from_str("key:").unwrap();
+    let explicit: Value = serde_yaml_ng::from_str("key: null").unwrap();
+    assert_eq!(empty, explicit);
+}
+
+#[test]
+fn test_merge_empty_values() {
+    fn load(yaml: &str) -> Value {
+        let de = serde_yaml_ng::Deserializer::from_str(yaml).mark_empty_values(true);
+        Value::deserialize(de).unwrap()
+    }
+
+    let base = load(indoc! {"
+        proxy: http://proxy:3128
+        timeout: 30
+        retries: 3
+        tls: {verify: true}
+    "});
+    let overrides = load(indoc! {"
+        proxy:
+        timeout: null
+        tls:
+        extra:
+        quoted: ''
+    "});
+    assert!(overrides["proxy"].is_empty_value());
+    assert!(overrides["proxy"].is_null());
+    assert!(!overrides["timeout"].is_empty_value());
+    assert_eq!(overrides["quoted"], "");
+
+    // An explicit null overrides, while an empty value and an absent key
+    // leave the base alone.
+    let mut merged = base.clone();
+    merged.merge(overrides.clone());
+    assert_eq!(merged["proxy"], "http://proxy:3128");
+    assert!(merged["timeout"].is_null());
+    assert!(!merged["timeout"].is_empty_value());
+    assert_eq!(merged["retries"], 3);
+    assert_eq!(merged["tls"]["verify"], true);
+    assert!(merged["extra"].is_empty_value());
+
+    let mut merged = base;
+    merged.merge_with_delete(overrides.clone());
+    assert_eq!(merged["proxy"], "http://proxy:3128");
+    assert_eq!(merged.get("timeout"), None);
+
+    // The mark survives serialization.
+    let yaml = serde_yaml_ng::to_string(&overrides).unwrap();
+    assert_eq!(serde_yaml_ng::from_str::<Value>(&yaml).unwrap(), overrides);
+
+    // Types other than Value are unaffected.
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Config {
+        proxy: Option<String>,
+    }
+    let de = serde_yaml_ng::Deserializer::from_str("proxy:").mark_empty_values(true);
+    assert_eq!(Config::deserialize(de).unwrap(), Config { proxy: None });
+}
+
+#[test]
+fn test_try_get() {
+    let value: Value = serde_yaml_ng::from_str(indoc! {"
+        server:
+          host: localhost
+          ports: [80, 443]
+    "})
+    .unwrap();
+
+    let server = value.try_get("server").unwrap();
+    assert_eq!(server.try_get("host").unwrap(), "localhost");
+
+    let err = server.try_get("user").unwrap_err();
+    assert_eq!(err.to_string(), "missing key 'user' in mapping");
+
+    let ports = server.try_get("ports").unwrap();
+    let err = ports.try_get("0").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid type: sequence, expected a mapping"
+    );
+
+    let err = Value::Null.try_get("x").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid type: unit value, expected a mapping"
+    );
+}
+
+#[test]
+fn test_flatten_to_map() {
+    let value: Value = serde_yaml_ng::from_str(indoc! {"
+        db:
+          hosts: [x, y]
+          port: 5432
+          options: {}
+        debug: true
+        404: not found
+    "})
+    .unwrap();
+
+    let flat = value.flatten_to_map('.');
+    let keys: Vec<&str> = flat.keys().map(|key| key.as_str().unwrap()).collect();
+    assert_eq!(
+        keys,
+        [
+            "db.hosts.0",
+            "db.hosts.1",
+            "db.port",
+            "db.options",
+            "debug",
+            "404"
+        ],
+    );
+    assert_eq!(flat["db.hosts.0"], "x");
+    assert_eq!(flat["db.hosts.1"], "y");
+    assert_eq!(flat["db.port"], 5432);
+    assert_eq!(flat["db.options"], Value::Mapping(Mapping::new()));
+    assert_eq!(flat["debug"], true);
+    assert_eq!(flat["404"], "not found");
+
+    let flat = value.flatten_to_map('/');
+    assert_eq!(flat["db/hosts/1"], "y");
+
+    let flat = Value::from(1).flatten_to_map('.');
+    assert_eq!(flat[""], 1);
+}
+
+#[test]
+fn test_project() {
+    let config: Value = serde_yaml_ng::from_str(indoc! {"
+        server:
+          host: localhost
+          port: 8080
+          tls: {cert: a.pem, key: b.pem}
+        db:
+          user: admin
+          password: hunter2
+        replicas: [x, y, z]
+        debug: true
+    "})
+    .unwrap();
+
+    let projected = config.project(&["/server/tls/cert", "/db/user"]);
+    let expected: Value = serde_yaml_ng::from_str(indoc! {"
+        server:
+          tls: {cert: a.pem}
+        db:
+          user: admin
+    "})
+    .unwrap();
+    assert_eq!(projected, expected);
+
+    let projected = config.project(&["/server/port", "/server", "/replicas/2", "/replicas/0"]);
+    let expected: Value = serde_yaml_ng::from_str(indoc! {"
+        server:
+          host: localhost
+          port: 8080
+          tls: {cert: a.pem, key: b.pem}
+        replicas: [x, z]
+    "})
+    .unwrap();
+    assert_eq!(projected, expected);
+
+    let projected = config.project(&["/missing", "/db/user/x", "db"]);
+    assert_eq!(projected, Value::Mapping(Mapping::new()));
+    assert_eq!(config.project(&[""]), config);
+}
+
+#[test]
+fn test_merge_with_delete() {
+    let mut config: Value = serde_yaml_ng::from_str(indoc! {"
+        proxy: http://proxy:3128
+        timeout: 30
+        retries: 3
+        tls: {verify: true, ca: /etc/ca.pem}
+    "})
+    .unwrap();
+    let overlay: Value = serde_yaml_ng::from_str(indoc! {"
+        proxy:
+        timeout: 60
+        tls: {ca: ~}
+        missing: null
+        added: {x: null}
+    "})
+    .unwrap();
+
+    config.merge_with_delete(overlay);
+    let expected: Value = serde_yaml_ng::from_str(indoc! {"
+        timeout: 60
+        retries: 3
+        tls: {verify: true}
+        added: {x: null}
+    "})
+    .unwrap();
+    assert_eq!(config, expected);
+    let keys: Vec<&str> = config
+        .as_mapping()
+        .unwrap()
+        .keys()
+        .map(|key| key.as_str().unwrap())
+        .collect();
+    assert_eq!(keys, ["timeout", "retries", "tls", "added"]);
+}
+
+#[test]
+fn test_typed_accessors() {
+    let mut value: Value = serde_yaml_ng::from_str(indoc! {"
+        name: app
+        port: 8080
+        ratio: 0.5
+        debug: true
+        hosts: [a, b]
+        env: {LOG: debug}
+    "})
+    .unwrap();
+
+    assert_eq!(value["name"].as_str(), Some("app"));
+    assert_eq!(value["port"].as_i64(), Some(8080));
+    assert_eq!(value["port"].as_f64(), Some(8080.0));
+    assert_eq!(value["ratio"].as_f64(), Some(0.5));
+    assert_eq!(value["debug"].as_bool(), Some(true));
+    assert_eq!(value["hosts"].as_sequence().map(Vec::len), Some(2));
+    assert_eq!(value["env"].as_mapping().map(Mapping::len), Some(1));
+
+    assert_eq!(value["name"].as_i64(), None);
+    assert_eq!(value["ratio"].as_i64(), None);
+    assert_eq!(value["port"].as_str(), None);
+    assert_eq!(value["debug"].as_f64(), None);
+    assert_eq!(value["hosts"].as_mapping(), None);
+    assert_eq!(value["env"].as_sequence(), None);
+    assert_eq!(value["missing"].as_bool(), None);
+
+    value["hosts"]
+        .as_sequence_mut()
+        .unwrap()
+        .push(Value::from("c"));
+    assert_eq!(value["hosts"][2], "c");
+    value["env"]
+        .as_mapping_mut()
+        .unwrap()
+        .insert(Value::from("REGION"), Value::from("eu"));
+    assert_eq!(value["env"]["REGION"], "eu");
+    assert!(value["name"].as_sequence_mut().is_none());
+    assert!(value["name"].as_mapping_mut().is_none());
+}
+
+#[test]
+fn test_predicates() {
+    let value: Value = serde_yaml_ng::from_str(indoc! {"
+        - ~
+        - true
+        - 1
+        - 1.5
+        - x
+        - [a]
+        - {k: v}
+    "})
+    .unwrap();
+
+    let checks: Vec<[bool; 8]> = value
+        .as_sequence()
+        .unwrap()
+        .iter()
+        .map(|v| {
+            [
+                v.is_null(),
+                v.is_bool(),
+                v.is_i64(),
+                v.is_f64(),
+                v.is_number(),
+                v.is_string(),
+                v.is_sequence(),
+                v.is_mapping(),
+            ]
+        })
+        .collect();
+    assert_eq!(
+        checks,
+        [
+            [true, false, false, false, false, false, false, false],
+            [false, true, false, false, false, false, false, false],
+            [false, false, true, false, true, false, false, false],
+            [false, false, false, true, true, false, false, false],
+            [false, false, false, false, false, true, false, false],
+            [false, false, false, false, false, false, true, false],
+            [false, false, false, false, false, false, false, true],
+        ],
+    );
+
+    let big: Value = serde_yaml_ng::from_str("18446744073709551615").unwrap();
+    assert!(big.is_number());
+    assert!(big.is_u64());
+    assert!(!big.is_i64());
+}
+
+#[test]
+fn test_count_occurrences() {
+    let value: Value = serde_yaml_ng::from_str(indoc! {"
+        primary: db
+        replicas:
+        - {host: db, role: replica}
+        - {host: cache, role: replica}
+        db: {aliases: [db, database]}
+        tagged: !Host db
+    "})
+    .unwrap();
+
+    assert_eq!(value.count_occurrences(&Value::from("db")), 5);
+    assert_eq!(value.count_occurrences(&Value::from("replica")), 2);
+    assert_eq!(value.count_occurrences(&Value::from("missing")), 0);
+
+    let subtree: Value = serde_yaml_ng::from_str("[db, database]").unwrap();
+    assert_eq!(value.count_occurrences(&subtree), 1);
+    assert_eq!(value.count_occurrences(&value), 1);
+}
+
+#[test]
+fn test_index() {
+    let mut value: Value = serde_yaml_ng::from_str(indoc! {"
+        servers:
+        - {host: a, port: 80}
+        - {host: b, port: 443}
+    "})
+    .unwrap();
+
+    assert_eq!(value["servers"][1]["port"], 443);
+    assert_eq!(value["servers"][2]["port"], Value::Null);
+    assert_eq!(value["servers"]["host"], Value::Null);
+    assert_eq!(value["servers"][0]["host"][0], Value::Null);
+
+    value["servers"][0]["port"] = Value::from(8080);
+    assert_eq!(value["servers"][0]["port"], 8080);
+
+    let mut built = Value::Null;
+    built["db"]["host"] = Value::from("localhost");
+    assert_eq!(
+        built,
+        serde_yaml_ng::from_str::<Value>("db: {host: localhost}").unwrap(),
+    );
+}
+
+#[test]
+#[should_panic(expected = "cannot access index 2 of YAML sequence of length 2")]
+fn test_index_mut_out_of_bounds() {
+    let mut value: Value = serde_yaml_ng::from_str("[a, b]").unwrap();
+    value[2] = Value::from("c");
+}
+
+#[test]
+#[should_panic(expected = "cannot access key \"k\" in YAML sequence")]
+fn test_index_mut_wrong_type() {
+    let mut value: Value = serde_yaml_ng::from_str("[a, b]").unwrap();
+    value["k"] = Value::from("c");
+}
+
+#[test]
+fn test_get() {
+    let mut value: Value = serde_yaml_ng::from_str(indoc! {"
+        a: [x, y]
+        b: {c: 1}
+    "})
+    .unwrap();
+
+    assert_eq!(value.get("a").and_then(|v| v.get(1)).unwrap(), "y");
+    assert_eq!(value.get("a").and_then(|v| v.get(2)), None);
+    assert_eq!(value.get("missing"), None);
+    assert_eq!(value.get("a").and_then(|v| v.get("c")), None);
+    assert_eq!(value.get("b").and_then(|v| v.get(0)), None);
+    assert_eq!(value.get("b").and_then(|v| v.get("c")).unwrap(), 1);
+    assert_eq!(Value::from(1).get("a"), None);
+
+    *value.get_mut("b").and_then(|v| v.get_mut("c")).unwrap() = Value::from(2);
+    assert_eq!(value["b"]["c"], 2);
+    assert!(value.get_mut("a").and_then(|v| v.get_mut(5)).is_none());
+    assert!(value.get_mut("missing").is_none());
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_to_json_string() {
+    let value: Value = serde_yaml_ng::from_str(indoc! {r#"
+        service:
+          name: "api \"v2\""
+          replicas: 3
+          ratio: 0.25
+          big: 340282366920938463463374607431768211455
+          limits: {cpu: .inf, memory: ~}
+        ports: [80, 443]
+        2: two
+        [a, b]: pair
+        true: yes
+        tagged: !secret hunter2
+        text: "line\ttab\nnext\u0001"
+    "#})
+    .unwrap();
+    let expected = concat!(
+        r#"{"2":"two","[\"a\",\"b\"]":"pair","ports":[80,443],"#,
+        r#""service":{"big":340282366920938463463374607431768211455,"#,
+        r#""limits":{"cpu":null,"memory":null},"name":"api \"v2\"","#,
+        r#""ratio":0.25,"replicas":3},"tagged":"hunter2","#,
+        r#""text":"line\ttab\nnext\u0001","true":"yes"}"#,
+    );
+    assert_eq!(value.to_json_string().unwrap(), expected);
+
+    let value: Value = serde_yaml_ng::from_str("{1: a, '1': b}").unwrap();
+    let error = value.to_json_string().unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "duplicate key \"1\" after converting mapping keys to JSON strings",
+    );
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_json_value_conversion() {
+    let json = serde_json::json!({
+        "name": "api",
+        "replicas": 3,
+        "offset": -2,
+        "ratio": 0.25,
+        "enabled": true,
+        "owner": null,
+        "ports": [80, 443],
+        "limits": {"cpu": 1.5, "memory": "512Mi"},
+    });
+    let yaml = Value::from(json.clone());
+    let expected: Value = serde_yaml_ng::from_str(indoc! {"
+        enabled: true
+        limits: {cpu: 1.5, memory: 512Mi}
+        name: api
+        offset: -2
+        owner: null
+        ports: [80, 443]
+        ratio: 0.25
+        replicas: 3
+    "})
+    .unwrap();
+    assert_eq!(yaml, expected);
+    assert!(yaml["replicas"].is_u64());
+    assert!(yaml["offset"].is_i64());
+    assert!(yaml["ratio"].is_f64());
+    assert_eq!(serde_json::Value::try_from(yaml).unwrap(), json);
+
+    // The lossy cases.
+    let yaml: Value = serde_yaml_ng::from_str(indoc! {"
+        2: two
+        [a, b]: pair
+        tagged: !secret hunter2
+        nan: .nan
+    "})
+    .unwrap();
+    let expected = serde_json::json!({
+        "2": "two",
+        "[\"a\",\"b\"]": "pair",
+        "tagged": "hunter2",
+        "nan": null,
+    });
+    assert_eq!(serde_json::Value::try_from(yaml).unwrap(), expected);
+
+    let yaml: Value = serde_yaml_ng::from_str("{1: a, '1': b}").unwrap();
+    let error = serde_json::Value::try_from(yaml).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "duplicate key \"1\" after converting mapping keys to JSON strings",
+    );
+
+    let yaml: Value = serde_yaml_ng::from_str("[340282366920938463463374607431768211455]").unwrap();
+    let error = serde_json::Value::try_from(yaml).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "integer 340282366920938463463374607431768211455 is out of range for serde_json::Value",
+    );
+}
+
+#[test]
+fn test_dedup_sequence() {
+    let yaml = "[1, 1, a, {k: v}, {k: v}, 1, a, !t x, x, 1.0, 1]";
+
+    let mut value: Value = serde_yaml_ng::from_str(yaml).unwrap();
+    value.dedup_sequence();
+    let expected: Value = serde_yaml_ng::from_str("[1, a, {k: v}, 1, a, !t x, x, 1.0, 1]").unwrap();
+    assert_eq!(value, expected);
+
+    let mut value: Value = serde_yaml_ng::from_str(yaml).unwrap();
+    value.dedup_sequence_all();
+    let expected: Value = serde_yaml_ng::from_str("[1, a, {k: v}, !t x, x, 1.0]").unwrap();
+    assert_eq!(value, expected);
+
+    let mut value: Value = serde_yaml_ng::from_str("!set [b, b, a, b]").unwrap();
+    value.dedup_sequence_all();
+    assert_eq!(
+        value,
+        serde_yaml_ng::from_str::<Value>("!set [b, a]").unwrap()
+    );
+
+    let mut value = Value::from("a");
+    value.dedup_sequence();
+    value.dedup_sequence_all();
+    assert_eq!(value, "a");
+}
+
+#[test]
+fn test_sort_sequence_by() {
+    let mut services: Value = serde_yaml_ng::from_str(indoc! {"
+        - name: web
+          port: 80
+        - name: cache
+          port: 6379
+        - port: 22
+        - name: db
+          port: 5432
+    "})
+    .unwrap();
+    services.sort_sequence_by(|service| service["name"].as_str().map(str::to_owned));
+    let names: Vec<Option<&str>> = services
+        .as_sequence()
+        .unwrap()
+        .iter()
+        .map(|service| service["name"].as_str())
+        .collect();
+    assert_eq!(names, [None, Some("cache"), Some("db"), Some("web")]);
+
+    // Stable: equal keys keep their order.
+    let mut value: Value = serde_yaml_ng::from_str("!list [b1, a1, b2, a2]").unwrap();
+    value.sort_sequence_by(|item| item.as_str().unwrap().as_bytes()[0]);
+    assert_eq!(
+        value,
+        serde_yaml_ng::from_str::<Value>("!list [a1, a2, b1, b2]").unwrap(),
+    );
+
+    let mut value = Value::from("a");
+    value.sort_sequence_by(|_| 0);
+    assert_eq!(value, "a");
+}
+
+#[test]
+fn test_scalar_kind() {
+    let cases = [
+        ("~", Some(ScalarKind::Null)),
+        ("true", Some(ScalarKind::Bool)),
+        ("1", Some(ScalarKind::Number)),
+        ("-1", Some(ScalarKind::Number)),
+        ("1.5", Some(ScalarKind::Number)),
+        (".nan", Some(ScalarKind::Number)),
+        ("text", Some(ScalarKind::String)),
+        ("'1'", Some(ScalarKind::String)),
+        ("!t 1", Some(ScalarKind::Number)),
+        ("[1]", None),
+        ("{a: 1}", None),
+        ("!t [1]", None),
+    ];
+    for (yaml, expected) in cases {
+        let value: Value = serde_yaml_ng::from_str(yaml).unwrap();
+        assert_eq!(value.scalar_kind(), expected, "{}", yaml);
+        assert_eq!(value.is_scalar(), expected.is_some(), "{}", yaml);
+    }
+}
+
+#[test]
+fn test_mapping_preserves_order() {
+    let yaml = indoc! {"
+        zeta: 1
+        alpha: 2
+        mid: 3
+        beta: 4
+    "};
+    let value: Value = serde_yaml_ng::from_str(yaml).unwrap();
+    let keys: Vec<&str> = value
+        .as_mapping()
+        .unwrap()
+        .keys()
+        .map(|key| key.as_str().unwrap())
+        .collect();
+    assert_eq!(keys, ["zeta", "alpha", "mid", "beta"]);
+    assert_eq!(serde_yaml_ng::to_string(&value).unwrap(), yaml);
+
+    let mut mapping = value.as_mapping().unwrap().clone();
+    mapping.shift_remove("alpha");
+    mapping.insert(Value::from("alpha"), Value::from(5));
+    let entries: Vec<(String, u32)> = mapping
+        .clone()
+        .into_iter()
+        .map(|(k, v)| (k.as_str().unwrap().to_owned(), v.as_u64().unwrap() as u32))
+        .collect();
+    assert_eq!(
+        entries,
+        [("zeta", 1), ("mid", 3), ("beta", 4), ("alpha", 5)].map(|(k, v)| (k.to_owned(), v)),
+    );
+
+    // Deserializing from the Value visits the entries in order too.
+    struct Entries(Vec<(String, u32)>);
+
+    impl<'de> serde::Deserialize<'de> for Entries {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            struct EntriesVisitor;
+
+            impl<'de> serde::de::Visitor<'de> for EntriesVisitor {
+                type Value = Entries;
+
+                fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    formatter.write_str("a map")
+                }
+
+                fn visit_map<A>(self, mut map: A) -> Result<Entries, A::Error>
+                where
+                    A: serde::de::MapAccess<'de>,
+                {
+                    let mut entries = Vec::new();
+                    while let Some(entry) = map.next_entry()? {
+                        entries.push(entry);
+                    }
+                    Ok(Entries(entries))
+                }
+            }
+
+            deserializer.deserialize_map(EntriesVisitor)
+        }
+    }
+
+    let deserialized: Entries = serde_yaml_ng::from_value(Value::Mapping(mapping)).unwrap();
+    assert_eq!(deserialized.0, entries);
+}
+
+#[test]
+fn test_mapping_entry() {
+    let mut mapping = Mapping::new();
+    for word in ["a", "b", "a", "a"] {
+        let count = mapping.entry(Value::from(word)).or_insert(Value::from(0));
+        *count = Value::from(count.as_u64().unwrap() + 1);
+    }
+    assert_eq!(mapping["a"], 3);
+    assert_eq!(mapping["b"], 1);
+
+    mapping
+        .entry(Value::from("a"))
+        .and_modify(|count| *count = Value::from(10))
+        .or_insert(Value::from(0));
+    mapping
+        .entry(Value::from("c"))
+        .and_modify(|count| *count = Value::from(10))
+        .or_insert_with(|| Value::from(7));
+    assert_eq!(mapping["a"], 10);
+    assert_eq!(mapping["c"], 7);
+
+    *mapping.get_mut("b").unwrap() = Value::from("two");
+    assert_eq!(mapping["b"], "two");
+    assert!(mapping.get_mut("missing").is_none());
+
+    let keys: Vec<&str> = mapping.keys().map(|key| key.as_str().unwrap()).collect();
+    assert_eq!(keys, ["a", "b", "c"]);
+}
+
+#[test]
+fn test_mapping_try_insert() {
+    let mut mapping = Mapping::new();
+    let inserted = mapping
+        .try_insert(Value::from("a"), Value::from(1))
+        .unwrap();
+    *inserted = Value::from(2);
+    assert_eq!(mapping["a"], 2);
+
+    let (key, value) = mapping
+        .try_insert(Value::from("a"), Value::from(3))
+        .unwrap_err();
+    assert_eq!(key, "a");
+    assert_eq!(value, 3);
+    assert_eq!(mapping["a"], 2);
+    assert_eq!(mapping.len(), 1);
+}
+
+#[test]
+fn test_mapping_retain_and_remove_entry() {
+    let mut mapping: Mapping = serde_yaml_ng::from_str("a: 1\nb: 2\nc: 3\nd: 4\ne: 5\n").unwrap();
+    mapping.retain(|key, value| {
+        *value = Value::from(value.as_u64().unwrap() * 10);
+        key != "b"
+    });
+    let keys: Vec<&str> = mapping.keys().map(|key| key.as_str().unwrap()).collect();
+    assert_eq!(keys, ["a", "c", "d", "e"]);
+    assert_eq!(mapping["c"], 30);
+
+    let (key, value) = mapping.shift_remove_entry("c").unwrap();
+    assert_eq!(key, "c");
+    assert_eq!(value, 30);
+    let keys: Vec<&str> = mapping.keys().map(|key| key.as_str().unwrap()).collect();
+    assert_eq!(keys, ["a", "d", "e"]);
+
+    // `remove_entry` swaps the last entry into the removed slot.
+    let (key, value) = mapping.remove_entry(Value::from("a")).unwrap();
+    assert_eq!(key, "a");
+    assert_eq!(value, 10);
+    let keys: Vec<&str> = mapping.keys().map(|key| key.as_str().unwrap()).collect();
+    assert_eq!(keys, ["e", "d"]);
+    assert_eq!(mapping.remove_entry("a"), None);
+}
+
+#[test]
+fn test_multiline_strings_as_block_literals() {
+    // Escaped newlines, as they come in from JSON, are real newlines in the
+    // Value, which are written back out as block literals.
+    let value: Value = serde_yaml_ng::from_str(r#"{"text": "line one\nline two\n"}"#).unwrap();
+    let yaml = serde_yaml_ng::to_string(&value).unwrap();
+    assert_eq!(
+        yaml,
+        indoc! {"
+            text: |
+              line one
+              line two
+        "},
+    );
+    assert_eq!(serde_yaml_ng::from_str::<Value>(&yaml).unwrap(), value);
+}
+
+#[test]
+fn test_merge_with_options() {
+    let base: Value = serde_yaml_ng::from_str(indoc! {"
+        hosts: [a, b]
+        server:
+          ports: [80, 443]
+          name: web
+        single: x
+    "})
+    .unwrap();
+    let other: Value = serde_yaml_ng::from_str(indoc! {"
+        hosts: [b, c, c]
+        server:
+          ports: [8080, 80]
+        single: [y]
+    "})
+    .unwrap();
+
+    for (array_strategy, hosts, ports) in [
+        (ArrayStrategy::Replace, "[b, c, c]", "[8080, 80]"),
+        (
+            ArrayStrategy::Append,
+            "[a, b, b, c, c]",
+            "[80, 443, 8080, 80]",
+        ),
+        (
+            ArrayStrategy::Prepend,
+            "[b, c, c, a, b]",
+            "[8080, 80, 80, 443]",
+        ),
+        (ArrayStrategy::Unique, "[a, b, c]", "[80, 443, 8080]"),
+    ] {
+        let mut merged = base.clone();
+        merged.merge_with_options(other.clone(), &MergeOptions { array_strategy });
+        assert_eq!(
+            merged["hosts"],
+            serde_yaml_ng::from_str::<Value>(hosts).unwrap()
+        );
+        assert_eq!(
+            merged["server"]["ports"],
+            serde_yaml_ng::from_str::<Value>(ports).unwrap(),
+        );
+        assert_eq!(merged["server"]["name"], "web");
+        // A sequence replaces a scalar whatever the strategy.
+        assert_eq!(
+            merged["single"],
+            serde_yaml_ng::from_str::<Value>("[y]").unwrap()
+        );
+    }
+
+    let mut replaced = base.clone();
+    replaced.merge(other.clone());
+    let mut merged = base;
+    merged.merge_with_options(other, &MergeOptions::default());
+    assert_eq!(merged, replaced);
+}
+
+#[test]
+fn test_merge_with_options_tagged() {
+    let base: Value = serde_yaml_ng::from_str("{a: !list [x, y], b: !old [x], c: [x]}").unwrap();
+    let other: Value = serde_yaml_ng::from_str("{a: !list [y, z], b: [y], c: !new [y]}").unwrap();
+
+    for (array_strategy, a, b, c) in [
+        (
+            ArrayStrategy::Replace,
+            "!list [y, z]",
+            "!old [y]",
+            "!new [y]",
+        ),
+        (
+            ArrayStrategy::Append,
+            "!list [x, y, y, z]",
+            "!old [x, y]",
+            "!new [x, y]",
+        ),
+        (
+            ArrayStrategy::Prepend,
+            "!list [y, z, x, y]",
+            "!old [y, x]",
+            "!new [y, x]",
+        ),
+        (
+            ArrayStrategy::Unique,
+            "!list [x, y, z]",
+            "!old [x, y]",
+            "!new [x, y]",
+        ),
+    ] {
+        let mut merged = base.clone();
+        merged.merge_with_options(other.clone(), &MergeOptions { array_strategy });
+        assert_eq!(merged["a"], serde_yaml_ng::from_str::<Value>(a).unwrap());
+        assert_eq!(merged["b"], serde_yaml_ng::from_str::<Value>(b).unwrap());
+        assert_eq!(merged["c"], serde_yaml_ng::from_str::<Value>(c).unwrap());
+    }
+}
+
+#[test]
+fn test_hash() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashMap;
+    use std::hash::{Hash, Hasher};
+
+    fn hash(value: &Value) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    let pairs = [
+        ("~", "null"),
+        ("true", "True"),
+        ("10", "0xa"),
+        ("-1", "-0o1"),
+        ("1.5", "15e-1"),
+        ("0.0", "-0.0"),
+        (".nan", ".NaN"),
+        ("x", "'x'"),
+        ("[1, {a: b}]", "- 1\n- a: b"),
+        ("{a: 1, b: [c]}", "a: 1\nb:\n- c"),
+        ("!t {a: 1}", "!t\na: 1"),
+    ];
+    for (a, b) in pairs {
+        let a: Value = serde_yaml_ng::from_str(a).unwrap();
+        let b: Value = serde_yaml_ng::from_str(b).unwrap();
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b), "{:?}", a);
+    }
+
+    let mut groups = HashMap::new();
+    let records: Vec<Value> = serde_yaml_ng::from_str(indoc! {"
+        - {team: [eu, web], name: a}
+        - {team: [us, web], name: b}
+        - {team: [eu, web], name: c}
+        - {team: .nan, name: d}
+        - {team: .NAN, name: e}
+    "})
+    .unwrap();
+    for record in &records {
+        groups
+            .entry(record["team"].clone())
+            .or_insert_with(Vec::new)
+            .push(record["name"].as_str().unwrap());
+    }
+    assert_eq!(groups.len(), 3);
+    assert_eq!(
+        groups[&serde_yaml_ng::from_str::<Value>("[eu, web]").unwrap()],
+        ["a", "c"]
+    );
+    assert_eq!(groups[&Value::from(f64::NAN)], ["d", "e"]);
+}
+
+#[test]
+fn test_ord() {
+    use std::collections::BTreeSet;
+
+    let ascending = [
+        "~",
+        "false",
+        "true",
+        "-.inf",
+        "-170141183460469231731687303715884105728",
+        "-2",
+        "-1.5",
+        "-1",
+        "-1.0",
+        "0",
+        "0.0",
+        "1",
+        "1.0",
+        "1.5",
+        "18446744073709551615",
+        "18446744073709551616.0",
+        "18446744073709551617",
+        ".inf",
+        ".nan",
+        "''",
+        "a",
+        "b",
+        "[]",
+        "[a]",
+        "[a, b]",
+        "[b]",
+        "{}",
+        "{a: 1}",
+        "{a: 2}",
+        "{b: 0}",
+        "!a x",
+        "!b a",
+    ];
+    let values: Vec<Value> = ascending
+        .iter()
+        .map(|yaml| serde_yaml_ng::from_str(yaml).unwrap())
+        .collect();
+    for (i, a) in values.iter().enumerate() {
+        for (j, b) in values.iter().enumerate() {
+            assert_eq!(a.cmp(b), i.cmp(&j), "{:?} vs {:?}", a, b);
+            assert_eq!(a.partial_cmp(b), Some(i.cmp(&j)));
+        }
+    }
+
+    // Ordering agrees with equality where values are equal but spelled
+    // differently.
+    let a: Value = serde_yaml_ng::from_str("{x: 1, y: [0.0]}").unwrap();
+    let b: Value = serde_yaml_ng::from_str("{y: [-0.0], x: 1}").unwrap();
+    assert_eq!(a, b);
+    assert_eq!(a.cmp(&b), std::cmp::Ordering::Equal);
+
+    let mut reversed = values.clone();
+    reversed.reverse();
+    reversed.sort();
+    assert_eq!(reversed, values);
+
+    let set: BTreeSet<Value> = values.iter().chain(&values).cloned().collect();
+    assert!(set.into_iter().eq(values));
+}
+
+#[test]
+fn test_as_str_deserializer() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    #[serde(rename_all = "lowercase")]
+    enum Level {
+        Debug,
+        Warn,
+    }
+
+    let value: Value = serde_yaml_ng::from_str("[warn, !level debug, 3]").unwrap();
+
+    let de = value[0].as_str_deserializer().unwrap();
+    assert_eq!(<&str>::deserialize(de).unwrap(), "warn");
+    let de = value[0].as_str_deserializer().unwrap();
+    assert_eq!(String::deserialize(de).unwrap(), "warn");
+    let de = value[0].as_str_deserializer().unwrap();
+    assert_eq!(Level::deserialize(de).unwrap(), Level::Warn);
+    let de = value[1].as_str_deserializer().unwrap();
+    assert_eq!(Level::deserialize(de).unwrap(), Level::Debug);
+
+    assert!(value[2].as_str_deserializer().is_none());
+    let de = Value::from("info")
+        .as_str_deserializer()
+        .map(Level::deserialize);
+    assert_eq!(
+        de.unwrap().unwrap_err().to_string(),
+        "unknown variant `info`, expected `debug` or `warn`",
+    );
+}
+
+#[test]
+fn test_from() {
+    let value = Value::from(vec![
+        Value::from(true),
+        Value::from(-1i8),
+        Value::from(u64::MAX),
+        Value::from(u128::from(u64::MAX) + 1),
+        Value::from(i128::from(i64::MIN) - 1),
+        Value::from(usize::MAX),
+        Value::from(1.5f32),
+        Value::from("a"),
+        Value::from(String::from("b")),
+        Value::from(Some(2u16)),
+        Value::from(None::<&str>),
+        vec![1, 2].into(),
+        vec![Some("c"), None].into(),
+    ]);
+    let expected: Value = serde_yaml_ng::from_str(&format!(
+        "[true, -1, 18446744073709551615, 18446744073709551616, -9223372036854775809, {}, 1.5, a, b, 2, null, [1, 2], [c, null]]",
+        usize::MAX,
+    ))
+    .unwrap();
+    assert_eq!(value, expected);
+
+    assert_eq!(value[2].as_u64(), Some(u64::MAX));
+    assert_eq!(value[2].as_i64(), None);
+    assert_eq!(value[3].as_u64(), None);
+    assert_eq!(value[3].as_u128(), Some(u128::from(u64::MAX) + 1));
+    assert_eq!(value[4].as_i128(), Some(i128::from(i64::MIN) - 1));
+}
+
+#[test]
+fn test_validator() {
+    let config: Value = serde_yaml_ng::from_str(indoc! {"
+        server:
+          port: 8080
+          tls: yes please
+        workers: [a, b]
+        mode: fast
+    "})
+    .unwrap();
+
+    let v = config.validator();
+    let server = v.require("server");
+    assert_eq!(server.require("host").as_str(), None);
+    assert_eq!(server.require("port").as_u64(), Some(8080));
+    assert_eq!(server.optional("tls").as_bool(), None);
+    assert_eq!(server.optional("timeout").as_u64(), None);
+    assert_eq!(v.require("database").require("url").as_str(), None);
+    assert_eq!(v.require("workers").as_sequence().map(Vec::len), Some(2));
+    assert_eq!(v.require("mode").require("name").value(), None);
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    #[serde(rename_all = "lowercase")]
+    enum Mode {
+        Fast,
+    }
+    assert_eq!(v.require("mode").deserialize::<Mode>(), Some(Mode::Fast));
+    assert_eq!(v.require("workers").deserialize::<Vec<u8>>(), None);
+
+    let errors: Vec<String> = v
+        .finish()
+        .unwrap_err()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            "server: missing field `host`",
+            "server.tls: invalid type: string \"yes please\", expected a boolean",
+            "missing field `database`",
+            "mode: invalid type: string \"fast\", expected a mapping",
+            "workers: invalid type: string \"a\", expected u8",
+        ],
+    );
+
+    let v = config.validator();
+    assert_eq!(v.require("server").require("port").as_i64(), Some(8080));
+    assert!(v.finish().is_ok());
+}
+
+#[test]
+fn test_replace_if() {
+    let mut value: Value = serde_yaml_ng::from_str(indoc! {"
+        services:
+          web: {image: nginx}
+          legacy: {image: httpd, _delete: true}
+        jobs:
+        - {name: a, _delete: false}
+        - {name: b, _delete: true}
+        - !cron {name: c, _delete: true}
+    "})
+    .unwrap();
+    value.replace_if(
+        |node| node.get("_delete") == Some(&Value::from(true)),
+        |_| Value::Null,
+    );
+    let expected: Value = serde_yaml_ng::from_str(indoc! {"
+        services:
+          web: {image: nginx}
+          legacy: null
+        jobs:
+        - {name: a, _delete: false}
+        - null
+        - null
+    "})
+    .unwrap();
+    assert_eq!(value, expected);
+
+    // The children of a replacement are visited, whatever its shape.
+    let mut value: Value = serde_yaml_ng::from_str("[split, [x, split]]").unwrap();
+    let mut calls = 0;
+    value.replace_if(
+        |node| {
+            calls += 1;
+            node == "split"
+        },
+        |_| serde_yaml_ng::from_str("{a: 1, b: split-done}").unwrap(),
+    );
+    let expected: Value =
+        serde_yaml_ng::from_str("[{a: 1, b: split-done}, [x, {a: 1, b: split-done}]]").unwrap();
+    assert_eq!(value, expected);
+    assert_eq!(calls, 9);
+}
+
+#[test]
+fn test_tag() {
+    let yaml = indoc! {"
+        Bucket: !Ref MyBucket
+        Arn: !GetAtt
+        - MyBucket
+        - Arn
+        Name: !Sub
+          Prefix: app
+        Plain: value
+    "};
+    let value: Value = serde_yaml_ng::from_str(yaml).unwrap();
+    assert_eq!(value["Bucket"].tag().unwrap(), "Ref");
+    assert_eq!(value["Bucket"], "MyBucket");
+    assert_eq!(value["Arn"].tag().unwrap(), "!GetAtt");
+    assert_eq!(value["Arn"][1], "Arn");
+    assert_eq!(value["Name"].tag().unwrap(), "Sub");
+    assert_eq!(value["Plain"].tag(), None);
+    assert_eq!(value.tag(), None);
+    assert_eq!(serde_yaml_ng::to_string(&value).unwrap(), yaml);
+}